@@ -4,9 +4,18 @@ pub use async_trait::async_trait;
 pub use error::DatabaseError;
 
 use synx_domain::{
+    api_key::ApiKey,
+    blob::Blob,
     embedding::Embedding,
-    message::{CreateMessage, Message, ThreadMessagesResponse, UpdateMessage},
-    thread::{Thread, UpdateThread},
+    job::Job,
+    message::{
+        CreateMessage, ListMessages, Message, MessageRevision, ThreadMessagesResponse,
+        UpdateMessage,
+    },
+    metadata::Metadata,
+    tenant::Tenant,
+    thread::{ListThreads, ListThreadsResponse, SummaryCursor, Thread, UpdateThread},
+    webhook::{Webhook, WebhookDelivery},
 };
 use uuid::Uuid;
 
@@ -16,49 +25,216 @@ pub trait Db: Send + Sync {
 
     async fn get_threads_with_embeddings(
         &self,
+        tenant_id: Uuid,
         thread_ids: &[Uuid],
     ) -> Result<Vec<Thread>, DatabaseError>;
 
+    /// Returns up to `limit` threads whose metadata matches every entry of `metadata`.
+    async fn search_thread_embeddings(
+        &self,
+        tenant_id: Uuid,
+        embedding: &Embedding,
+        thread_ids: Option<&[Uuid]>,
+        metadata: &Metadata,
+        limit: usize,
+    ) -> Result<Vec<(Thread, f32)>, DatabaseError>;
+
+    /// Replaces the summary only if the thread's summary cursor still equals
+    /// `expected_cursor`, returning `DatabaseError::Conflict` otherwise.
     async fn update_thread_summary_and_embedding(
         &self,
+        tenant_id: Uuid,
         thread_id: Uuid,
+        expected_cursor: Option<SummaryCursor>,
+        cursor: SummaryCursor,
         summary: String,
         embedding: Embedding,
     ) -> Result<(), DatabaseError>;
 
-    async fn create_thread(&self) -> Result<Thread, DatabaseError>;
+    async fn search_message_embeddings(
+        &self,
+        tenant_id: Uuid,
+        embedding: &Embedding,
+        thread_ids: Option<&[Uuid]>,
+        limit: usize,
+    ) -> Result<Vec<(Message, f32)>, DatabaseError>;
+
+    /// Stores the embedding of a message, or drops it when the message has no text left.
+    async fn update_message_embedding(
+        &self,
+        tenant_id: Uuid,
+        thread_id: Uuid,
+        message_id: Uuid,
+        embedding: Option<Embedding>,
+    ) -> Result<(), DatabaseError>;
 
-    async fn delete_thread(&self, thread_id: Uuid) -> Result<(), DatabaseError>;
+    async fn create_thread(&self, tenant_id: Uuid) -> Result<Thread, DatabaseError>;
 
+    async fn delete_thread(&self, tenant_id: Uuid, thread_id: Uuid) -> Result<(), DatabaseError>;
+
+    /// Blobs the content refers to must exist; each reference is counted.
     async fn create_message(
         &self,
+        tenant_id: Uuid,
         thread_id: Uuid,
         input: CreateMessage,
     ) -> Result<Message, DatabaseError>;
 
+    /// Writes whole threads with their messages as given, in one transaction.
+    /// Blobs the contents refer to must exist; each reference is counted.
+    async fn import_threads(
+        &self,
+        threads: Vec<(Thread, Vec<Message>)>,
+    ) -> Result<(), DatabaseError>;
+
+    /// Replaces the content, keeping the previous one as the message's next revision.
     async fn update_message(
         &self,
+        tenant_id: Uuid,
         thread_id: Uuid,
         message_id: Uuid,
         content: UpdateMessage,
     ) -> Result<Message, DatabaseError>;
 
+    /// Previous contents of the message, oldest first.
+    async fn list_message_revisions(
+        &self,
+        tenant_id: Uuid,
+        thread_id: Uuid,
+        message_id: Uuid,
+    ) -> Result<Vec<MessageRevision>, DatabaseError>;
+
+    async fn get_message_revision(
+        &self,
+        tenant_id: Uuid,
+        thread_id: Uuid,
+        message_id: Uuid,
+        revision: u32,
+    ) -> Result<MessageRevision, DatabaseError>;
+
     async fn update_thread(
         &self,
+        tenant_id: Uuid,
         thread_id: Uuid,
         update: UpdateThread,
     ) -> Result<Thread, DatabaseError>;
 
-    async fn list_threads(&self) -> Result<Vec<Thread>, DatabaseError>;
+    /// One page of the threads passing the query's filters, in the requested order.
+    async fn list_threads(
+        &self,
+        tenant_id: Uuid,
+        query: &ListThreads,
+    ) -> Result<ListThreadsResponse, DatabaseError>;
 
-    async fn get_thread(&self, thread_id: Uuid) -> Result<Thread, DatabaseError>;
+    async fn get_thread(&self, tenant_id: Uuid, thread_id: Uuid) -> Result<Thread, DatabaseError>;
 
+    /// Messages of the thread within the query's range, in the requested order.
+    /// `total` counts all messages of the thread.
     async fn get_thread_messages(
         &self,
+        tenant_id: Uuid,
         thread_id: Uuid,
-        limit: Option<usize>,
-        offset: Option<usize>,
+        query: &ListMessages,
     ) -> Result<ThreadMessagesResponse, DatabaseError>;
 
-    async fn delete_message(&self, thread_id: Uuid, message_id: Uuid) -> Result<(), DatabaseError>;
+    async fn delete_message(
+        &self,
+        tenant_id: Uuid,
+        thread_id: Uuid,
+        message_id: Uuid,
+    ) -> Result<(), DatabaseError>;
+
+    async fn get_message(
+        &self,
+        tenant_id: Uuid,
+        thread_id: Uuid,
+        message_id: Uuid,
+    ) -> Result<Message, DatabaseError>;
+
+    /// Stores the blob unless the tenant has one with the same hash, in which case
+    /// only its upload time is refreshed, and returns the stored one. Each tenant
+    /// keeps its own blobs, referenced only by its messages. Dropping the last
+    /// reference removes a blob only if it was last uploaded before
+    /// [`Blob::collection_cutoff`].
+    async fn put_blob(
+        &self,
+        tenant_id: Uuid,
+        blob: Blob,
+        data: Vec<u8>,
+    ) -> Result<Blob, DatabaseError>;
+
+    async fn get_blob(&self, tenant_id: Uuid, hash: &str)
+        -> Result<(Blob, Vec<u8>), DatabaseError>;
+
+    /// Removes the blobs of every tenant with no reference left that were last
+    /// uploaded before `uploaded_before`, returning how many were removed.
+    async fn delete_unreferenced_blobs(&self, uploaded_before: u64) -> Result<u64, DatabaseError>;
+
+    /// Inserts the job, or replaces it if a job with the same id already exists.
+    async fn save_job(&self, job: &Job) -> Result<(), DatabaseError>;
+
+    async fn delete_job(
+        &self,
+        tenant_id: Uuid,
+        thread_id: Uuid,
+        job_id: Uuid,
+    ) -> Result<(), DatabaseError>;
+
+    async fn get_pending_jobs(&self) -> Result<Vec<Job>, DatabaseError>;
+
+    async fn get_thread_jobs(
+        &self,
+        tenant_id: Uuid,
+        thread_id: Uuid,
+    ) -> Result<Vec<Job>, DatabaseError>;
+
+    /// Inserts the webhook, or replaces it if one with the same id already exists;
+    /// fails with `NotFound` unless its tenant exists.
+    async fn save_webhook(&self, webhook: &Webhook) -> Result<(), DatabaseError>;
+
+    async fn get_webhook(
+        &self,
+        tenant_id: Uuid,
+        webhook_id: Uuid,
+    ) -> Result<Webhook, DatabaseError>;
+
+    /// Oldest first.
+    async fn list_webhooks(&self, tenant_id: Uuid) -> Result<Vec<Webhook>, DatabaseError>;
+
+    /// Removes the webhook together with its deliveries.
+    async fn delete_webhook(&self, tenant_id: Uuid, webhook_id: Uuid) -> Result<(), DatabaseError>;
+
+    /// Inserts or replaces the delivery; fails with `NotFound` once its webhook is gone.
+    async fn save_webhook_delivery(&self, delivery: &WebhookDelivery) -> Result<(), DatabaseError>;
+
+    /// Oldest first.
+    async fn list_webhook_deliveries(
+        &self,
+        tenant_id: Uuid,
+        webhook_id: Uuid,
+    ) -> Result<Vec<WebhookDelivery>, DatabaseError>;
+
+    async fn get_pending_webhook_deliveries(&self) -> Result<Vec<WebhookDelivery>, DatabaseError>;
+
+    /// Inserts the tenant, or replaces it if one with the same id already exists.
+    async fn save_tenant(&self, tenant: &Tenant) -> Result<(), DatabaseError>;
+
+    async fn get_tenant(&self, tenant_id: Uuid) -> Result<Tenant, DatabaseError>;
+
+    /// Oldest first.
+    async fn list_tenants(&self) -> Result<Vec<Tenant>, DatabaseError>;
+
+    /// Removes the tenant with its API keys, webhooks and their deliveries, and
+    /// its blobs; fails with `Conflict` while the tenant still owns threads.
+    async fn delete_tenant(&self, tenant_id: Uuid) -> Result<(), DatabaseError>;
+
+    /// Inserts or replaces the key; fails with `NotFound` unless its tenant exists.
+    async fn save_api_key(&self, api_key: &ApiKey) -> Result<(), DatabaseError>;
+
+    async fn get_api_key_by_hash(&self, key_hash: &str) -> Result<ApiKey, DatabaseError>;
+
+    /// Oldest first.
+    async fn list_api_keys(&self, tenant_id: Uuid) -> Result<Vec<ApiKey>, DatabaseError>;
+
+    async fn delete_api_key(&self, tenant_id: Uuid, key_id: Uuid) -> Result<(), DatabaseError>;
 }
@@ -4,6 +4,8 @@ use thiserror::Error;
 pub enum DatabaseError {
     #[error("Item not found")]
     NotFound,
+    #[error("Conflict: {0}")]
+    Conflict(String),
     #[error("Connection error: {0}")]
     ConnectionError(String),
     #[error("Query error: {0}")]
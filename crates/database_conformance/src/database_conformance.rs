@@ -0,0 +1,2331 @@
+//! Behaviour every [`Db`] implementation is expected to share.
+//!
+//! Backends run the whole suite from an integration test with
+//! [`db_conformance_tests!`], passing an expression that builds a fresh, empty
+//! [`DbFixture`] for each case.
+
+use std::{collections::HashSet, sync::Arc, time::Duration};
+
+pub use synx_database::Db;
+
+use serde_json::json;
+use synx_database::DatabaseError;
+use synx_domain::{
+    api_key::{ApiKey, Scope},
+    blob::Blob,
+    branch::MessageTree,
+    content::Content,
+    embedding::Embedding,
+    import::{ImportedMessage, ImportedThread},
+    job::{Job, JobKind, JobStatus},
+    message::{CreateMessage, ListMessages, Message, Role, UpdateMessage},
+    metadata::Metadata,
+    tenant::Tenant,
+    thread::{
+        ListThreads, ListThreadsResponse, SortOrder, SummaryCursor, ThreadSort, UpdateThread,
+    },
+    webhook::{CreateWebhook, DeliveryStatus, WebhookDelivery},
+};
+use uuid::Uuid;
+
+/// Tenant every case works in, unless it is about tenants.
+const TENANT: Uuid = Tenant::DEFAULT_ID;
+
+#[macro_export]
+macro_rules! db_conformance_tests {
+    ($make_db:expr) => {
+        $crate::db_conformance_tests!(
+            @cases $make_db;
+            thread_crud,
+            thread_not_found,
+            thread_pagination,
+            message_crud,
+            message_not_found,
+            timestamps,
+            message_revisions,
+            message_branches,
+            content_kinds,
+            blobs,
+            roles,
+            import_threads,
+            messages_are_chronological,
+            message_pagination,
+            message_cursors,
+            delete_message_updates_thread,
+            delete_thread_cascades,
+            thread_embeddings_are_not_invented,
+            summary_updates_compare_cursor,
+            thread_search,
+            metadata,
+            message_search,
+            jobs,
+            webhooks,
+            tenants,
+            concurrent_message_creation,
+            concurrent_summary_updates,
+        );
+    };
+    (@cases $make_db:expr; $($case:ident),* $(,)?) => {
+        $(
+            #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+            async fn $case() {
+                let fixture = $make_db;
+                $crate::$case($crate::DbFixture::db(&fixture)).await;
+            }
+        )*
+    };
+}
+
+/// A database under test, together with whatever must outlive it (e.g. a temporary directory).
+pub trait DbFixture {
+    fn db(&self) -> Arc<dyn Db>;
+}
+
+impl<T: Db + 'static> DbFixture for Arc<T> {
+    fn db(&self) -> Arc<dyn Db> {
+        self.clone()
+    }
+}
+
+impl<T: Db + 'static, G> DbFixture for (Arc<T>, G) {
+    fn db(&self) -> Arc<dyn Db> {
+        self.0.clone()
+    }
+}
+
+fn text(text: &str) -> Content {
+    Content::from(text.to_string())
+}
+
+fn create_message_input(content: &str) -> CreateMessage {
+    CreateMessage {
+        role: Role::User,
+        content: text(content),
+        metadata: Metadata::new(),
+        parent_id: None,
+    }
+}
+
+fn texts(messages: &[Message]) -> Vec<String> {
+    messages
+        .iter()
+        .map(|message| message.content.to_string())
+        .collect()
+}
+
+fn embedding(vector: &[f32]) -> Embedding {
+    Embedding::from(vector.to_vec())
+}
+
+fn cursor(message: &Message) -> SummaryCursor {
+    SummaryCursor::from(message)
+}
+
+fn assert_not_found<T: std::fmt::Debug>(result: Result<T, DatabaseError>) {
+    match result {
+        Err(DatabaseError::NotFound) => {}
+        other => panic!("expected NotFound, got {:?}", other),
+    }
+}
+
+async fn create_messages(db: &Arc<dyn Db>, thread_id: Uuid, contents: &[&str]) -> Vec<Message> {
+    let mut messages = Vec::with_capacity(contents.len());
+    for content in contents {
+        messages.push(
+            db.create_message(TENANT, thread_id, create_message_input(content))
+                .await
+                .unwrap(),
+        );
+    }
+    messages
+}
+
+pub async fn thread_crud(db: Arc<dyn Db>) {
+    let thread = db.create_thread(TENANT).await.unwrap();
+    assert!(thread.title.is_none());
+    assert!(thread.summary.is_none());
+
+    let fetched = db.get_thread(TENANT, thread.id()).await.unwrap();
+    assert_eq!(fetched.id(), thread.id());
+
+    let updated = db
+        .update_thread(
+            TENANT,
+            thread.id(),
+            UpdateThread {
+                title: Some("Title".to_string()),
+                metadata: Metadata::new(),
+            },
+        )
+        .await
+        .unwrap();
+    assert_eq!(updated.title.as_deref(), Some("Title"));
+    assert_eq!(
+        db.get_thread(TENANT, thread.id())
+            .await
+            .unwrap()
+            .title
+            .as_deref(),
+        Some("Title")
+    );
+
+    let other = db.create_thread(TENANT).await.unwrap();
+    let listed: HashSet<Uuid> = db
+        .list_threads(TENANT, &ListThreads::default())
+        .await
+        .unwrap()
+        .threads
+        .iter()
+        .map(|thread| thread.id())
+        .collect();
+    assert_eq!(listed, HashSet::from([thread.id(), other.id()]));
+
+    db.delete_thread(TENANT, thread.id()).await.unwrap();
+    assert_not_found(db.get_thread(TENANT, thread.id()).await);
+    let listed: Vec<Uuid> = db
+        .list_threads(TENANT, &ListThreads::default())
+        .await
+        .unwrap()
+        .threads
+        .iter()
+        .map(|thread| thread.id())
+        .collect();
+    assert_eq!(listed, vec![other.id()]);
+}
+
+/// Follows `next_cursor` until the listing is exhausted, checking every page.
+async fn list_all_threads(db: &Arc<dyn Db>, mut query: ListThreads) -> Vec<Uuid> {
+    let mut ids = Vec::new();
+    loop {
+        let page = db.list_threads(TENANT, &query).await.unwrap();
+        assert!(page.threads.len() <= page.limit);
+        ids.extend(page.threads.iter().map(|thread| thread.id()));
+        match page.next_cursor {
+            Some(cursor) => {
+                assert_eq!(page.threads.len(), page.limit);
+                query.cursor = Some(cursor);
+            }
+            None => return ids,
+        }
+    }
+}
+
+pub async fn thread_pagination(db: Arc<dyn Db>) {
+    let mut threads = Vec::new();
+    for _ in 0..5 {
+        threads.push(db.create_thread(TENANT).await.unwrap());
+        tokio::time::sleep(Duration::from_millis(2)).await;
+    }
+    let ids: Vec<Uuid> = threads.iter().map(|thread| thread.id()).collect();
+    let newest_first: Vec<Uuid> = ids.iter().rev().copied().collect();
+
+    let first_page = db
+        .list_threads(
+            TENANT,
+            &ListThreads {
+                limit: Some(2),
+                ..ListThreads::default()
+            },
+        )
+        .await
+        .unwrap();
+    assert_eq!(first_page.total, 5);
+    assert_eq!(first_page.limit, 2);
+    assert_eq!(
+        first_page.next_cursor,
+        Some(threads[3].cursor(ThreadSort::CreatedAt))
+    );
+
+    let ascending = ListThreads {
+        order: SortOrder::Asc,
+        limit: Some(2),
+        ..ListThreads::default()
+    };
+    assert_eq!(list_all_threads(&db, ascending.clone()).await, ids);
+    let descending = ListThreads {
+        limit: Some(2),
+        ..ListThreads::default()
+    };
+    assert_eq!(
+        list_all_threads(&db, descending.clone()).await,
+        newest_first
+    );
+    assert_eq!(
+        list_all_threads(&db, ListThreads::default()).await,
+        newest_first
+    );
+
+    let created_between = ListThreads {
+        created_after: Some(threads[1].created_at),
+        created_before: Some(threads[4].created_at),
+        ..ascending.clone()
+    };
+    assert_eq!(
+        db.list_threads(TENANT, &created_between)
+            .await
+            .unwrap()
+            .total,
+        2
+    );
+    assert_eq!(
+        list_all_threads(&db, created_between.clone()).await,
+        vec![ids[2], ids[3]]
+    );
+
+    // A new message makes the oldest thread the most recently active one.
+    let message = db
+        .create_message(TENANT, ids[0], create_message_input("hello"))
+        .await
+        .unwrap();
+    let thread = db.get_thread(TENANT, ids[0]).await.unwrap();
+    assert_eq!(thread.last_message_at, Some(message.created_at));
+    assert_eq!(thread.last_activity_at(), message.created_at);
+    let by_activity = ListThreads {
+        sort: ThreadSort::LastActivity,
+        ..descending.clone()
+    };
+    assert_eq!(
+        list_all_threads(&db, by_activity.clone()).await,
+        vec![ids[0], ids[4], ids[3], ids[2], ids[1]]
+    );
+    assert_eq!(
+        list_all_threads(
+            &db,
+            ListThreads {
+                created_after: Some(threads[0].created_at),
+                ..by_activity.clone()
+            }
+        )
+        .await,
+        vec![ids[4], ids[3], ids[2], ids[1]]
+    );
+    // The creation order is unaffected.
+    assert_eq!(list_all_threads(&db, ascending.clone()).await, ids);
+
+    db.delete_message(TENANT, ids[0], message.id())
+        .await
+        .unwrap();
+    assert_eq!(
+        db.get_thread(TENANT, ids[0]).await.unwrap().last_message_at,
+        None
+    );
+    assert_eq!(list_all_threads(&db, by_activity).await, newest_first);
+
+    let tagged: Metadata = serde_json::from_value(json!({"tag": "x"})).unwrap();
+    for &id in [ids[1], ids[3]].iter() {
+        db.update_thread(
+            TENANT,
+            id,
+            UpdateThread {
+                title: None,
+                metadata: tagged.clone(),
+            },
+        )
+        .await
+        .unwrap();
+    }
+    let tagged_page = ListThreads {
+        metadata: tagged,
+        limit: Some(1),
+        ..ascending.clone()
+    };
+    assert_eq!(
+        db.list_threads(TENANT, &tagged_page).await.unwrap().total,
+        2
+    );
+    assert_eq!(
+        list_all_threads(&db, tagged_page).await,
+        vec![ids[1], ids[3]]
+    );
+
+    // Cursors stay valid when the thread they point at is deleted.
+    let page = db.list_threads(TENANT, &ascending).await.unwrap();
+    db.delete_thread(TENANT, ids[1]).await.unwrap();
+    let rest = db
+        .list_threads(
+            TENANT,
+            &ListThreads {
+                cursor: page.next_cursor,
+                ..ascending
+            },
+        )
+        .await
+        .unwrap();
+    assert_eq!(rest.total, 4);
+    assert_eq!(
+        rest.threads
+            .iter()
+            .map(|thread| thread.id())
+            .collect::<Vec<_>>(),
+        vec![ids[2], ids[3]]
+    );
+}
+
+pub async fn thread_not_found(db: Arc<dyn Db>) {
+    let missing = Uuid::new_v4();
+
+    assert_not_found(db.get_thread(TENANT, missing).await);
+    assert_not_found(db.delete_thread(TENANT, missing).await);
+    assert_not_found(
+        db.update_thread(
+            TENANT,
+            missing,
+            UpdateThread {
+                title: None,
+                metadata: Metadata::new(),
+            },
+        )
+        .await,
+    );
+    assert_not_found(
+        db.get_thread_messages(TENANT, missing, &ListMessages::default())
+            .await,
+    );
+    assert_not_found(
+        db.create_message(TENANT, missing, create_message_input("hello"))
+            .await,
+    );
+    assert_not_found(db.get_thread_jobs(TENANT, missing).await);
+    assert_not_found(
+        db.save_job(&Job::new(
+            TENANT,
+            missing,
+            JobKind::Summarize {
+                message_id: Uuid::new_v4(),
+            },
+        ))
+        .await,
+    );
+    assert_not_found(
+        db.update_thread_summary_and_embedding(
+            TENANT,
+            missing,
+            None,
+            SummaryCursor {
+                created_at: 0,
+                message_id: Uuid::new_v4(),
+            },
+            "summary".to_string(),
+            embedding(&[1.0, 0.0]),
+        )
+        .await,
+    );
+
+    let thread = db.create_thread(TENANT).await.unwrap();
+    db.delete_thread(TENANT, thread.id()).await.unwrap();
+    assert_not_found(db.delete_thread(TENANT, thread.id()).await);
+}
+
+pub async fn message_crud(db: Arc<dyn Db>) {
+    let thread = db.create_thread(TENANT).await.unwrap();
+    let message = db
+        .create_message(TENANT, thread.id(), create_message_input("hello"))
+        .await
+        .unwrap();
+    assert_eq!(message.thread_id, thread.id());
+    assert_eq!(message.role, Role::User);
+
+    let fetched = db
+        .get_message(TENANT, thread.id(), message.id())
+        .await
+        .unwrap();
+    assert_eq!(fetched.id(), message.id());
+    assert_eq!(fetched.content.to_string(), "hello");
+    assert_eq!(fetched.created_at, message.created_at);
+
+    let updated = db
+        .update_message(
+            TENANT,
+            thread.id(),
+            message.id(),
+            UpdateMessage {
+                content: text("hello again"),
+            },
+        )
+        .await
+        .unwrap();
+    assert_eq!(updated.content.to_string(), "hello again");
+    assert_eq!(updated.created_at, message.created_at);
+    assert_eq!(
+        db.get_message(TENANT, thread.id(), message.id())
+            .await
+            .unwrap()
+            .content
+            .to_string(),
+        "hello again"
+    );
+
+    db.delete_message(TENANT, thread.id(), message.id())
+        .await
+        .unwrap();
+    assert_not_found(db.get_message(TENANT, thread.id(), message.id()).await);
+}
+
+pub async fn message_not_found(db: Arc<dyn Db>) {
+    let thread = db.create_thread(TENANT).await.unwrap();
+    let other = db.create_thread(TENANT).await.unwrap();
+    let message = db
+        .create_message(TENANT, thread.id(), create_message_input("hello"))
+        .await
+        .unwrap();
+    let missing = Uuid::new_v4();
+
+    assert_not_found(db.get_message(TENANT, thread.id(), missing).await);
+    assert_not_found(db.delete_message(TENANT, thread.id(), missing).await);
+    assert_not_found(
+        db.update_message(
+            TENANT,
+            thread.id(),
+            missing,
+            UpdateMessage {
+                content: text("nope"),
+            },
+        )
+        .await,
+    );
+    assert_not_found(
+        db.update_message_embedding(TENANT, thread.id(), missing, Some(embedding(&[1.0, 0.0])))
+            .await,
+    );
+
+    // A message is only reachable through the thread it belongs to.
+    assert_not_found(db.get_message(TENANT, other.id(), message.id()).await);
+    assert_not_found(db.delete_message(TENANT, other.id(), message.id()).await);
+    assert_not_found(
+        db.update_message(
+            TENANT,
+            other.id(),
+            message.id(),
+            UpdateMessage {
+                content: text("nope"),
+            },
+        )
+        .await,
+    );
+    assert_not_found(
+        db.update_message_embedding(
+            TENANT,
+            other.id(),
+            message.id(),
+            Some(embedding(&[1.0, 0.0])),
+        )
+        .await,
+    );
+
+    let fetched = db
+        .get_message(TENANT, thread.id(), message.id())
+        .await
+        .unwrap();
+    assert_eq!(fetched.content.to_string(), "hello");
+}
+
+pub async fn timestamps(db: Arc<dyn Db>) {
+    let thread = db.create_thread(TENANT).await.unwrap();
+    assert!(thread.created_at > 0);
+    assert_eq!(thread.updated_at, None);
+    assert_eq!(thread.last_message_at, None);
+
+    let messages = create_messages(&db, thread.id(), &["a", "b"]).await;
+    assert_eq!(messages[0].updated_at, None);
+    let fetched = db.get_thread(TENANT, thread.id()).await.unwrap();
+    assert_eq!(fetched.last_message_at, Some(messages[1].created_at));
+    assert_eq!(
+        fetched.updated_at, None,
+        "new messages do not update the thread itself"
+    );
+
+    tokio::time::sleep(Duration::from_millis(2)).await;
+    let updated = db
+        .update_thread(
+            TENANT,
+            thread.id(),
+            UpdateThread {
+                title: Some("Title".to_string()),
+                metadata: Metadata::new(),
+            },
+        )
+        .await
+        .unwrap();
+    let updated_at = updated.updated_at.expect("update_thread sets updated_at");
+    assert!(updated_at > thread.created_at);
+    let fetched = db.get_thread(TENANT, thread.id()).await.unwrap();
+    assert_eq!(fetched.created_at, thread.created_at);
+    assert_eq!(fetched.updated_at, Some(updated_at));
+    assert_eq!(fetched.last_message_at, Some(messages[1].created_at));
+
+    let edited = db
+        .update_message(
+            TENANT,
+            thread.id(),
+            messages[0].id(),
+            UpdateMessage {
+                content: text("edited"),
+            },
+        )
+        .await
+        .unwrap();
+    let edited_at = edited.updated_at.expect("update_message sets updated_at");
+    assert!(edited_at >= messages[0].created_at);
+    let fetched = db
+        .get_message(TENANT, thread.id(), messages[0].id())
+        .await
+        .unwrap();
+    assert_eq!(fetched.created_at, messages[0].created_at);
+    assert_eq!(fetched.updated_at, Some(edited_at));
+    let listed = db
+        .get_thread_messages(TENANT, thread.id(), &ListMessages::default())
+        .await
+        .unwrap();
+    assert_eq!(listed.messages[0].updated_at, Some(edited_at));
+    assert_eq!(listed.messages[1].updated_at, None);
+    assert_eq!(
+        db.get_thread(TENANT, thread.id())
+            .await
+            .unwrap()
+            .last_message_at,
+        Some(messages[1].created_at),
+        "editing a message does not move the thread's last message time"
+    );
+
+    db.delete_message(TENANT, thread.id(), messages[1].id())
+        .await
+        .unwrap();
+    assert_eq!(
+        db.get_thread(TENANT, thread.id())
+            .await
+            .unwrap()
+            .last_message_at,
+        Some(messages[0].created_at)
+    );
+}
+
+pub async fn message_revisions(db: Arc<dyn Db>) {
+    let thread = db.create_thread(TENANT).await.unwrap();
+    let messages = create_messages(&db, thread.id(), &["v1", "other"]).await;
+    let message = &messages[0];
+    assert!(db
+        .list_message_revisions(TENANT, thread.id(), message.id())
+        .await
+        .unwrap()
+        .is_empty());
+
+    for content in ["v2", "v3"] {
+        db.update_message(
+            TENANT,
+            thread.id(),
+            message.id(),
+            UpdateMessage {
+                content: text(content),
+            },
+        )
+        .await
+        .unwrap();
+    }
+
+    let revisions = db
+        .list_message_revisions(TENANT, thread.id(), message.id())
+        .await
+        .unwrap();
+    let numbered: Vec<(u32, String)> = revisions
+        .iter()
+        .map(|revision| (revision.revision, revision.content.to_string()))
+        .collect();
+    assert_eq!(numbered, [(1, "v1".to_string()), (2, "v2".to_string())]);
+    assert_eq!(revisions[0].message_id, message.id());
+    assert_eq!(revisions[0].created_at, message.created_at);
+    assert_eq!(revisions[1].created_at, revisions[0].replaced_at);
+    assert_eq!(
+        db.get_message(TENANT, thread.id(), message.id())
+            .await
+            .unwrap()
+            .updated_at,
+        Some(revisions[1].replaced_at)
+    );
+
+    let second = db
+        .get_message_revision(TENANT, thread.id(), message.id(), 2)
+        .await
+        .unwrap();
+    assert_eq!(second.content.to_string(), "v2");
+    assert_not_found(
+        db.get_message_revision(TENANT, thread.id(), message.id(), 3)
+            .await,
+    );
+    assert_not_found(
+        db.get_message_revision(TENANT, thread.id(), message.id(), 0)
+            .await,
+    );
+    assert!(db
+        .list_message_revisions(TENANT, thread.id(), messages[1].id())
+        .await
+        .unwrap()
+        .is_empty());
+
+    let other_thread = db.create_thread(TENANT).await.unwrap();
+    assert_not_found(
+        db.list_message_revisions(TENANT, other_thread.id(), message.id())
+            .await,
+    );
+    assert_not_found(
+        db.get_message_revision(TENANT, other_thread.id(), message.id(), 1)
+            .await,
+    );
+
+    db.delete_message(TENANT, thread.id(), message.id())
+        .await
+        .unwrap();
+    assert_not_found(
+        db.list_message_revisions(TENANT, thread.id(), message.id())
+            .await,
+    );
+    assert_not_found(
+        db.get_message_revision(TENANT, thread.id(), message.id(), 1)
+            .await,
+    );
+
+    db.update_message(
+        TENANT,
+        thread.id(),
+        messages[1].id(),
+        UpdateMessage {
+            content: text("edited"),
+        },
+    )
+    .await
+    .unwrap();
+    db.delete_thread(TENANT, thread.id()).await.unwrap();
+    assert_not_found(
+        db.list_message_revisions(TENANT, thread.id(), messages[1].id())
+            .await,
+    );
+}
+
+pub async fn message_branches(db: Arc<dyn Db>) {
+    let thread = db.create_thread(TENANT).await.unwrap();
+    let first = create_messages(&db, thread.id(), &["question", "answer"]).await;
+    let reply = |content: &str, parent_id: Uuid| CreateMessage {
+        parent_id: Some(parent_id),
+        ..create_message_input(content)
+    };
+    let regenerated = db
+        .create_message(TENANT, thread.id(), reply("better answer", first[0].id()))
+        .await
+        .unwrap();
+    assert_eq!(regenerated.parent_id, Some(first[0].id()));
+    let follow_up = create_messages(&db, thread.id(), &["thanks"]).await;
+    assert_eq!(
+        db.get_message(TENANT, thread.id(), regenerated.id())
+            .await
+            .unwrap()
+            .parent_id,
+        Some(first[0].id())
+    );
+    assert_eq!(follow_up[0].parent_id, None);
+
+    let other = db.create_thread(TENANT).await.unwrap();
+    let foreign = create_messages(&db, other.id(), &["elsewhere"]).await;
+    for parent_id in [foreign[0].id(), Uuid::new_v4()] {
+        assert!(matches!(
+            db.create_message(TENANT, thread.id(), reply("orphan", parent_id))
+                .await,
+            Err(DatabaseError::InvalidInput(_))
+        ));
+    }
+
+    let tree = MessageTree::new(
+        db.get_thread_messages(TENANT, thread.id(), &ListMessages::default())
+            .await
+            .unwrap()
+            .messages,
+    );
+    assert_eq!(
+        texts(&tree.branch(None).unwrap()),
+        ["question", "better answer", "thanks"]
+    );
+    assert_eq!(
+        texts(&tree.branch(Some(first[1].id())).unwrap()),
+        ["question", "answer"]
+    );
+    assert_eq!(
+        texts(&tree.branch(Some(first[0].id())).unwrap()),
+        ["question", "better answer", "thanks"]
+    );
+    assert_eq!(
+        texts(&tree.ancestors(regenerated.id()).unwrap()),
+        ["question", "better answer"]
+    );
+    assert!(tree.branch(Some(foreign[0].id())).is_none());
+}
+
+pub async fn content_kinds(db: Arc<dyn Db>) {
+    let content: Content = serde_json::from_value(json!([
+        {"type": "text", "text": "let me check"},
+        {"type": "tool_use", "id": "toolu_1", "name": "search", "input": {"query": "synx", "limit": 3}},
+        {"type": "tool_result", "tool_use_id": "toolu_1", "content": "no results", "is_error": true},
+        {"type": "tool_result", "tool_use_id": "toolu_2", "content": [{"type": "text", "text": "ok"}]},
+        {"type": "document", "source": {"type": "url", "url": "https://example.com/a.pdf"}, "mimeType": "application/pdf"},
+        {"type": "document", "source": {"type": "base64", "data": "aGk="}, "mimeType": "text/plain"},
+    ]))
+    .unwrap();
+    let expected = serde_json::to_value(&content).unwrap();
+    assert_eq!(
+        expected[2]["content"],
+        json!([{"type": "text", "text": "no results"}])
+    );
+    assert_eq!(expected[3]["is_error"], json!(false));
+
+    let thread = db.create_thread(TENANT).await.unwrap();
+    let message = db
+        .create_message(
+            TENANT,
+            thread.id(),
+            CreateMessage {
+                content,
+                ..create_message_input("")
+            },
+        )
+        .await
+        .unwrap();
+    let stored = db
+        .get_message(TENANT, thread.id(), message.id())
+        .await
+        .unwrap();
+    assert_eq!(serde_json::to_value(&stored.content).unwrap(), expected);
+    let listed = db
+        .get_thread_messages(TENANT, thread.id(), &ListMessages::default())
+        .await
+        .unwrap();
+    assert_eq!(
+        serde_json::to_value(&listed.messages[0].content).unwrap(),
+        expected
+    );
+}
+
+fn blob_content(hashes: &[&str]) -> Content {
+    serde_json::from_value(json!(hashes
+        .iter()
+        .map(|hash| json!({"type": "blob", "hash": hash, "mimeType": "image/png"}))
+        .collect::<Vec<_>>()))
+    .unwrap()
+}
+
+async fn ref_count(db: &Arc<dyn Db>, hash: &str) -> Option<u64> {
+    match db.get_blob(TENANT, hash).await {
+        Ok((blob, _)) => Some(blob.ref_count),
+        Err(DatabaseError::NotFound) => None,
+        Err(e) => panic!("unexpected error: {:?}", e),
+    }
+}
+
+/// A blob of `data` as if last uploaded at `uploaded_at`.
+fn uploaded(data: &[u8], uploaded_at: u64) -> Blob {
+    Blob {
+        uploaded_at,
+        ..Blob::new(data, None)
+    }
+}
+
+pub async fn blobs(db: Arc<dyn Db>) {
+    let stored = db
+        .put_blob(
+            TENANT,
+            Blob {
+                mime_type: Some("image/png".to_string()),
+                ..uploaded(b"first", 0)
+            },
+            b"first".to_vec(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(stored.ref_count, 0);
+    assert_eq!(stored.size, 5);
+    let again = db
+        .put_blob(TENANT, uploaded(b"first", 1), b"first".to_vec())
+        .await
+        .unwrap();
+    assert_eq!(again.hash, stored.hash);
+    assert_eq!(again.mime_type.as_deref(), Some("image/png"));
+    assert_eq!(
+        again.uploaded_at, 1,
+        "uploading again refreshes the upload time"
+    );
+    let (fetched, data) = db.get_blob(TENANT, &stored.hash).await.unwrap();
+    assert_eq!(fetched.hash, stored.hash);
+    assert_eq!(data, b"first");
+    let first = stored.hash;
+    let second = db
+        .put_blob(TENANT, uploaded(b"second", 0), b"second".to_vec())
+        .await
+        .unwrap()
+        .hash;
+    assert_not_found(db.get_blob(TENANT, "missing").await);
+
+    let thread = db.create_thread(TENANT).await.unwrap();
+    let missing = db
+        .create_message(
+            TENANT,
+            thread.id(),
+            CreateMessage {
+                content: blob_content(&[&first, "missing"]),
+                ..create_message_input("")
+            },
+        )
+        .await;
+    assert!(matches!(missing, Err(DatabaseError::InvalidInput(_))));
+    assert_eq!(ref_count(&db, &first).await, Some(0));
+
+    let attach = |content: Content| CreateMessage {
+        content,
+        ..create_message_input("")
+    };
+    let a = db
+        .create_message(TENANT, thread.id(), attach(blob_content(&[&first])))
+        .await
+        .unwrap();
+    let nested: Content = serde_json::from_value(json!([{
+        "type": "tool_result",
+        "tool_use_id": "toolu_1",
+        "content": [{"type": "blob", "hash": first, "mimeType": "image/png"}],
+    }]))
+    .unwrap();
+    let b = db
+        .create_message(TENANT, thread.id(), attach(nested))
+        .await
+        .unwrap();
+    assert_eq!(ref_count(&db, &first).await, Some(2));
+
+    db.update_message(
+        TENANT,
+        thread.id(),
+        a.id(),
+        UpdateMessage {
+            content: blob_content(&[&second]),
+        },
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        ref_count(&db, &first).await,
+        Some(2),
+        "the revision still refers to the old content"
+    );
+    assert_eq!(ref_count(&db, &second).await, Some(1));
+    assert!(matches!(
+        db.update_message(
+            TENANT,
+            thread.id(),
+            a.id(),
+            UpdateMessage {
+                content: blob_content(&["missing"]),
+            },
+        )
+        .await,
+        Err(DatabaseError::InvalidInput(_))
+    ));
+
+    db.delete_message(TENANT, thread.id(), a.id())
+        .await
+        .unwrap();
+    assert_eq!(ref_count(&db, &first).await, Some(1));
+    assert_eq!(ref_count(&db, &second).await, None);
+    assert_not_found(db.get_blob(TENANT, &second).await);
+
+    let other = db.create_thread(TENANT).await.unwrap();
+    db.create_message(TENANT, other.id(), attach(blob_content(&[&first])))
+        .await
+        .unwrap();
+    assert_eq!(ref_count(&db, &first).await, Some(2));
+    db.delete_thread(TENANT, thread.id()).await.unwrap();
+    assert_eq!(ref_count(&db, &first).await, Some(1));
+    assert!(db.get_message(TENANT, thread.id(), b.id()).await.is_err());
+    db.delete_thread(TENANT, other.id()).await.unwrap();
+    assert_eq!(ref_count(&db, &first).await, None);
+
+    // Blobs uploaded within the grace period outlive their last reference
+    // until a sweep past it.
+    let fresh = db
+        .put_blob(TENANT, Blob::new(b"fresh", None), b"fresh".to_vec())
+        .await
+        .unwrap();
+    let thread = db.create_thread(TENANT).await.unwrap();
+    let message = db
+        .create_message(TENANT, thread.id(), attach(blob_content(&[&fresh.hash])))
+        .await
+        .unwrap();
+    db.delete_message(TENANT, thread.id(), message.id())
+        .await
+        .unwrap();
+    assert_eq!(ref_count(&db, &fresh.hash).await, Some(0));
+
+    let stale = db
+        .put_blob(TENANT, uploaded(b"stale", 0), b"stale".to_vec())
+        .await
+        .unwrap()
+        .hash;
+    let kept = db
+        .put_blob(TENANT, uploaded(b"kept", 0), b"kept".to_vec())
+        .await
+        .unwrap()
+        .hash;
+    db.create_message(TENANT, thread.id(), attach(blob_content(&[&kept])))
+        .await
+        .unwrap();
+    assert_eq!(
+        db.delete_unreferenced_blobs(Blob::collection_cutoff())
+            .await
+            .unwrap(),
+        1
+    );
+    assert_eq!(ref_count(&db, &stale).await, None);
+    assert_eq!(ref_count(&db, &fresh.hash).await, Some(0));
+    assert_eq!(ref_count(&db, &kept).await, Some(1));
+
+    assert_eq!(
+        db.delete_unreferenced_blobs(fresh.uploaded_at + 1)
+            .await
+            .unwrap(),
+        1
+    );
+    assert_not_found(db.get_blob(TENANT, &fresh.hash).await);
+    assert_eq!(ref_count(&db, &kept).await, Some(1));
+}
+
+pub async fn roles(db: Arc<dyn Db>) {
+    let thread = db.create_thread(TENANT).await.unwrap();
+    let roles = [
+        Role::User,
+        Role::Assistant,
+        Role::System,
+        Role::Tool,
+        Role::Custom("critic".to_string()),
+    ];
+    for role in &roles {
+        db.create_message(
+            TENANT,
+            thread.id(),
+            CreateMessage {
+                role: role.clone(),
+                ..create_message_input("hi")
+            },
+        )
+        .await
+        .unwrap();
+    }
+
+    let stored: Vec<Role> = db
+        .get_thread_messages(TENANT, thread.id(), &ListMessages::default())
+        .await
+        .unwrap()
+        .messages
+        .into_iter()
+        .map(|message| message.role)
+        .collect();
+    assert_eq!(stored, roles);
+    assert_eq!(
+        serde_json::to_value(&stored).unwrap(),
+        json!(["user", "assistant", "system", "tool", "critic"])
+    );
+}
+
+pub async fn import_threads(db: Arc<dyn Db>) {
+    let imported = |messages: Vec<ImportedMessage>| ImportedThread {
+        title: Some("imported".to_string()),
+        metadata: Metadata::new(),
+        created_at: None,
+        messages,
+    };
+    let (thread, messages) = imported(vec![
+        ImportedMessage::new(Role::User, text("first"), Some(1_000)),
+        ImportedMessage::new(Role::Assistant, text("second"), None),
+        ImportedMessage::new(Role::User, text("third"), Some(500)),
+        ImportedMessage::new(
+            Role::Custom("critic".to_string()),
+            text("fourth"),
+            Some(5_000),
+        ),
+    ])
+    .into_records(TENANT);
+    let (empty, _) = ImportedThread {
+        created_at: Some(100),
+        ..imported(Vec::new())
+    }
+    .into_records(TENANT);
+    db.import_threads(vec![
+        (thread.clone(), messages),
+        (empty.clone(), Vec::new()),
+    ])
+    .await
+    .unwrap();
+
+    let stored = db.get_thread(TENANT, thread.id()).await.unwrap();
+    assert_eq!(stored.title.as_deref(), Some("imported"));
+    assert_eq!(stored.created_at, 1_000);
+    assert_eq!(stored.last_message_at, Some(5_000));
+    let stored_messages = db
+        .get_thread_messages(TENANT, thread.id(), &ListMessages::default())
+        .await
+        .unwrap()
+        .messages;
+    assert_eq!(
+        texts(&stored_messages),
+        vec!["first", "second", "third", "fourth"]
+    );
+    assert_eq!(
+        stored_messages
+            .iter()
+            .map(|message| message.created_at)
+            .collect::<Vec<_>>(),
+        vec![1_000, 1_001, 1_002, 5_000],
+        "times keep the import order"
+    );
+    assert_eq!(stored_messages[3].role, Role::Custom("critic".to_string()));
+    let stored_empty = db.get_thread(TENANT, empty.id()).await.unwrap();
+    assert_eq!(stored_empty.created_at, 100);
+    assert_eq!(stored_empty.last_message_at, None);
+    assert_eq!(
+        list_all_threads(
+            &db,
+            ListThreads {
+                sort: ThreadSort::LastActivity,
+                order: SortOrder::Asc,
+                ..ListThreads::default()
+            }
+        )
+        .await,
+        vec![empty.id(), thread.id()]
+    );
+
+    let next = db
+        .create_message(TENANT, thread.id(), create_message_input("fifth"))
+        .await
+        .unwrap();
+    assert!(next.created_at > 5_000);
+
+    let hash = db
+        .put_blob(TENANT, Blob::new(b"attached", None), b"attached".to_vec())
+        .await
+        .unwrap()
+        .hash;
+    let (attached, attached_messages) = imported(vec![ImportedMessage::new(
+        Role::User,
+        blob_content(&[&hash]),
+        None,
+    )])
+    .into_records(TENANT);
+    let (broken, broken_messages) = imported(vec![ImportedMessage::new(
+        Role::User,
+        blob_content(&[&hash, "missing"]),
+        None,
+    )])
+    .into_records(TENANT);
+    assert!(matches!(
+        db.import_threads(vec![
+            (attached.clone(), attached_messages),
+            (broken.clone(), broken_messages)
+        ])
+        .await,
+        Err(DatabaseError::InvalidInput(_))
+    ));
+    assert_not_found(db.get_thread(TENANT, attached.id()).await);
+    assert_not_found(db.get_thread(TENANT, broken.id()).await);
+    assert_eq!(ref_count(&db, &hash).await, Some(0));
+}
+
+pub async fn messages_are_chronological(db: Arc<dyn Db>) {
+    let thread = db.create_thread(TENANT).await.unwrap();
+    let contents: Vec<String> = (0..20).map(|i| format!("message {i}")).collect();
+    let contents: Vec<&str> = contents.iter().map(String::as_str).collect();
+    create_messages(&db, thread.id(), &contents[..10]).await;
+    tokio::time::sleep(Duration::from_millis(2)).await;
+    create_messages(&db, thread.id(), &contents[10..]).await;
+
+    let response = db
+        .get_thread_messages(TENANT, thread.id(), &ListMessages::default())
+        .await
+        .unwrap();
+    assert_eq!(response.total, 20);
+    assert_eq!(texts(&response.messages), contents);
+    assert!(response
+        .messages
+        .windows(2)
+        .all(|pair| pair[0].created_at < pair[1].created_at));
+}
+
+pub async fn message_pagination(db: Arc<dyn Db>) {
+    let thread = db.create_thread(TENANT).await.unwrap();
+
+    let empty = db
+        .get_thread_messages(TENANT, thread.id(), &ListMessages::default())
+        .await
+        .unwrap();
+    assert!(empty.messages.is_empty());
+    assert_eq!(empty.total, 0);
+    assert_eq!(empty.offset, 0);
+
+    create_messages(&db, thread.id(), &["a", "b", "c", "d", "e"]).await;
+
+    let all = db
+        .get_thread_messages(TENANT, thread.id(), &ListMessages::default())
+        .await
+        .unwrap();
+    assert_eq!(texts(&all.messages), ["a", "b", "c", "d", "e"]);
+    assert_eq!((all.total, all.offset, all.limit), (5, 0, 5));
+
+    let page = db
+        .get_thread_messages(TENANT, thread.id(), &ListMessages::page(Some(2), Some(1)))
+        .await
+        .unwrap();
+    assert_eq!(texts(&page.messages), ["b", "c"]);
+    assert_eq!((page.total, page.offset, page.limit), (5, 1, 2));
+
+    let tail = db
+        .get_thread_messages(TENANT, thread.id(), &ListMessages::page(Some(10), Some(3)))
+        .await
+        .unwrap();
+    assert_eq!(texts(&tail.messages), ["d", "e"]);
+    assert_eq!(tail.total, 5);
+
+    let past_end = db
+        .get_thread_messages(TENANT, thread.id(), &ListMessages::page(Some(2), Some(5)))
+        .await
+        .unwrap();
+    assert!(past_end.messages.is_empty());
+    assert_eq!(past_end.total, 5);
+
+    let far_past_end = db
+        .get_thread_messages(TENANT, thread.id(), &ListMessages::page(None, Some(100)))
+        .await
+        .unwrap();
+    assert!(far_past_end.messages.is_empty());
+    assert_eq!(far_past_end.total, 5);
+
+    let zero = db
+        .get_thread_messages(TENANT, thread.id(), &ListMessages::page(Some(0), None))
+        .await
+        .unwrap();
+    assert!(zero.messages.is_empty());
+    assert_eq!((zero.total, zero.limit), (5, 0));
+}
+
+pub async fn message_cursors(db: Arc<dyn Db>) {
+    let thread = db.create_thread(TENANT).await.unwrap();
+    let messages = create_messages(&db, thread.id(), &["a", "b", "c", "d", "e", "f"]).await;
+    let newest_first = ListMessages {
+        order: SortOrder::Desc,
+        limit: Some(2),
+        ..ListMessages::default()
+    };
+
+    let page = db
+        .get_thread_messages(TENANT, thread.id(), &newest_first)
+        .await
+        .unwrap();
+    assert_eq!(texts(&page.messages), ["f", "e"]);
+    assert_eq!(page.total, 6);
+    assert_eq!(page.next_cursor, Some(messages[4].cursor()));
+    let page = db
+        .get_thread_messages(
+            TENANT,
+            thread.id(),
+            &ListMessages {
+                before: page.next_cursor,
+                ..newest_first.clone()
+            },
+        )
+        .await
+        .unwrap();
+    assert_eq!(texts(&page.messages), ["d", "c"]);
+    let page = db
+        .get_thread_messages(
+            TENANT,
+            thread.id(),
+            &ListMessages {
+                before: page.next_cursor,
+                ..newest_first.clone()
+            },
+        )
+        .await
+        .unwrap();
+    assert_eq!(texts(&page.messages), ["b", "a"]);
+    assert_eq!(page.next_cursor, None);
+
+    let page = db
+        .get_thread_messages(
+            TENANT,
+            thread.id(),
+            &ListMessages {
+                after: Some(messages[1].cursor()),
+                limit: Some(2),
+                ..ListMessages::default()
+            },
+        )
+        .await
+        .unwrap();
+    assert_eq!(texts(&page.messages), ["c", "d"]);
+    assert_eq!(page.next_cursor, Some(messages[3].cursor()));
+
+    let between = ListMessages {
+        after: Some(messages[0].cursor()),
+        before: Some(messages[5].cursor()),
+        ..ListMessages::default()
+    };
+    let page = db
+        .get_thread_messages(TENANT, thread.id(), &between)
+        .await
+        .unwrap();
+    assert_eq!(texts(&page.messages), ["b", "c", "d", "e"]);
+    assert_eq!((page.total, page.next_cursor), (6, None));
+    let page = db
+        .get_thread_messages(
+            TENANT,
+            thread.id(),
+            &ListMessages {
+                order: SortOrder::Desc,
+                offset: Some(1),
+                limit: Some(2),
+                ..between
+            },
+        )
+        .await
+        .unwrap();
+    assert_eq!(texts(&page.messages), ["d", "c"]);
+    assert_eq!(page.next_cursor, Some(messages[2].cursor()));
+
+    // Cursors stay valid when the message they point at is deleted.
+    db.delete_message(TENANT, thread.id(), messages[2].id())
+        .await
+        .unwrap();
+    let page = db
+        .get_thread_messages(
+            TENANT,
+            thread.id(),
+            &ListMessages {
+                after: Some(messages[2].cursor()),
+                ..ListMessages::default()
+            },
+        )
+        .await
+        .unwrap();
+    assert_eq!(texts(&page.messages), ["d", "e", "f"]);
+    assert_eq!(page.total, 5);
+}
+
+pub async fn delete_message_updates_thread(db: Arc<dyn Db>) {
+    let thread = db.create_thread(TENANT).await.unwrap();
+    let messages = create_messages(&db, thread.id(), &["a", "b", "c"]).await;
+    db.update_message_embedding(
+        TENANT,
+        thread.id(),
+        messages[1].id(),
+        Some(embedding(&[0.0, 1.0])),
+    )
+    .await
+    .unwrap();
+
+    db.delete_message(TENANT, thread.id(), messages[1].id())
+        .await
+        .unwrap();
+    assert_not_found(
+        db.delete_message(TENANT, thread.id(), messages[1].id())
+            .await,
+    );
+
+    let response = db
+        .get_thread_messages(TENANT, thread.id(), &ListMessages::default())
+        .await
+        .unwrap();
+    assert_eq!(texts(&response.messages), ["a", "c"]);
+    assert_eq!(response.total, 2);
+
+    let page = db
+        .get_thread_messages(TENANT, thread.id(), &ListMessages::page(Some(1), Some(1)))
+        .await
+        .unwrap();
+    assert_eq!(texts(&page.messages), ["c"]);
+
+    let found = db
+        .search_message_embeddings(TENANT, &embedding(&[0.0, 1.0]), None, 10)
+        .await
+        .unwrap();
+    assert!(found.is_empty());
+}
+
+pub async fn delete_thread_cascades(db: Arc<dyn Db>) {
+    let thread = db.create_thread(TENANT).await.unwrap();
+    let other = db.create_thread(TENANT).await.unwrap();
+    let messages = create_messages(&db, thread.id(), &["a", "b"]).await;
+    let kept = create_messages(&db, other.id(), &["c"]).await;
+
+    for message in messages.iter().chain(&kept) {
+        db.update_message_embedding(
+            TENANT,
+            message.thread_id,
+            message.id(),
+            Some(embedding(&[1.0, 0.0])),
+        )
+        .await
+        .unwrap();
+    }
+    for id in [thread.id(), other.id()] {
+        db.update_thread_summary_and_embedding(
+            TENANT,
+            id,
+            None,
+            cursor(&kept[0]),
+            "summary".to_string(),
+            embedding(&[1.0, 0.0]),
+        )
+        .await
+        .unwrap();
+    }
+    db.save_job(&Job::new(
+        TENANT,
+        thread.id(),
+        JobKind::Summarize {
+            message_id: messages[0].id(),
+        },
+    ))
+    .await
+    .unwrap();
+    let kept_job = Job::new(
+        TENANT,
+        other.id(),
+        JobKind::Summarize {
+            message_id: kept[0].id(),
+        },
+    );
+    db.save_job(&kept_job).await.unwrap();
+
+    db.delete_thread(TENANT, thread.id()).await.unwrap();
+
+    assert_not_found(db.get_thread(TENANT, thread.id()).await);
+    assert_not_found(
+        db.get_thread_messages(TENANT, thread.id(), &ListMessages::default())
+            .await,
+    );
+    assert_not_found(db.get_thread_jobs(TENANT, thread.id()).await);
+    for message in &messages {
+        assert_not_found(db.get_message(TENANT, thread.id(), message.id()).await);
+    }
+    assert!(db
+        .get_threads_with_embeddings(TENANT, &[thread.id()])
+        .await
+        .unwrap()
+        .is_empty());
+
+    let found_messages: Vec<Uuid> = db
+        .search_message_embeddings(TENANT, &embedding(&[1.0, 0.0]), None, 10)
+        .await
+        .unwrap()
+        .iter()
+        .map(|(message, _)| message.id())
+        .collect();
+    assert_eq!(found_messages, vec![kept[0].id()]);
+
+    let found_threads: Vec<Uuid> = db
+        .search_thread_embeddings(TENANT, &embedding(&[1.0, 0.0]), None, &Metadata::new(), 10)
+        .await
+        .unwrap()
+        .iter()
+        .map(|(thread, _)| thread.id())
+        .collect();
+    assert_eq!(found_threads, vec![other.id()]);
+
+    let pending: Vec<Uuid> = db
+        .get_pending_jobs()
+        .await
+        .unwrap()
+        .iter()
+        .map(|job| job.id())
+        .collect();
+    assert_eq!(pending, vec![kept_job.id()]);
+
+    assert_eq!(
+        db.get_thread_messages(TENANT, other.id(), &ListMessages::default())
+            .await
+            .unwrap()
+            .total,
+        1
+    );
+}
+
+pub async fn thread_embeddings_are_not_invented(db: Arc<dyn Db>) {
+    let thread = db.create_thread(TENANT).await.unwrap();
+    let missing = Uuid::new_v4();
+
+    let threads = db
+        .get_threads_with_embeddings(TENANT, &[thread.id(), missing])
+        .await
+        .unwrap();
+    assert_eq!(threads.len(), 1);
+    assert!(threads[0].embedding.is_none());
+
+    let message = db
+        .create_message(TENANT, thread.id(), create_message_input("hello"))
+        .await
+        .unwrap();
+    db.update_thread_summary_and_embedding(
+        TENANT,
+        thread.id(),
+        None,
+        cursor(&message),
+        "summary".to_string(),
+        embedding(&[0.5, 0.25, 0.125]),
+    )
+    .await
+    .unwrap();
+
+    let threads = db
+        .get_threads_with_embeddings(TENANT, &[thread.id()])
+        .await
+        .unwrap();
+    assert_eq!(
+        threads[0]
+            .embedding
+            .as_ref()
+            .map(|embedding| embedding.to_vec()),
+        Some(vec![0.5, 0.25, 0.125])
+    );
+    assert_eq!(threads[0].summary.as_deref(), Some("summary"));
+}
+
+pub async fn summary_updates_compare_cursor(db: Arc<dyn Db>) {
+    let thread = db.create_thread(TENANT).await.unwrap();
+    let messages = create_messages(&db, thread.id(), &["a", "b"]).await;
+
+    db.update_thread_summary_and_embedding(
+        TENANT,
+        thread.id(),
+        None,
+        cursor(&messages[0]),
+        "a".to_string(),
+        embedding(&[1.0, 0.0]),
+    )
+    .await
+    .unwrap();
+
+    let stale = db
+        .update_thread_summary_and_embedding(
+            TENANT,
+            thread.id(),
+            None,
+            cursor(&messages[1]),
+            "stale".to_string(),
+            embedding(&[0.0, 1.0]),
+        )
+        .await;
+    assert!(
+        matches!(stale, Err(DatabaseError::Conflict(_))),
+        "expected Conflict, got {:?}",
+        stale
+    );
+
+    db.update_thread_summary_and_embedding(
+        TENANT,
+        thread.id(),
+        Some(cursor(&messages[0])),
+        cursor(&messages[1]),
+        "a b".to_string(),
+        embedding(&[0.0, 1.0]),
+    )
+    .await
+    .unwrap();
+
+    let thread = db.get_thread(TENANT, thread.id()).await.unwrap();
+    assert_eq!(thread.summary.as_deref(), Some("a b"));
+    assert_eq!(thread.summary_cursor, Some(cursor(&messages[1])));
+}
+
+pub async fn thread_search(db: Arc<dyn Db>) {
+    let vectors = [
+        [1.0, 0.0, 0.0],
+        [0.0, 1.0, 0.0],
+        [0.0, 0.0, 1.0],
+        [0.9, 0.1, 0.0],
+    ];
+    let mut thread_ids = Vec::new();
+    for vector in vectors {
+        let thread = db.create_thread(TENANT).await.unwrap();
+        let message = db
+            .create_message(TENANT, thread.id(), create_message_input("hello"))
+            .await
+            .unwrap();
+        db.update_thread_summary_and_embedding(
+            TENANT,
+            thread.id(),
+            None,
+            cursor(&message),
+            "summary".to_string(),
+            embedding(&vector),
+        )
+        .await
+        .unwrap();
+        thread_ids.push(thread.id());
+    }
+    // Threads without a summary are never returned.
+    db.create_thread(TENANT).await.unwrap();
+
+    let query = embedding(&[1.0, 0.0, 0.0]);
+    let results = db
+        .search_thread_embeddings(TENANT, &query, None, &Metadata::new(), 10)
+        .await
+        .unwrap();
+    assert_eq!(results.len(), 4);
+    assert_eq!(results[0].0.id(), thread_ids[0]);
+    assert_eq!(results[1].0.id(), thread_ids[3]);
+    assert!(results.windows(2).all(|pair| pair[0].1 >= pair[1].1));
+
+    let limited = db
+        .search_thread_embeddings(TENANT, &query, None, &Metadata::new(), 2)
+        .await
+        .unwrap();
+    assert_eq!(limited.len(), 2);
+
+    let filtered = db
+        .search_thread_embeddings(
+            TENANT,
+            &query,
+            Some(&[thread_ids[1], thread_ids[2]]),
+            &Metadata::new(),
+            10,
+        )
+        .await
+        .unwrap();
+    let filtered: HashSet<Uuid> = filtered.iter().map(|(thread, _)| thread.id()).collect();
+    assert_eq!(filtered, HashSet::from([thread_ids[1], thread_ids[2]]));
+
+    let none = db
+        .search_thread_embeddings(
+            TENANT,
+            &query,
+            Some(&[Uuid::new_v4()]),
+            &Metadata::new(),
+            10,
+        )
+        .await
+        .unwrap();
+    assert!(none.is_empty());
+}
+
+pub async fn metadata(db: Arc<dyn Db>) {
+    let tagged: Metadata =
+        serde_json::from_value(json!({"project": "synx", "priority": 2})).unwrap();
+    let thread = db.create_thread(TENANT).await.unwrap();
+    assert!(thread.metadata.is_empty());
+    let updated = db
+        .update_thread(
+            TENANT,
+            thread.id(),
+            UpdateThread {
+                title: None,
+                metadata: tagged.clone(),
+            },
+        )
+        .await
+        .unwrap();
+    assert_eq!(updated.metadata, tagged);
+    assert_eq!(
+        db.get_thread(TENANT, thread.id()).await.unwrap().metadata,
+        tagged
+    );
+
+    let message = db
+        .create_message(
+            TENANT,
+            thread.id(),
+            CreateMessage {
+                metadata: tagged.clone(),
+                ..create_message_input("hello")
+            },
+        )
+        .await
+        .unwrap();
+    assert_eq!(message.metadata, tagged);
+    db.update_message(
+        TENANT,
+        thread.id(),
+        message.id(),
+        UpdateMessage {
+            content: text("hello again"),
+        },
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        db.get_message(TENANT, thread.id(), message.id())
+            .await
+            .unwrap()
+            .metadata,
+        tagged
+    );
+
+    let other = db.create_thread(TENANT).await.unwrap();
+    for id in [thread.id(), other.id()] {
+        db.update_thread_summary_and_embedding(
+            TENANT,
+            id,
+            None,
+            SummaryCursor {
+                created_at: 0,
+                message_id: Uuid::new_v4(),
+            },
+            "summary".to_string(),
+            embedding(&[1.0, 0.0]),
+        )
+        .await
+        .unwrap();
+    }
+
+    let filter = |value: serde_json::Value| -> Metadata { serde_json::from_value(value).unwrap() };
+    let by_metadata = |value: serde_json::Value| ListThreads {
+        metadata: filter(value),
+        ..ListThreads::default()
+    };
+    let listed = |response: ListThreadsResponse| -> Vec<Uuid> {
+        response.threads.iter().map(|thread| thread.id()).collect()
+    };
+    assert_eq!(
+        listed(
+            db.list_threads(TENANT, &by_metadata(json!({"project": "synx"})))
+                .await
+                .unwrap()
+        ),
+        vec![thread.id()]
+    );
+    assert_eq!(
+        listed(
+            db.list_threads(TENANT, &by_metadata(json!({"priority": 2})))
+                .await
+                .unwrap()
+        ),
+        vec![thread.id()]
+    );
+    // Query strings only carry text, so a string filter also matches a number.
+    assert_eq!(
+        listed(
+            db.list_threads(TENANT, &by_metadata(json!({"priority": "2"})))
+                .await
+                .unwrap()
+        ),
+        vec![thread.id()]
+    );
+    assert!(db
+        .list_threads(
+            TENANT,
+            &by_metadata(json!({"project": "synx", "priority": 3}))
+        )
+        .await
+        .unwrap()
+        .threads
+        .is_empty());
+    assert_eq!(
+        db.list_threads(TENANT, &ListThreads::default())
+            .await
+            .unwrap()
+            .threads
+            .len(),
+        2
+    );
+
+    let query = embedding(&[1.0, 0.0]);
+    let results = db
+        .search_thread_embeddings(
+            TENANT,
+            &query,
+            None,
+            &filter(json!({"project": "synx"})),
+            10,
+        )
+        .await
+        .unwrap();
+    assert_eq!(
+        results
+            .iter()
+            .map(|(thread, _)| thread.id())
+            .collect::<Vec<_>>(),
+        vec![thread.id()]
+    );
+    assert!(db
+        .search_thread_embeddings(
+            TENANT,
+            &query,
+            None,
+            &filter(json!({"project": "other"})),
+            10
+        )
+        .await
+        .unwrap()
+        .is_empty());
+}
+
+pub async fn message_search(db: Arc<dyn Db>) {
+    let thread = db.create_thread(TENANT).await.unwrap();
+    let other = db.create_thread(TENANT).await.unwrap();
+    let messages = create_messages(&db, thread.id(), &["x", "y"]).await;
+    let other_messages = create_messages(&db, other.id(), &["z"]).await;
+
+    let vectors = [[1.0, 0.0], [0.0, 1.0], [0.8, 0.2]];
+    for (message, vector) in messages.iter().chain(&other_messages).zip(vectors) {
+        db.update_message_embedding(
+            TENANT,
+            message.thread_id,
+            message.id(),
+            Some(embedding(&vector)),
+        )
+        .await
+        .unwrap();
+    }
+
+    let query = embedding(&[1.0, 0.0]);
+    let ids = |results: Vec<(Message, f32)>| -> Vec<Uuid> {
+        results.iter().map(|(message, _)| message.id()).collect()
+    };
+
+    let results = db
+        .search_message_embeddings(TENANT, &query, None, 10)
+        .await
+        .unwrap();
+    assert_eq!(
+        ids(results),
+        vec![messages[0].id(), other_messages[0].id(), messages[1].id()]
+    );
+
+    let results = db
+        .search_message_embeddings(TENANT, &query, Some(&[thread.id()]), 10)
+        .await
+        .unwrap();
+    assert_eq!(ids(results), vec![messages[0].id(), messages[1].id()]);
+
+    let results = db
+        .search_message_embeddings(TENANT, &query, None, 1)
+        .await
+        .unwrap();
+    assert_eq!(ids(results), vec![messages[0].id()]);
+
+    // Re-embedding replaces the previous vector, and clearing it unindexes the message.
+    db.update_message_embedding(
+        TENANT,
+        thread.id(),
+        messages[0].id(),
+        Some(embedding(&[0.0, 1.0])),
+    )
+    .await
+    .unwrap();
+    db.update_message_embedding(TENANT, other.id(), other_messages[0].id(), None)
+        .await
+        .unwrap();
+    let results = db
+        .search_message_embeddings(TENANT, &query, None, 10)
+        .await
+        .unwrap();
+    assert_eq!(results.len(), 2);
+    assert!(results
+        .iter()
+        .all(|(message, _)| message.id() != other_messages[0].id()));
+}
+
+pub async fn jobs(db: Arc<dyn Db>) {
+    let thread = db.create_thread(TENANT).await.unwrap();
+    let message = db
+        .create_message(TENANT, thread.id(), create_message_input("hello"))
+        .await
+        .unwrap();
+
+    assert!(db
+        .get_thread_jobs(TENANT, thread.id())
+        .await
+        .unwrap()
+        .is_empty());
+
+    let mut first = Job::new(
+        TENANT,
+        thread.id(),
+        JobKind::Summarize {
+            message_id: message.id(),
+        },
+    );
+    let mut second = Job::new(
+        TENANT,
+        thread.id(),
+        JobKind::EmbedMessage {
+            message_id: message.id(),
+        },
+    );
+    second.created_at = first.created_at + 1;
+    db.save_job(&second).await.unwrap();
+    db.save_job(&first).await.unwrap();
+
+    let ids = |jobs: Vec<Job>| -> Vec<Uuid> { jobs.iter().map(|job| job.id()).collect() };
+    assert_eq!(
+        ids(db.get_thread_jobs(TENANT, thread.id()).await.unwrap()),
+        vec![first.id(), second.id()]
+    );
+    assert_eq!(
+        ids(db.get_pending_jobs().await.unwrap()),
+        vec![first.id(), second.id()]
+    );
+
+    // Saving again replaces the stored job.
+    first.attempts = 3;
+    first.last_error = Some("boom".to_string());
+    first.status = JobStatus::Failed;
+    db.save_job(&first).await.unwrap();
+
+    let stored = db.get_thread_jobs(TENANT, thread.id()).await.unwrap();
+    assert_eq!(stored.len(), 2);
+    assert_eq!(stored[0].attempts, 3);
+    assert_eq!(stored[0].last_error.as_deref(), Some("boom"));
+    assert_eq!(stored[0].status, JobStatus::Failed);
+    assert_eq!(ids(db.get_pending_jobs().await.unwrap()), vec![second.id()]);
+
+    let other = db.create_thread(TENANT).await.unwrap();
+    assert_not_found(db.delete_job(TENANT, other.id(), second.id()).await);
+
+    db.delete_job(TENANT, thread.id(), second.id())
+        .await
+        .unwrap();
+    assert_not_found(db.delete_job(TENANT, thread.id(), second.id()).await);
+    assert_eq!(
+        ids(db.get_thread_jobs(TENANT, thread.id()).await.unwrap()),
+        vec![first.id()]
+    );
+    assert!(db.get_pending_jobs().await.unwrap().is_empty());
+}
+
+pub async fn webhooks(db: Arc<dyn Db>) {
+    assert!(db.list_webhooks(TENANT).await.unwrap().is_empty());
+
+    let mut first = CreateWebhook {
+        url: "https://example.com/first".to_string(),
+        events: vec!["message.created".to_string()],
+        secret: None,
+    }
+    .into_webhook(TENANT, "first secret".to_string());
+    let mut second = CreateWebhook {
+        url: "https://example.com/second".to_string(),
+        events: Vec::new(),
+        secret: None,
+    }
+    .into_webhook(TENANT, "second secret".to_string());
+    second.created_at = first.created_at + 1;
+    db.save_webhook(&second).await.unwrap();
+    db.save_webhook(&first).await.unwrap();
+
+    let stored = db.get_webhook(TENANT, first.id()).await.unwrap();
+    assert_eq!(stored.url, "https://example.com/first");
+    assert_eq!(stored.events, vec!["message.created".to_string()]);
+    assert_eq!(stored.secret.as_deref(), Some("first secret"));
+    assert_not_found(db.get_webhook(TENANT, Uuid::new_v4()).await);
+
+    let listed: Vec<Uuid> = db
+        .list_webhooks(TENANT)
+        .await
+        .unwrap()
+        .iter()
+        .map(|webhook| webhook.id())
+        .collect();
+    assert_eq!(listed, vec![first.id(), second.id()]);
+    let orphan = CreateWebhook {
+        url: "https://example.com/orphan".to_string(),
+        events: Vec::new(),
+        secret: None,
+    }
+    .into_webhook(Uuid::new_v4(), "orphan secret".to_string());
+    assert_not_found(db.save_webhook(&orphan).await);
+    assert_not_found(db.get_webhook(orphan.tenant_id, orphan.id()).await);
+
+    let delivery_ids = |deliveries: Vec<WebhookDelivery>| -> Vec<Uuid> {
+        deliveries.iter().map(|delivery| delivery.id()).collect()
+    };
+    let mut early = WebhookDelivery::new(&first, "message.created", json!({"n": 1}));
+    let mut late = WebhookDelivery::new(&first, "message.created", json!({"n": 2}));
+    late.created_at = early.created_at + 1;
+    let other = WebhookDelivery::new(&second, "thread.deleted", json!({"n": 3}));
+    db.save_webhook_delivery(&late).await.unwrap();
+    db.save_webhook_delivery(&early).await.unwrap();
+    db.save_webhook_delivery(&other).await.unwrap();
+    let unsaved = CreateWebhook {
+        url: "https://example.com/unsaved".to_string(),
+        events: Vec::new(),
+        secret: None,
+    }
+    .into_webhook(TENANT, "unsaved secret".to_string());
+    assert_not_found(
+        db.save_webhook_delivery(&WebhookDelivery::new(
+            &unsaved,
+            "message.created",
+            json!({}),
+        ))
+        .await,
+    );
+
+    assert_eq!(
+        delivery_ids(
+            db.list_webhook_deliveries(TENANT, first.id())
+                .await
+                .unwrap()
+        ),
+        vec![early.id(), late.id()]
+    );
+    assert_eq!(
+        db.list_webhook_deliveries(TENANT, second.id())
+            .await
+            .unwrap()[0]
+            .payload,
+        json!({"n": 3})
+    );
+    assert_not_found(db.list_webhook_deliveries(TENANT, Uuid::new_v4()).await);
+    assert_eq!(db.get_pending_webhook_deliveries().await.unwrap().len(), 3);
+
+    // Saving again replaces the stored delivery.
+    early.attempts = 1;
+    early.status = DeliveryStatus::Succeeded;
+    early.response_status = Some(204);
+    early.delivered_at = Some(early.created_at + 5);
+    db.save_webhook_delivery(&early).await.unwrap();
+    late.attempts = 8;
+    late.status = DeliveryStatus::Failed;
+    late.last_error = Some("connection refused".to_string());
+    db.save_webhook_delivery(&late).await.unwrap();
+
+    let stored = db
+        .list_webhook_deliveries(TENANT, first.id())
+        .await
+        .unwrap();
+    assert_eq!(stored.len(), 2);
+    assert_eq!(stored[0].status, DeliveryStatus::Succeeded);
+    assert_eq!(stored[0].response_status, Some(204));
+    assert_eq!(stored[0].delivered_at, Some(early.created_at + 5));
+    assert_eq!(stored[1].status, DeliveryStatus::Failed);
+    assert_eq!(stored[1].last_error.as_deref(), Some("connection refused"));
+    assert_eq!(
+        delivery_ids(db.get_pending_webhook_deliveries().await.unwrap()),
+        vec![other.id()]
+    );
+
+    // Saving the webhook again keeps its deliveries.
+    first.events.push("thread.deleted".to_string());
+    db.save_webhook(&first).await.unwrap();
+    assert_eq!(
+        db.get_webhook(TENANT, first.id())
+            .await
+            .unwrap()
+            .events
+            .len(),
+        2
+    );
+    assert_eq!(
+        db.list_webhook_deliveries(TENANT, first.id())
+            .await
+            .unwrap()
+            .len(),
+        2
+    );
+
+    db.delete_webhook(TENANT, second.id()).await.unwrap();
+    assert_not_found(db.delete_webhook(TENANT, second.id()).await);
+    assert_not_found(db.get_webhook(TENANT, second.id()).await);
+    assert_not_found(db.list_webhook_deliveries(TENANT, second.id()).await);
+    assert!(db
+        .get_pending_webhook_deliveries()
+        .await
+        .unwrap()
+        .is_empty());
+    assert_eq!(db.list_webhooks(TENANT).await.unwrap().len(), 1);
+    assert_not_found(db.save_webhook_delivery(&other).await);
+}
+
+pub async fn tenants(db: Arc<dyn Db>) {
+    let listed: Vec<Uuid> = db
+        .list_tenants()
+        .await
+        .unwrap()
+        .iter()
+        .map(|tenant| tenant.id())
+        .collect();
+    assert_eq!(listed, vec![TENANT]);
+
+    let mut other = Tenant::new("other".to_string());
+    other.created_at = 1;
+    db.save_tenant(&other).await.unwrap();
+    assert_eq!(db.get_tenant(other.id()).await.unwrap().name, "other");
+    assert_not_found(db.get_tenant(Uuid::new_v4()).await);
+    assert_eq!(db.list_tenants().await.unwrap().len(), 2);
+
+    // Nothing of one tenant is reachable from another.
+    let thread = db.create_thread(other.id()).await.unwrap();
+    let message = db
+        .create_message(other.id(), thread.id(), create_message_input("secret"))
+        .await
+        .unwrap();
+    db.update_thread_summary_and_embedding(
+        other.id(),
+        thread.id(),
+        None,
+        cursor(&message),
+        "summary".to_string(),
+        embedding(&[1.0, 0.0]),
+    )
+    .await
+    .unwrap();
+    db.update_message_embedding(
+        other.id(),
+        thread.id(),
+        message.id(),
+        Some(embedding(&[1.0, 0.0])),
+    )
+    .await
+    .unwrap();
+    let own = db.create_thread(TENANT).await.unwrap();
+
+    assert_not_found(db.get_thread(TENANT, thread.id()).await);
+    assert_not_found(
+        db.get_thread_messages(TENANT, thread.id(), &ListMessages::default())
+            .await,
+    );
+    assert_not_found(db.get_message(TENANT, thread.id(), message.id()).await);
+    assert_not_found(
+        db.create_message(TENANT, thread.id(), create_message_input("intruder"))
+            .await,
+    );
+    assert_not_found(db.delete_thread(TENANT, thread.id()).await);
+    assert_not_found(db.get_thread(other.id(), own.id()).await);
+
+    let listed = db
+        .list_threads(TENANT, &ListThreads::default())
+        .await
+        .unwrap();
+    assert_eq!(listed.total, 1);
+    assert_eq!(listed.threads[0].id(), own.id());
+    assert_eq!(
+        db.list_threads(other.id(), &ListThreads::default())
+            .await
+            .unwrap()
+            .threads[0]
+            .id(),
+        thread.id()
+    );
+
+    let query = embedding(&[1.0, 0.0]);
+    assert!(db
+        .search_thread_embeddings(TENANT, &query, None, &Metadata::new(), 10)
+        .await
+        .unwrap()
+        .is_empty());
+    assert!(db
+        .search_thread_embeddings(TENANT, &query, Some(&[thread.id()]), &Metadata::new(), 10)
+        .await
+        .unwrap()
+        .is_empty());
+    assert!(db
+        .search_message_embeddings(TENANT, &query, None, 10)
+        .await
+        .unwrap()
+        .is_empty());
+    assert_eq!(
+        db.search_thread_embeddings(other.id(), &query, None, &Metadata::new(), 10)
+            .await
+            .unwrap()
+            .len(),
+        1
+    );
+    assert_eq!(
+        db.search_message_embeddings(other.id(), &query, None, 10)
+            .await
+            .unwrap()
+            .len(),
+        1
+    );
+
+    assert_not_found(
+        db.save_job(&Job::new(
+            TENANT,
+            thread.id(),
+            JobKind::Summarize {
+                message_id: message.id(),
+            },
+        ))
+        .await,
+    );
+    assert_not_found(db.get_thread_jobs(TENANT, thread.id()).await);
+
+    let webhook = CreateWebhook {
+        url: "https://example.com/other".to_string(),
+        events: Vec::new(),
+        secret: None,
+    }
+    .into_webhook(other.id(), "secret".to_string());
+    db.save_webhook(&webhook).await.unwrap();
+    db.save_webhook_delivery(&WebhookDelivery::new(&webhook, "thread.deleted", json!({})))
+        .await
+        .unwrap();
+    assert!(db.list_webhooks(TENANT).await.unwrap().is_empty());
+    assert_not_found(db.get_webhook(TENANT, webhook.id()).await);
+    assert_not_found(db.delete_webhook(TENANT, webhook.id()).await);
+    assert_not_found(db.list_webhook_deliveries(TENANT, webhook.id()).await);
+
+    // Blobs are stored and counted per tenant, even with the same bytes.
+    let hash = db
+        .put_blob(other.id(), Blob::new(b"private", None), b"private".to_vec())
+        .await
+        .unwrap()
+        .hash;
+    let unused = db
+        .put_blob(other.id(), Blob::new(b"unused", None), b"unused".to_vec())
+        .await
+        .unwrap()
+        .hash;
+    assert_not_found(db.get_blob(TENANT, &hash).await);
+    let attach = |content: Content| CreateMessage {
+        content,
+        ..create_message_input("")
+    };
+    assert!(matches!(
+        db.create_message(TENANT, own.id(), attach(blob_content(&[&hash])))
+            .await,
+        Err(DatabaseError::InvalidInput(_))
+    ));
+    db.create_message(other.id(), thread.id(), attach(blob_content(&[&hash])))
+        .await
+        .unwrap();
+    db.put_blob(TENANT, Blob::new(b"private", None), b"private".to_vec())
+        .await
+        .unwrap();
+    db.create_message(TENANT, own.id(), attach(blob_content(&[&hash])))
+        .await
+        .unwrap();
+    assert_eq!(db.get_blob(other.id(), &hash).await.unwrap().0.ref_count, 1);
+    assert_eq!(db.get_blob(TENANT, &hash).await.unwrap().0.ref_count, 1);
+
+    // Keys are found by hash, whatever their tenant.
+    let (first, key) = ApiKey::generate(
+        other.id(),
+        Some("bot".to_string()),
+        vec![Scope::ThreadsRead, Scope::Search],
+    );
+    let (mut second, _) = ApiKey::generate(other.id(), None, vec![Scope::Admin]);
+    second.created_at = first.created_at + 1;
+    db.save_api_key(&second).await.unwrap();
+    db.save_api_key(&first).await.unwrap();
+    let found = db.get_api_key_by_hash(&ApiKey::hash(&key)).await.unwrap();
+    assert_eq!(found.id(), first.id());
+    assert_eq!(found.tenant_id, other.id());
+    assert_eq!(found.name.as_deref(), Some("bot"));
+    assert_eq!(found.scopes, vec![Scope::ThreadsRead, Scope::Search]);
+    assert_not_found(db.get_api_key_by_hash(&ApiKey::hash("synx_unknown")).await);
+
+    let key_ids = |keys: Vec<ApiKey>| -> Vec<Uuid> { keys.iter().map(|key| key.id()).collect() };
+    assert_eq!(
+        key_ids(db.list_api_keys(other.id()).await.unwrap()),
+        vec![first.id(), second.id()]
+    );
+    assert!(db.list_api_keys(TENANT).await.unwrap().is_empty());
+    assert_not_found(db.list_api_keys(Uuid::new_v4()).await);
+    assert_not_found(
+        db.save_api_key(&ApiKey::generate(Uuid::new_v4(), None, vec![Scope::Search]).0)
+            .await,
+    );
+
+    assert_not_found(db.delete_api_key(TENANT, second.id()).await);
+    db.delete_api_key(other.id(), second.id()).await.unwrap();
+    assert_not_found(db.delete_api_key(other.id(), second.id()).await);
+    assert_eq!(
+        key_ids(db.list_api_keys(other.id()).await.unwrap()),
+        vec![first.id()]
+    );
+
+    // A tenant is only removed once it owns no threads, taking its keys,
+    // webhooks and blobs with it.
+    match db.delete_tenant(other.id()).await {
+        Err(DatabaseError::Conflict(_)) => {}
+        other => panic!("expected Conflict, got {:?}", other),
+    }
+    db.delete_thread(other.id(), thread.id()).await.unwrap();
+    db.delete_tenant(other.id()).await.unwrap();
+    assert_not_found(db.delete_tenant(other.id()).await);
+    assert_not_found(db.get_tenant(other.id()).await);
+    assert_not_found(db.get_api_key_by_hash(&ApiKey::hash(&key)).await);
+    assert_not_found(db.get_webhook(other.id(), webhook.id()).await);
+    assert_not_found(db.get_blob(other.id(), &hash).await);
+    assert_not_found(db.get_blob(other.id(), &unused).await);
+    assert_eq!(db.get_blob(TENANT, &hash).await.unwrap().0.ref_count, 1);
+    assert!(db
+        .get_pending_webhook_deliveries()
+        .await
+        .unwrap()
+        .is_empty());
+    assert_eq!(db.list_tenants().await.unwrap().len(), 1);
+}
+
+pub async fn concurrent_message_creation(db: Arc<dyn Db>) {
+    let thread = db.create_thread(TENANT).await.unwrap();
+
+    let mut tasks = tokio::task::JoinSet::new();
+    for i in 0..50 {
+        let db = db.clone();
+        let thread_id = thread.id();
+        tasks.spawn(async move {
+            db.create_message(
+                TENANT,
+                thread_id,
+                create_message_input(&format!("message {i}")),
+            )
+            .await
+            .unwrap()
+            .id()
+        });
+    }
+
+    let mut created = HashSet::new();
+    while let Some(id) = tasks.join_next().await {
+        created.insert(id.unwrap());
+    }
+
+    let response = db
+        .get_thread_messages(TENANT, thread.id(), &ListMessages::default())
+        .await
+        .unwrap();
+    assert_eq!(response.total, 50);
+    let listed: HashSet<Uuid> = response
+        .messages
+        .iter()
+        .map(|message| message.id())
+        .collect();
+    assert_eq!(listed, created);
+}
+
+pub async fn concurrent_summary_updates(db: Arc<dyn Db>) {
+    let thread = db.create_thread(TENANT).await.unwrap();
+    let message = db
+        .create_message(TENANT, thread.id(), create_message_input("hello"))
+        .await
+        .unwrap();
+
+    let mut tasks = tokio::task::JoinSet::new();
+    for i in 0..10 {
+        let db = db.clone();
+        let thread_id = thread.id();
+        let cursor = cursor(&message);
+        tasks.spawn(async move {
+            db.update_thread_summary_and_embedding(
+                TENANT,
+                thread_id,
+                None,
+                cursor,
+                format!("summary {i}"),
+                embedding(&[1.0, i as f32]),
+            )
+            .await
+        });
+    }
+
+    let mut applied = 0;
+    while let Some(result) = tasks.join_next().await {
+        match result.unwrap() {
+            Ok(()) => applied += 1,
+            Err(DatabaseError::Conflict(_)) => {}
+            Err(e) => panic!("unexpected error: {e}"),
+        }
+    }
+    assert_eq!(applied, 1);
+}
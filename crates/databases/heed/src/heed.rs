@@ -1,19 +1,42 @@
 mod heed_ids;
+mod heed_index;
 
-use std::sync::Arc;
+use std::{
+    collections::{HashMap, HashSet},
+    ops::Bound,
+    sync::Arc,
+};
 
 pub use heed;
 use heed::{
-    types::{SerdeJson, Unit},
-    Database, Env,
+    types::{Bytes, DecodeIgnore, SerdeJson, Str, Unit},
+    Database, Env, RoTxn, RwTxn,
+};
+use heed_ids::{
+    HeedMessageCreationTimeId, HeedTenantTimestampUuid, HeedTimestampUuid, HeedUuid,
+    HeedUuidString, HeedUuidTimestampUuid, HeedUuidTuple,
 };
-use heed_ids::{HeedMessageCreationTimeId, HeedTimestampUuid, HeedUuid, HeedUuidTuple};
+use heed_index::{HeedIndex, HeedIndexReader, HeedIndexWriter};
 use synx_database::{DatabaseError, Db};
 use synx_domain::{
+    api_key::ApiKey,
+    blob::Blob,
+    content::Content,
     embedding::Embedding,
-    message::{CreateMessage, Message, ThreadMessagesResponse, UpdateMessage},
-    thread::{Thread, UpdateThread},
+    job::{Job, JobStatus},
+    message::{
+        CreateMessage, ListMessages, Message, MessageCursor, MessageRevision,
+        ThreadMessagesResponse, UpdateMessage,
+    },
+    metadata::{self, Metadata},
+    tenant::Tenant,
+    thread::{
+        ListThreads, ListThreadsResponse, SortOrder, SummaryCursor, Thread, ThreadCursor,
+        ThreadSort, UpdateThread,
+    },
+    webhook::{DeliveryStatus, Webhook, WebhookDelivery},
 };
+use synx_vector_index::{Hnsw, HnswEntryPoint, HnswNode};
 use uuid::Uuid;
 
 #[derive(Debug)]
@@ -22,24 +45,36 @@ pub struct SynxHeedDatabase {
     threads_db: Database<HeedUuid, SerdeJson<Thread>>,
     messages_db: Database<HeedUuidTuple, SerdeJson<Message>>,
     thread_messages_db: Database<HeedUuid, SerdeJson<Vec<Uuid>>>,
+    /// Length of each list of `thread_messages_db`, read without loading the list.
+    thread_message_counts_db: Database<HeedUuid, SerdeJson<u64>>,
+    /// Previous contents of each message, oldest first.
+    message_revisions_db: Database<HeedUuidTuple, SerdeJson<Vec<MessageRevision>>>,
     embeddings_db: Database<HeedUuid, SerdeJson<Embedding>>,
-    thread_creation_time_db: Database<HeedTimestampUuid, Unit>,
+    thread_creation_time_db: Database<HeedTenantTimestampUuid, Unit>,
+    /// Threads keyed by [`Thread::last_activity_at`].
+    thread_activity_time_db: Database<HeedTenantTimestampUuid, Unit>,
+    /// Number of threads of each tenant.
+    tenant_thread_counts_db: Database<HeedUuid, SerdeJson<u64>>,
+    /// Keyed by tenant, then thread; tells a thread's owner without loading it.
+    tenant_threads_db: Database<HeedUuidTuple, Unit>,
     message_creation_time_db: Database<HeedMessageCreationTimeId, Unit>,
+    message_embeddings_db: Database<HeedUuid, SerdeJson<(Uuid, Embedding)>>,
+    jobs_db: Database<HeedUuidTuple, SerdeJson<Job>>,
+    /// Keyed by tenant, then hash.
+    blobs_db: Database<HeedUuidString, SerdeJson<Blob>>,
+    blob_data_db: Database<HeedUuidString, Bytes>,
+    webhooks_db: Database<HeedUuid, SerdeJson<Webhook>>,
+    /// Keyed by webhook, then delivery.
+    webhook_deliveries_db: Database<HeedUuidTuple, SerdeJson<WebhookDelivery>>,
+    tenants_db: Database<HeedUuid, SerdeJson<Tenant>>,
+    /// Keyed by the hash of the key.
+    api_keys_db: Database<Str, SerdeJson<ApiKey>>,
+    thread_index: HeedIndex,
+    message_index: HeedIndex,
+    hnsw: Hnsw,
 }
 
 impl SynxHeedDatabase {
-    fn apply_pagination<T>(
-        items: Vec<T>,
-        limit: Option<usize>,
-        offset: Option<usize>,
-    ) -> (Vec<T>, usize, usize, usize) {
-        let total = items.len();
-        let offset = offset.unwrap_or(0);
-        let limit = limit.unwrap_or(total);
-        let paginated_items = items.into_iter().skip(offset).take(limit).collect();
-        (paginated_items, total, offset, limit)
-    }
-
     fn get_thread_with_embedding(
         &self,
         rtxn: &heed::RoTxn,
@@ -61,6 +96,112 @@ impl SynxHeedDatabase {
         Ok(thread)
     }
 
+    /// The thread, unless it is missing or owned by another tenant.
+    fn get_tenant_thread(
+        &self,
+        rtxn: &heed::RoTxn,
+        tenant_id: Uuid,
+        thread_id: Uuid,
+    ) -> Result<Option<Thread>, DatabaseError> {
+        Ok(self
+            .threads_db
+            .get(rtxn, &thread_id.into())
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?
+            .filter(|thread| thread.tenant_id == tenant_id))
+    }
+
+    /// Whether the thread exists and is owned by the tenant.
+    fn owns_thread(
+        &self,
+        rtxn: &heed::RoTxn,
+        tenant_id: Uuid,
+        thread_id: Uuid,
+    ) -> Result<bool, DatabaseError> {
+        Ok(self
+            .tenant_threads_db
+            .get(rtxn, &(tenant_id, thread_id).into())
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?
+            .is_some())
+    }
+
+    /// Ids of the tenant's threads, oldest first.
+    fn tenant_thread_ids(
+        &self,
+        rtxn: &heed::RoTxn,
+        tenant_id: Uuid,
+    ) -> Result<Vec<Uuid>, DatabaseError> {
+        self.thread_creation_time_db
+            .range(rtxn, &Self::exclusive_range(tenant_id, None, None))
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?
+            .map(|entry| {
+                entry
+                    .map(|(HeedUuidTimestampUuid((_, _, thread_id)), ())| thread_id)
+                    .map_err(|e| DatabaseError::QueryError(e.to_string()))
+            })
+            .collect()
+    }
+
+    fn get_tenant_webhook(
+        &self,
+        rtxn: &heed::RoTxn,
+        tenant_id: Uuid,
+        webhook_id: Uuid,
+    ) -> Result<Option<Webhook>, DatabaseError> {
+        Ok(self
+            .webhooks_db
+            .get(rtxn, &webhook_id.into())
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?
+            .filter(|webhook| webhook.tenant_id == tenant_id))
+    }
+
+    fn thread_vector(
+        &self,
+        rtxn: &RoTxn,
+        thread_id: Uuid,
+    ) -> Result<Option<Vec<f32>>, DatabaseError> {
+        Ok(self
+            .embeddings_db
+            .get(rtxn, &thread_id.into())
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?
+            .map(|embedding| embedding.to_vec()))
+    }
+
+    fn message_vector(
+        &self,
+        rtxn: &RoTxn,
+        message_id: Uuid,
+    ) -> Result<Option<Vec<f32>>, DatabaseError> {
+        Ok(self
+            .message_embeddings_db
+            .get(rtxn, &message_id.into())
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?
+            .map(|(_, embedding)| embedding.to_vec()))
+    }
+
+    fn index_reader<'a, 't>(
+        &'a self,
+        index: &'a HeedIndex,
+        rtxn: &'a RoTxn<'t>,
+    ) -> HeedIndexReader<'a, 't> {
+        HeedIndexReader {
+            db: self,
+            index,
+            rtxn,
+        }
+    }
+
+    fn index_writer<'a, 't>(
+        &'a self,
+        index: &'a HeedIndex,
+        wtxn: &'a mut RwTxn<'t>,
+    ) -> HeedIndexWriter<'a, 't> {
+        HeedIndexWriter {
+            db: self,
+            index,
+            wtxn,
+        }
+    }
+
     fn create_thread_internal(
         &self,
         wtxn: &mut heed::RwTxn,
@@ -72,27 +213,184 @@ impl SynxHeedDatabase {
         self.thread_messages_db
             .put(wtxn, &thread.id().into(), &Vec::new())
             .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
-        let timestamp = chrono::Utc::now().timestamp() as u64;
+        self.thread_message_counts_db
+            .put(wtxn, &thread.id().into(), &0)
+            .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
         self.thread_creation_time_db
-            .put(wtxn, &(timestamp, thread.id()).into(), &())
+            .put(
+                wtxn,
+                &(thread.tenant_id, thread.created_at, thread.id()).into(),
+                &(),
+            )
+            .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+        self.thread_activity_time_db
+            .put(
+                wtxn,
+                &(thread.tenant_id, thread.last_activity_at(), thread.id()).into(),
+                &(),
+            )
+            .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+        self.tenant_threads_db
+            .put(wtxn, &(thread.tenant_id, thread.id()).into(), &())
+            .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+        self.count_tenant_threads(wtxn, thread.tenant_id, 1)
+    }
+
+    /// Adds `delta` to the tenant's entry of `tenant_thread_counts_db`.
+    fn count_tenant_threads(
+        &self,
+        wtxn: &mut heed::RwTxn,
+        tenant_id: Uuid,
+        delta: i64,
+    ) -> Result<(), DatabaseError> {
+        let count = self
+            .tenant_thread_counts_db
+            .get(wtxn, &tenant_id.into())
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?
+            .unwrap_or(0);
+        self.tenant_thread_counts_db
+            .put(wtxn, &tenant_id.into(), &count.saturating_add_signed(delta))
             .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
         Ok(())
     }
 
-    fn delete_thread_internal(
+    /// Stores the thread with a new `last_message_at`, moving its activity index entry.
+    fn set_last_message_at(
         &self,
         wtxn: &mut heed::RwTxn,
-        thread_id: Uuid,
+        mut thread: Thread,
+        last_message_at: Option<u64>,
     ) -> Result<(), DatabaseError> {
+        self.thread_activity_time_db
+            .delete(
+                wtxn,
+                &(thread.tenant_id, thread.last_activity_at(), thread.id()).into(),
+            )
+            .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+        thread.set_last_message_at(last_message_at);
+        self.thread_activity_time_db
+            .put(
+                wtxn,
+                &(thread.tenant_id, thread.last_activity_at(), thread.id()).into(),
+                &(),
+            )
+            .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
         self.threads_db
-            .delete(wtxn, &thread_id.into())
+            .put(wtxn, &thread.id().into(), &thread)
             .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
-        self.thread_messages_db
+        Ok(())
+    }
+
+    /// Creation time of the latest message of the thread.
+    fn latest_message_time(
+        &self,
+        rtxn: &heed::RoTxn,
+        thread_id: Uuid,
+    ) -> Result<Option<u64>, DatabaseError> {
+        self.message_creation_time_db
+            .rev_range(rtxn, &Self::message_time_range(thread_id, None, None))
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?
+            .next()
+            .transpose()
+            .map(|entry| entry.map(|(HeedUuidTimestampUuid((_, created_at, _)), ())| created_at))
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))
+    }
+
+    /// Range of `message_creation_time_db` holding the thread's messages strictly
+    /// between `after` and `before`.
+    fn message_time_range(
+        thread_id: Uuid,
+        after: Option<MessageCursor>,
+        before: Option<MessageCursor>,
+    ) -> (
+        Bound<HeedMessageCreationTimeId>,
+        Bound<HeedMessageCreationTimeId>,
+    ) {
+        (
+            after.map_or(
+                Bound::Included((thread_id, 0, Uuid::nil()).into()),
+                |after| Bound::Excluded((thread_id, after.created_at, after.message_id).into()),
+            ),
+            before.map_or(
+                Bound::Included((thread_id, u64::MAX, Uuid::max()).into()),
+                |before| Bound::Excluded((thread_id, before.created_at, before.message_id).into()),
+            ),
+        )
+    }
+
+    fn thread_time_index(&self, sort: ThreadSort) -> Database<HeedTenantTimestampUuid, Unit> {
+        match sort {
+            ThreadSort::CreatedAt => self.thread_creation_time_db,
+            ThreadSort::LastActivity => self.thread_activity_time_db,
+        }
+    }
+
+    /// Exclusive bounds of `thread_creation_time_db` covering the query's creation time range.
+    fn creation_time_limits(query: &ListThreads) -> (Option<ThreadCursor>, Option<ThreadCursor>) {
+        (
+            query.created_after.map(|after| ThreadCursor {
+                timestamp: after,
+                thread_id: Uuid::max(),
+            }),
+            query.created_before.map(|before| ThreadCursor {
+                timestamp: before,
+                thread_id: Uuid::nil(),
+            }),
+        )
+    }
+
+    /// Range of a thread time index holding the tenant's threads strictly
+    /// between `lower` and `upper`.
+    fn exclusive_range(
+        tenant_id: Uuid,
+        lower: Option<ThreadCursor>,
+        upper: Option<ThreadCursor>,
+    ) -> (
+        Bound<HeedTenantTimestampUuid>,
+        Bound<HeedTenantTimestampUuid>,
+    ) {
+        (
+            lower.map_or(
+                Bound::Included((tenant_id, 0, Uuid::nil()).into()),
+                |lower| Bound::Excluded((tenant_id, lower.timestamp, lower.thread_id).into()),
+            ),
+            upper.map_or(
+                Bound::Included((tenant_id, u64::MAX, Uuid::max()).into()),
+                |upper| Bound::Excluded((tenant_id, upper.timestamp, upper.thread_id).into()),
+            ),
+        )
+    }
+
+    fn delete_thread_internal(
+        &self,
+        wtxn: &mut heed::RwTxn,
+        thread: &Thread,
+    ) -> Result<(), DatabaseError> {
+        let thread_id = thread.id();
+        self.thread_creation_time_db
+            .delete(
+                wtxn,
+                &(thread.tenant_id, thread.created_at, thread_id).into(),
+            )
+            .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+        self.thread_activity_time_db
+            .delete(
+                wtxn,
+                &(thread.tenant_id, thread.last_activity_at(), thread_id).into(),
+            )
+            .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+        self.tenant_threads_db
+            .delete(wtxn, &(thread.tenant_id, thread_id).into())
+            .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+        self.count_tenant_threads(wtxn, thread.tenant_id, -1)?;
+        self.threads_db
             .delete(wtxn, &thread_id.into())
             .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
         self.embeddings_db
             .delete(wtxn, &thread_id.into())
             .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+        self.hnsw
+            .remove(&mut self.index_writer(&self.thread_index, wtxn), thread_id)?;
 
         let message_ids = self
             .thread_messages_db
@@ -100,19 +398,19 @@ impl SynxHeedDatabase {
             .map_err(|e| DatabaseError::QueryError(e.to_string()))?
             .unwrap_or_default();
         for message_id in message_ids {
-            self.delete_message_internal(wtxn, thread_id, message_id)?;
+            self.delete_message_records(wtxn, thread.tenant_id, thread_id, message_id)?;
         }
+        self.thread_messages_db
+            .delete(wtxn, &thread_id.into())
+            .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+        self.thread_message_counts_db
+            .delete(wtxn, &thread_id.into())
+            .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
 
-        if let Some((HeedTimestampUuid((_, id)), _)) = self
-            .thread_creation_time_db
-            .get_greater_than_or_equal_to(wtxn, &(0, thread_id).into())
-            .map_err(|e| DatabaseError::QueryError(e.to_string()))?
-        {
-            if id == thread_id {
-                self.thread_creation_time_db
-                    .delete(wtxn, &(0, thread_id).into())
-                    .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
-            }
+        for job in self.get_thread_jobs_internal(wtxn, thread_id)? {
+            self.jobs_db
+                .delete(wtxn, &(thread_id, job.id()).into())
+                .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
         }
 
         Ok(())
@@ -131,9 +429,12 @@ impl SynxHeedDatabase {
             .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
         self.update_thread_messages(wtxn, thread_id, |ids| ids.push(message_id))?;
 
-        let timestamp = message.created_at().timestamp() as u64;
         self.message_creation_time_db
-            .put(wtxn, &(thread_id, timestamp, message_id).into(), &())
+            .put(
+                wtxn,
+                &(thread_id, message.created_at, message_id).into(),
+                &(),
+            )
             .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
 
         Ok(())
@@ -142,26 +443,172 @@ impl SynxHeedDatabase {
     fn delete_message_internal(
         &self,
         wtxn: &mut heed::RwTxn,
+        tenant_id: Uuid,
+        thread_id: Uuid,
+        message_id: Uuid,
+    ) -> Result<(), DatabaseError> {
+        self.delete_message_records(wtxn, tenant_id, thread_id, message_id)?;
+        self.update_thread_messages(wtxn, thread_id, |ids| ids.retain(|&id| id != message_id))
+    }
+
+    /// Removes the message with its revisions, index entries and embedding,
+    /// leaving the thread's message list to the caller.
+    fn delete_message_records(
+        &self,
+        wtxn: &mut heed::RwTxn,
+        tenant_id: Uuid,
         thread_id: Uuid,
         message_id: Uuid,
     ) -> Result<(), DatabaseError> {
+        if let Some(message) = self
+            .messages_db
+            .get(wtxn, &(thread_id, message_id).into())
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?
+        {
+            self.message_creation_time_db
+                .delete(wtxn, &(thread_id, message.created_at, message_id).into())
+                .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+            self.release_blobs(wtxn, tenant_id, &message.content)?;
+        }
+        let revisions = self
+            .message_revisions_db
+            .get(wtxn, &(thread_id, message_id).into())
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?
+            .unwrap_or_default();
+        for revision in revisions {
+            self.release_blobs(wtxn, tenant_id, &revision.content)?;
+        }
         self.messages_db
             .delete(wtxn, &(thread_id, message_id).into())
             .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
-        self.update_thread_messages(wtxn, thread_id, |ids| ids.retain(|&id| id != message_id))?;
+        self.message_revisions_db
+            .delete(wtxn, &(thread_id, message_id).into())
+            .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+        self.message_embeddings_db
+            .delete(wtxn, &message_id.into())
+            .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+        self.hnsw.remove(
+            &mut self.index_writer(&self.message_index, wtxn),
+            message_id,
+        )?;
 
-        if let Some((HeedMessageCreationTimeId((t_id, _, m_id)), _)) = self
-            .message_creation_time_db
-            .get_greater_than_or_equal_to(wtxn, &(thread_id, 0, message_id).into())
-            .map_err(|e| DatabaseError::QueryError(e.to_string()))?
-        {
-            if t_id == thread_id && m_id == message_id {
-                self.message_creation_time_db
-                    .delete(wtxn, &(thread_id, 0, message_id).into())
+        Ok(())
+    }
+
+    /// Counts a reference to each of the tenant's blobs of `content`, failing if
+    /// one is missing.
+    fn retain_blobs(
+        &self,
+        wtxn: &mut heed::RwTxn,
+        tenant_id: Uuid,
+        content: &Content,
+    ) -> Result<(), DatabaseError> {
+        for hash in content.blob_hashes() {
+            let key = (tenant_id, hash.to_string()).into();
+            let mut blob = self
+                .blobs_db
+                .get(wtxn, &key)
+                .map_err(|e| DatabaseError::QueryError(e.to_string()))?
+                .ok_or_else(|| {
+                    DatabaseError::InvalidInput(format!("blob {} does not exist", hash))
+                })?;
+            blob.ref_count += 1;
+            self.blobs_db
+                .put(wtxn, &key, &blob)
+                .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+        }
+        Ok(())
+    }
+
+    /// Drops a reference to each of the tenant's blobs of `content`, removing blobs
+    /// left unreferenced past their grace period.
+    fn release_blobs(
+        &self,
+        wtxn: &mut heed::RwTxn,
+        tenant_id: Uuid,
+        content: &Content,
+    ) -> Result<(), DatabaseError> {
+        let cutoff = Blob::collection_cutoff();
+        for hash in content.blob_hashes() {
+            let key = (tenant_id, hash.to_string()).into();
+            let Some(mut blob) = self
+                .blobs_db
+                .get(wtxn, &key)
+                .map_err(|e| DatabaseError::QueryError(e.to_string()))?
+            else {
+                continue;
+            };
+            blob.ref_count = blob.ref_count.saturating_sub(1);
+            if blob.is_collectable(cutoff) {
+                self.blobs_db
+                    .delete(wtxn, &key)
+                    .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+                self.blob_data_db
+                    .delete(wtxn, &key)
+                    .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+            } else {
+                self.blobs_db
+                    .put(wtxn, &key, &blob)
                     .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
             }
         }
+        Ok(())
+    }
+
+    fn get_thread_jobs_internal(
+        &self,
+        rtxn: &heed::RoTxn,
+        thread_id: Uuid,
+    ) -> Result<Vec<Job>, DatabaseError> {
+        let jobs = self
+            .jobs_db
+            .remap_key_type::<HeedUuid>()
+            .prefix_iter(rtxn, &thread_id.into())
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?
+            .remap_key_type::<HeedUuidTuple>()
+            .map(|entry| {
+                entry
+                    .map(|(_, job)| job)
+                    .map_err(|e| DatabaseError::SerializationError(e.to_string()))
+            })
+            .collect::<Result<Vec<Job>, DatabaseError>>()?;
+        Ok(jobs)
+    }
+
+    fn get_webhook_deliveries_internal(
+        &self,
+        rtxn: &heed::RoTxn,
+        webhook_id: Uuid,
+    ) -> Result<Vec<WebhookDelivery>, DatabaseError> {
+        let deliveries = self
+            .webhook_deliveries_db
+            .remap_key_type::<HeedUuid>()
+            .prefix_iter(rtxn, &webhook_id.into())
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?
+            .remap_key_type::<HeedUuidTuple>()
+            .map(|entry| {
+                entry
+                    .map(|(_, delivery)| delivery)
+                    .map_err(|e| DatabaseError::SerializationError(e.to_string()))
+            })
+            .collect::<Result<Vec<WebhookDelivery>, DatabaseError>>()?;
+        Ok(deliveries)
+    }
 
+    /// Removes the webhook together with its deliveries.
+    fn delete_webhook_internal(
+        &self,
+        wtxn: &mut heed::RwTxn,
+        webhook_id: Uuid,
+    ) -> Result<(), DatabaseError> {
+        self.webhooks_db
+            .delete(wtxn, &webhook_id.into())
+            .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+        for delivery in self.get_webhook_deliveries_internal(wtxn, webhook_id)? {
+            self.webhook_deliveries_db
+                .delete(wtxn, &(webhook_id, delivery.id()).into())
+                .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+        }
         Ok(())
     }
 
@@ -183,6 +630,9 @@ impl SynxHeedDatabase {
         self.thread_messages_db
             .put(wtxn, &thread_id.into(), &message_ids)
             .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+        self.thread_message_counts_db
+            .put(wtxn, &thread_id.into(), &(message_ids.len() as u64))
+            .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
         Ok(())
     }
 
@@ -214,6 +664,23 @@ impl SynxHeedDatabase {
                 .map_err(|e| DatabaseError::QueryError(e.to_string()))?
                 .ok_or_else(|| DatabaseError::NotFound)?
         };
+        let thread_message_counts_db = if create_databases {
+            env.create_database(&mut wtxn, Some("thread_message_counts"))
+                .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?
+        } else {
+            env.open_database(&wtxn, Some("thread_message_counts"))
+                .map_err(|e| DatabaseError::QueryError(e.to_string()))?
+                .ok_or_else(|| DatabaseError::NotFound)?
+        };
+        let message_revisions_db = if create_databases {
+            env.create_database(&mut wtxn, Some("message_revisions"))
+                .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?
+        } else {
+            env.open_database(&wtxn, Some("message_revisions"))
+                .map_err(|e| DatabaseError::QueryError(e.to_string()))?
+                .ok_or_else(|| DatabaseError::NotFound)?
+        };
+
         let embeddings_db = if create_databases {
             env.create_database(&mut wtxn, Some("embeddings"))
                 .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?
@@ -230,6 +697,30 @@ impl SynxHeedDatabase {
                 .map_err(|e| DatabaseError::QueryError(e.to_string()))?
                 .ok_or_else(|| DatabaseError::NotFound)?
         };
+        let thread_activity_time_db = if create_databases {
+            env.create_database(&mut wtxn, Some("thread_activity_time"))
+                .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?
+        } else {
+            env.open_database(&wtxn, Some("thread_activity_time"))
+                .map_err(|e| DatabaseError::QueryError(e.to_string()))?
+                .ok_or_else(|| DatabaseError::NotFound)?
+        };
+        let tenant_thread_counts_db = if create_databases {
+            env.create_database(&mut wtxn, Some("tenant_thread_counts"))
+                .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?
+        } else {
+            env.open_database(&wtxn, Some("tenant_thread_counts"))
+                .map_err(|e| DatabaseError::QueryError(e.to_string()))?
+                .ok_or_else(|| DatabaseError::NotFound)?
+        };
+        let tenant_threads_db = if create_databases {
+            env.create_database(&mut wtxn, Some("tenant_threads"))
+                .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?
+        } else {
+            env.open_database(&wtxn, Some("tenant_threads"))
+                .map_err(|e| DatabaseError::QueryError(e.to_string()))?
+                .ok_or_else(|| DatabaseError::NotFound)?
+        };
         let message_creation_time_db = if create_databases {
             env.create_database(&mut wtxn, Some("message_creation_time"))
                 .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?
@@ -238,347 +729,1844 @@ impl SynxHeedDatabase {
                 .map_err(|e| DatabaseError::QueryError(e.to_string()))?
                 .ok_or_else(|| DatabaseError::NotFound)?
         };
-        wtxn.commit()
-            .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
-
-        Ok(Self {
-            env,
-            threads_db,
-            messages_db,
-            thread_messages_db,
-            embeddings_db,
-            thread_creation_time_db,
-            message_creation_time_db,
-        })
-    }
-}
-
-#[async_trait::async_trait]
-impl Db for SynxHeedDatabase {
-    async fn get_threads_with_embeddings(
-        &self,
-        thread_ids: &[Uuid],
-    ) -> Result<Vec<Thread>, DatabaseError> {
-        let rtxn = self
-            .env
-            .read_txn()
-            .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
-        let threads = thread_ids
-            .iter()
-            .filter_map(|&id| self.get_thread_with_embedding(&rtxn, &id).transpose())
-            .collect::<Result<Vec<Thread>, DatabaseError>>()?;
-        Ok(threads)
-    }
-
-    async fn update_thread_summary_and_embedding(
-        &self,
-        thread_id: Uuid,
-        summary: String,
-        embedding: Embedding,
-    ) -> Result<(), DatabaseError> {
-        let mut wtxn = self
-            .env
-            .write_txn()
-            .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
-
-        if let Some(mut thread) = self
-            .threads_db
-            .get(&wtxn, &thread_id.into())
-            .map_err(|e| DatabaseError::QueryError(e.to_string()))?
-        {
-            thread.set_summary(summary);
-            self.threads_db
-                .put(&mut wtxn, &thread_id.into(), &thread)
-                .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+        let message_embeddings_db = if create_databases {
+            env.create_database(&mut wtxn, Some("message_embeddings"))
+                .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?
         } else {
-            return Err(DatabaseError::NotFound);
-        }
+            env.open_database(&wtxn, Some("message_embeddings"))
+                .map_err(|e| DatabaseError::QueryError(e.to_string()))?
+                .ok_or_else(|| DatabaseError::NotFound)?
+        };
+        let jobs_db = if create_databases {
+            env.create_database(&mut wtxn, Some("jobs"))
+                .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?
+        } else {
+            env.open_database(&wtxn, Some("jobs"))
+                .map_err(|e| DatabaseError::QueryError(e.to_string()))?
+                .ok_or_else(|| DatabaseError::NotFound)?
+        };
+        let blobs_db = if create_databases {
+            env.create_database(&mut wtxn, Some("blobs"))
+                .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?
+        } else {
+            env.open_database(&wtxn, Some("blobs"))
+                .map_err(|e| DatabaseError::QueryError(e.to_string()))?
+                .ok_or_else(|| DatabaseError::NotFound)?
+        };
+
+        let blob_data_db = if create_databases {
+            env.create_database(&mut wtxn, Some("blob_data"))
+                .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?
+        } else {
+            env.open_database(&wtxn, Some("blob_data"))
+                .map_err(|e| DatabaseError::QueryError(e.to_string()))?
+                .ok_or_else(|| DatabaseError::NotFound)?
+        };
+        let webhooks_db = if create_databases {
+            env.create_database(&mut wtxn, Some("webhooks"))
+                .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?
+        } else {
+            env.open_database(&wtxn, Some("webhooks"))
+                .map_err(|e| DatabaseError::QueryError(e.to_string()))?
+                .ok_or_else(|| DatabaseError::NotFound)?
+        };
+        let webhook_deliveries_db = if create_databases {
+            env.create_database(&mut wtxn, Some("webhook_deliveries"))
+                .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?
+        } else {
+            env.open_database(&wtxn, Some("webhook_deliveries"))
+                .map_err(|e| DatabaseError::QueryError(e.to_string()))?
+                .ok_or_else(|| DatabaseError::NotFound)?
+        };
+        let tenants_db: Database<HeedUuid, SerdeJson<Tenant>> = if create_databases {
+            env.create_database(&mut wtxn, Some("tenants"))
+                .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?
+        } else {
+            env.open_database(&wtxn, Some("tenants"))
+                .map_err(|e| DatabaseError::QueryError(e.to_string()))?
+                .ok_or_else(|| DatabaseError::NotFound)?
+        };
+        let api_keys_db = if create_databases {
+            env.create_database(&mut wtxn, Some("api_keys"))
+                .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?
+        } else {
+            env.open_database(&wtxn, Some("api_keys"))
+                .map_err(|e| DatabaseError::QueryError(e.to_string()))?
+                .ok_or_else(|| DatabaseError::NotFound)?
+        };
+        if tenants_db
+            .get(&wtxn, &Tenant::DEFAULT_ID.into())
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?
+            .is_none()
+        {
+            tenants_db
+                .put(
+                    &mut wtxn,
+                    &Tenant::DEFAULT_ID.into(),
+                    &Tenant::default_tenant(),
+                )
+                .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+        }
+
+        let thread_index_db = if create_databases {
+            env.create_database(&mut wtxn, Some("thread_index"))
+                .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?
+        } else {
+            env.open_database(&wtxn, Some("thread_index"))
+                .map_err(|e| DatabaseError::QueryError(e.to_string()))?
+                .ok_or_else(|| DatabaseError::NotFound)?
+        };
+        let thread_index_entry_point_db = if create_databases {
+            env.create_database(&mut wtxn, Some("thread_index_entry_point"))
+                .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?
+        } else {
+            env.open_database(&wtxn, Some("thread_index_entry_point"))
+                .map_err(|e| DatabaseError::QueryError(e.to_string()))?
+                .ok_or_else(|| DatabaseError::NotFound)?
+        };
+        let message_index_db = if create_databases {
+            env.create_database(&mut wtxn, Some("message_index"))
+                .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?
+        } else {
+            env.open_database(&wtxn, Some("message_index"))
+                .map_err(|e| DatabaseError::QueryError(e.to_string()))?
+                .ok_or_else(|| DatabaseError::NotFound)?
+        };
+        let message_index_entry_point_db = if create_databases {
+            env.create_database(&mut wtxn, Some("message_index_entry_point"))
+                .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?
+        } else {
+            env.open_database(&wtxn, Some("message_index_entry_point"))
+                .map_err(|e| DatabaseError::QueryError(e.to_string()))?
+                .ok_or_else(|| DatabaseError::NotFound)?
+        };
+        wtxn.commit()
+            .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+
+        let database = Self {
+            env,
+            threads_db,
+            messages_db,
+            thread_messages_db,
+            thread_message_counts_db,
+            message_revisions_db,
+            embeddings_db,
+            thread_creation_time_db,
+            thread_activity_time_db,
+            tenant_thread_counts_db,
+            tenant_threads_db,
+            message_creation_time_db,
+            message_embeddings_db,
+            jobs_db,
+            blobs_db,
+            blob_data_db,
+            webhooks_db,
+            webhook_deliveries_db,
+            tenants_db,
+            api_keys_db,
+            thread_index: HeedIndex {
+                nodes_db: thread_index_db,
+                entry_point_db: thread_index_entry_point_db,
+                vector: Self::thread_vector,
+            },
+            message_index: HeedIndex {
+                nodes_db: message_index_db,
+                entry_point_db: message_index_entry_point_db,
+                vector: Self::message_vector,
+            },
+            hnsw: Hnsw::default(),
+        };
+        database.index_existing_embeddings()?;
+        database.index_message_times()?;
+        database.index_thread_times()?;
+        database.count_thread_messages()?;
+        database.index_thread_tenants()?;
+        database.scope_blobs()?;
+
+        Ok(database)
+    }
+
+    /// Builds the thread index for environments created before it existed.
+    fn index_existing_embeddings(&self) -> Result<(), DatabaseError> {
+        let mut wtxn = self
+            .env
+            .write_txn()
+            .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+
+        if !self
+            .thread_index
+            .nodes_db
+            .is_empty(&wtxn)
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?
+        {
+            return Ok(());
+        }
+
+        let embeddings: Vec<(Uuid, Embedding)> = self
+            .embeddings_db
+            .iter(&wtxn)
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?
+            .flatten()
+            .map(|(k, v)| (k.0, v))
+            .collect();
+        for (thread_id, embedding) in embeddings {
+            self.hnsw.insert(
+                &mut self.index_writer(&self.thread_index, &mut wtxn),
+                thread_id,
+                &embedding.to_vec(),
+            )?;
+        }
+
+        wtxn.commit()
+            .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+        Ok(())
+    }
+
+    /// Rebuilds `message_creation_time_db` for environments written when it was
+    /// keyed by seconds and deleted messages could leave entries behind.
+    fn index_message_times(&self) -> Result<(), DatabaseError> {
+        let mut wtxn = self
+            .env
+            .write_txn()
+            .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+
+        let first_entry_matches = match self
+            .message_creation_time_db
+            .first(&wtxn)
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?
+        {
+            Some((HeedUuidTimestampUuid((thread_id, created_at, message_id)), ())) => self
+                .messages_db
+                .get(&wtxn, &(thread_id, message_id).into())
+                .map_err(|e| DatabaseError::QueryError(e.to_string()))?
+                .is_some_and(|message| message.created_at == created_at),
+            None => true,
+        };
+        if first_entry_matches
+            && self
+                .message_creation_time_db
+                .len(&wtxn)
+                .map_err(|e| DatabaseError::QueryError(e.to_string()))?
+                == self
+                    .messages_db
+                    .len(&wtxn)
+                    .map_err(|e| DatabaseError::QueryError(e.to_string()))?
+        {
+            return Ok(());
+        }
+
+        let keys: Vec<(Uuid, u64, Uuid)> = self
+            .messages_db
+            .iter(&wtxn)
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?
+            .flatten()
+            .map(|(_, message)| (message.thread_id, message.created_at, message.id()))
+            .collect();
+        self.message_creation_time_db
+            .clear(&mut wtxn)
+            .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+        for key in keys {
+            self.message_creation_time_db
+                .put(&mut wtxn, &key.into(), &())
+                .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+        }
+
+        wtxn.commit()
+            .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+        Ok(())
+    }
+
+    /// Rebuilds the thread time indexes and `tenant_thread_counts_db` for
+    /// environments written before they were keyed by tenant. The oldest of
+    /// those kept creation times in seconds and only in `thread_creation_time_db`,
+    /// so threads without a creation time take it from there.
+    fn index_thread_times(&self) -> Result<(), DatabaseError> {
+        let mut wtxn = self
+            .env
+            .write_txn()
+            .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+
+        let keyed_by_tenant = match self
+            .thread_activity_time_db
+            .remap_key_type::<Bytes>()
+            .first(&wtxn)
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?
+        {
+            Some((key, ())) => key.len() == 40,
+            None => self
+                .threads_db
+                .is_empty(&wtxn)
+                .map_err(|e| DatabaseError::QueryError(e.to_string()))?,
+        };
+        if keyed_by_tenant {
+            return Ok(());
+        }
+
+        let created_at_secs: HashMap<Uuid, u64> = self
+            .thread_creation_time_db
+            .remap_key_type::<HeedTimestampUuid>()
+            .iter(&wtxn)
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?
+            .flatten()
+            .map(|(HeedTimestampUuid((secs, thread_id)), ())| (thread_id, secs))
+            .collect();
+        let threads: Vec<Thread> = self
+            .threads_db
+            .iter(&wtxn)
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?
+            .flatten()
+            .map(|(_, thread)| thread)
+            .collect();
+
+        self.thread_creation_time_db
+            .clear(&mut wtxn)
+            .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+        self.thread_activity_time_db
+            .clear(&mut wtxn)
+            .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+        self.tenant_thread_counts_db
+            .clear(&mut wtxn)
+            .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+        for mut thread in threads {
+            if thread.created_at == 0 {
+                thread.created_at = created_at_secs
+                    .get(&thread.id())
+                    .map_or(0, |secs| secs * 1000);
+            }
+            let last_message_at = self.latest_message_time(&wtxn, thread.id())?;
+            thread.set_last_message_at(last_message_at);
+            self.threads_db
+                .put(&mut wtxn, &thread.id().into(), &thread)
+                .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+            self.thread_creation_time_db
+                .put(
+                    &mut wtxn,
+                    &(thread.tenant_id, thread.created_at, thread.id()).into(),
+                    &(),
+                )
+                .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+            self.thread_activity_time_db
+                .put(
+                    &mut wtxn,
+                    &(thread.tenant_id, thread.last_activity_at(), thread.id()).into(),
+                    &(),
+                )
+                .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+            self.count_tenant_threads(&mut wtxn, thread.tenant_id, 1)?;
+        }
+
+        wtxn.commit()
+            .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+        Ok(())
+    }
+
+    /// Fills in `thread_message_counts_db` for environments created before it
+    /// existed.
+    fn count_thread_messages(&self) -> Result<(), DatabaseError> {
+        let mut wtxn = self
+            .env
+            .write_txn()
+            .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+
+        if self
+            .thread_message_counts_db
+            .len(&wtxn)
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?
+            == self
+                .thread_messages_db
+                .len(&wtxn)
+                .map_err(|e| DatabaseError::QueryError(e.to_string()))?
+        {
+            return Ok(());
+        }
+
+        let counts: Vec<(Uuid, u64)> = self
+            .thread_messages_db
+            .iter(&wtxn)
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?
+            .flatten()
+            .map(|(k, message_ids)| (k.0, message_ids.len() as u64))
+            .collect();
+        for (thread_id, count) in counts {
+            self.thread_message_counts_db
+                .put(&mut wtxn, &thread_id.into(), &count)
+                .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+        }
+
+        wtxn.commit()
+            .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+        Ok(())
+    }
+
+    /// Fills in `tenant_threads_db` for environments created before it existed.
+    fn index_thread_tenants(&self) -> Result<(), DatabaseError> {
+        let mut wtxn = self
+            .env
+            .write_txn()
+            .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+
+        if self
+            .tenant_threads_db
+            .len(&wtxn)
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?
+            == self
+                .threads_db
+                .len(&wtxn)
+                .map_err(|e| DatabaseError::QueryError(e.to_string()))?
+        {
+            return Ok(());
+        }
+
+        let owners: Vec<(Uuid, Uuid)> = self
+            .threads_db
+            .iter(&wtxn)
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?
+            .flatten()
+            .map(|(k, thread)| (thread.tenant_id, k.0))
+            .collect();
+        for owner in owners {
+            self.tenant_threads_db
+                .put(&mut wtxn, &owner.into(), &())
+                .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+        }
+
+        wtxn.commit()
+            .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+        Ok(())
+    }
+
+    /// Moves blobs stored before they were kept per tenant, keyed by hash alone,
+    /// to the default tenant, which owned every thread at the time.
+    fn scope_blobs(&self) -> Result<(), DatabaseError> {
+        let mut wtxn = self
+            .env
+            .write_txn()
+            .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+
+        let legacy_blobs = self.blobs_db.remap_key_type::<Str>();
+        let legacy_data = self.blob_data_db.remap_key_type::<Str>();
+        // Hashes are 64 hex digits, shorter than any key that starts with a tenant.
+        let hashes: Vec<String> = self
+            .blobs_db
+            .remap_types::<Bytes, DecodeIgnore>()
+            .iter(&wtxn)
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?
+            .flatten()
+            .filter(|(key, ())| key.len() == 64)
+            .filter_map(|(key, ())| std::str::from_utf8(key).ok().map(str::to_string))
+            .collect();
+        if hashes.is_empty() {
+            return Ok(());
+        }
+
+        for hash in hashes {
+            let blob = legacy_blobs
+                .get(&wtxn, &hash)
+                .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
+            let data = legacy_data
+                .get(&wtxn, &hash)
+                .map_err(|e| DatabaseError::QueryError(e.to_string()))?
+                .map(<[u8]>::to_vec);
+            legacy_blobs
+                .delete(&mut wtxn, &hash)
+                .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+            legacy_data
+                .delete(&mut wtxn, &hash)
+                .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+            let (Some(blob), Some(data)) = (blob, data) else {
+                continue;
+            };
+            let key = (Tenant::DEFAULT_ID, hash).into();
+            self.blobs_db
+                .put(&mut wtxn, &key, &blob)
+                .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+            self.blob_data_db
+                .put(&mut wtxn, &key, &data)
+                .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+        }
+
+        wtxn.commit()
+            .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl Db for SynxHeedDatabase {
+    async fn get_threads_with_embeddings(
+        &self,
+        tenant_id: Uuid,
+        thread_ids: &[Uuid],
+    ) -> Result<Vec<Thread>, DatabaseError> {
+        let rtxn = self
+            .env
+            .read_txn()
+            .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+        let threads = thread_ids
+            .iter()
+            .filter_map(|&id| self.get_thread_with_embedding(&rtxn, &id).transpose())
+            .filter(|thread| !matches!(thread, Ok(thread) if thread.tenant_id != tenant_id))
+            .collect::<Result<Vec<Thread>, DatabaseError>>()?;
+        Ok(threads)
+    }
+
+    async fn search_thread_embeddings(
+        &self,
+        tenant_id: Uuid,
+        embedding: &Embedding,
+        thread_ids: Option<&[Uuid]>,
+        metadata: &Metadata,
+        limit: usize,
+    ) -> Result<Vec<(Thread, f32)>, DatabaseError> {
+        let rtxn = self
+            .env
+            .read_txn()
+            .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+        let thread_ids: Option<HashSet<Uuid>> =
+            thread_ids.map(|thread_ids| thread_ids.iter().copied().collect());
+
+        let index = self.index_reader(&self.thread_index, &rtxn);
+        let vector = embedding.to_vec();
+        let accepts = |id: &Uuid| {
+            thread_ids
+                .as_ref()
+                .is_none_or(|thread_ids| thread_ids.contains(id))
+                && matches!(self.owns_thread(&rtxn, tenant_id, *id), Ok(true))
+                && (metadata.is_empty()
+                    || matches!(
+                        self.threads_db.get(&rtxn, &(*id).into()),
+                        Ok(Some(thread)) if metadata::matches(&thread.metadata, metadata)
+                    ))
+        };
+
+        let mut results = self.hnsw.search(&index, &vector, limit, &accepts)?;
+        if results.len() < limit {
+            let candidates = match &thread_ids {
+                Some(thread_ids) => thread_ids.iter().copied().collect(),
+                None => self.tenant_thread_ids(&rtxn, tenant_id)?,
+            };
+            results = self.hnsw.search_exact(
+                &index,
+                &vector,
+                limit,
+                candidates.into_iter().filter(accepts),
+            )?;
+        }
+
+        results
+            .into_iter()
+            .filter_map(|(id, score)| {
+                self.get_thread_with_embedding(&rtxn, &id)
+                    .transpose()
+                    .map(|thread| thread.map(|thread| (thread, score)))
+            })
+            .collect()
+    }
+
+    async fn update_thread_summary_and_embedding(
+        &self,
+        tenant_id: Uuid,
+        thread_id: Uuid,
+        expected_cursor: Option<SummaryCursor>,
+        cursor: SummaryCursor,
+        summary: String,
+        embedding: Embedding,
+    ) -> Result<(), DatabaseError> {
+        let mut wtxn = self
+            .env
+            .write_txn()
+            .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+
+        let mut thread = self
+            .get_tenant_thread(&wtxn, tenant_id, thread_id)?
+            .ok_or(DatabaseError::NotFound)?;
+        if thread.summary_cursor != expected_cursor {
+            return Err(DatabaseError::Conflict(format!(
+                "summary of thread {} was updated concurrently",
+                thread_id
+            )));
+        }
+
+        thread.set_summary(summary, cursor);
+        self.threads_db
+            .put(&mut wtxn, &thread_id.into(), &thread)
+            .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
 
         self.embeddings_db
             .put(&mut wtxn, &thread_id.into(), &embedding)
             .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+        self.hnsw.insert(
+            &mut self.index_writer(&self.thread_index, &mut wtxn),
+            thread_id,
+            &embedding.to_vec(),
+        )?;
+
+        wtxn.commit()
+            .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+        Ok(())
+    }
+
+    async fn search_message_embeddings(
+        &self,
+        tenant_id: Uuid,
+        embedding: &Embedding,
+        thread_ids: Option<&[Uuid]>,
+        limit: usize,
+    ) -> Result<Vec<(Message, f32)>, DatabaseError> {
+        let rtxn = self
+            .env
+            .read_txn()
+            .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+        let thread_ids: Option<HashSet<Uuid>> =
+            thread_ids.map(|thread_ids| thread_ids.iter().copied().collect());
+
+        let index = self.index_reader(&self.message_index, &rtxn);
+        let vector = embedding.to_vec();
+        let accepts = |id: &Uuid| {
+            let Ok(Some((thread_id, _))) = self.message_embeddings_db.get(&rtxn, &(*id).into())
+            else {
+                return false;
+            };
+            thread_ids
+                .as_ref()
+                .is_none_or(|thread_ids| thread_ids.contains(&thread_id))
+                && matches!(self.owns_thread(&rtxn, tenant_id, thread_id), Ok(true))
+        };
+
+        let mut results = self.hnsw.search(&index, &vector, limit, &accepts)?;
+        if results.len() < limit {
+            let searched_threads = match &thread_ids {
+                Some(thread_ids) => thread_ids.iter().copied().collect(),
+                None => self.tenant_thread_ids(&rtxn, tenant_id)?,
+            };
+            let mut candidates = Vec::new();
+            for thread_id in searched_threads {
+                candidates.extend(
+                    self.thread_messages_db
+                        .get(&rtxn, &thread_id.into())
+                        .map_err(|e| DatabaseError::QueryError(e.to_string()))?
+                        .unwrap_or_default(),
+                );
+            }
+            results = self.hnsw.search_exact(
+                &index,
+                &vector,
+                limit,
+                candidates.into_iter().filter(accepts),
+            )?;
+        }
+
+        let mut messages = Vec::with_capacity(results.len());
+        for (message_id, score) in results {
+            let Some((thread_id, _)) = self
+                .message_embeddings_db
+                .get(&rtxn, &message_id.into())
+                .map_err(|e| DatabaseError::QueryError(e.to_string()))?
+            else {
+                continue;
+            };
+            if let Some(message) = self
+                .messages_db
+                .get(&rtxn, &(thread_id, message_id).into())
+                .map_err(|e| DatabaseError::QueryError(e.to_string()))?
+            {
+                messages.push((message, score));
+            }
+        }
+
+        Ok(messages)
+    }
+
+    async fn update_message_embedding(
+        &self,
+        tenant_id: Uuid,
+        thread_id: Uuid,
+        message_id: Uuid,
+        embedding: Option<Embedding>,
+    ) -> Result<(), DatabaseError> {
+        let mut wtxn = self
+            .env
+            .write_txn()
+            .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+
+        if self
+            .get_tenant_thread(&wtxn, tenant_id, thread_id)?
+            .is_none()
+            || self
+                .messages_db
+                .get(&wtxn, &(thread_id, message_id).into())
+                .map_err(|e| DatabaseError::QueryError(e.to_string()))?
+                .is_none()
+        {
+            return Err(DatabaseError::NotFound);
+        }
+
+        match embedding {
+            Some(embedding) => {
+                let vector = embedding.to_vec();
+                self.message_embeddings_db
+                    .put(&mut wtxn, &message_id.into(), &(thread_id, embedding))
+                    .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+                self.hnsw.insert(
+                    &mut self.index_writer(&self.message_index, &mut wtxn),
+                    message_id,
+                    &vector,
+                )?;
+            }
+            None => {
+                self.message_embeddings_db
+                    .delete(&mut wtxn, &message_id.into())
+                    .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+                self.hnsw.remove(
+                    &mut self.index_writer(&self.message_index, &mut wtxn),
+                    message_id,
+                )?;
+            }
+        }
+
+        wtxn.commit()
+            .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+        Ok(())
+    }
+
+    async fn create_thread(&self, tenant_id: Uuid) -> Result<Thread, DatabaseError> {
+        let thread = Thread::new(tenant_id);
+        let mut wtxn = self
+            .env
+            .write_txn()
+            .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+
+        self.create_thread_internal(&mut wtxn, &thread)?;
+
+        if self
+            .threads_db
+            .get(&wtxn, &thread.id().into())
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?
+            .is_none()
+        {
+            return Err(DatabaseError::OperationFailed(
+                "Thread not found after insertion".to_string(),
+            ));
+        }
+
+        wtxn.commit()
+            .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+        Ok(thread)
+    }
+
+    async fn delete_thread(&self, tenant_id: Uuid, thread_id: Uuid) -> Result<(), DatabaseError> {
+        let mut wtxn = self
+            .env
+            .write_txn()
+            .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+
+        if let Some(thread) = self.get_tenant_thread(&wtxn, tenant_id, thread_id)? {
+            self.delete_thread_internal(&mut wtxn, &thread)?;
+            wtxn.commit()
+                .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+            Ok(())
+        } else {
+            Err(DatabaseError::NotFound)
+        }
+    }
+
+    async fn create_message(
+        &self,
+        tenant_id: Uuid,
+        thread_id: Uuid,
+        input: CreateMessage,
+    ) -> Result<Message, DatabaseError> {
+        let mut wtxn = self
+            .env
+            .write_txn()
+            .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+
+        let Some(thread) = self.get_tenant_thread(&wtxn, tenant_id, thread_id)? else {
+            return Err(DatabaseError::NotFound);
+        };
+        if let Some(parent_id) = input.parent_id {
+            if self
+                .messages_db
+                .get(&wtxn, &(thread_id, parent_id).into())
+                .map_err(|e| DatabaseError::QueryError(e.to_string()))?
+                .is_none()
+            {
+                return Err(DatabaseError::InvalidInput(format!(
+                    "parent message {} is not in thread {}",
+                    parent_id, thread_id
+                )));
+            }
+        }
+
+        self.retain_blobs(&mut wtxn, tenant_id, &input.content)?;
+
+        let message = input.into_message(&thread);
+        self.create_message_internal(&mut wtxn, &message)?;
+        self.set_last_message_at(&mut wtxn, thread, Some(message.created_at))?;
+
+        wtxn.commit()
+            .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+        Ok(message)
+    }
+
+    async fn import_threads(
+        &self,
+        threads: Vec<(Thread, Vec<Message>)>,
+    ) -> Result<(), DatabaseError> {
+        let mut wtxn = self
+            .env
+            .write_txn()
+            .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+
+        for (thread, messages) in &threads {
+            self.create_thread_internal(&mut wtxn, thread)?;
+            for message in messages {
+                self.retain_blobs(&mut wtxn, thread.tenant_id, &message.content)?;
+                self.create_message_internal(&mut wtxn, message)?;
+            }
+        }
+
+        wtxn.commit()
+            .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+        Ok(())
+    }
+
+    async fn update_message(
+        &self,
+        tenant_id: Uuid,
+        thread_id: Uuid,
+        message_id: Uuid,
+        content: UpdateMessage,
+    ) -> Result<Message, DatabaseError> {
+        let mut wtxn = self
+            .env
+            .write_txn()
+            .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+
+        if self
+            .get_tenant_thread(&wtxn, tenant_id, thread_id)?
+            .is_none()
+        {
+            return Err(DatabaseError::NotFound);
+        }
+        if let Some(mut message) = self
+            .messages_db
+            .get(&wtxn, &(thread_id, message_id).into())
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?
+        {
+            self.retain_blobs(&mut wtxn, tenant_id, &content.content)?;
+            let mut revisions = self
+                .message_revisions_db
+                .get(&wtxn, &(thread_id, message_id).into())
+                .map_err(|e| DatabaseError::QueryError(e.to_string()))?
+                .unwrap_or_default();
+            revisions.push(message.update_content(content, revisions.len() as u32 + 1));
+            self.messages_db
+                .put(&mut wtxn, &(thread_id, message_id).into(), &message)
+                .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+            self.message_revisions_db
+                .put(&mut wtxn, &(thread_id, message_id).into(), &revisions)
+                .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+            wtxn.commit()
+                .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+            Ok(message)
+        } else {
+            Err(DatabaseError::NotFound)
+        }
+    }
+
+    async fn list_message_revisions(
+        &self,
+        tenant_id: Uuid,
+        thread_id: Uuid,
+        message_id: Uuid,
+    ) -> Result<Vec<MessageRevision>, DatabaseError> {
+        let rtxn = self
+            .env
+            .read_txn()
+            .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+
+        if self
+            .get_tenant_thread(&rtxn, tenant_id, thread_id)?
+            .is_none()
+            || self
+                .messages_db
+                .get(&rtxn, &(thread_id, message_id).into())
+                .map_err(|e| DatabaseError::QueryError(e.to_string()))?
+                .is_none()
+        {
+            return Err(DatabaseError::NotFound);
+        }
+
+        Ok(self
+            .message_revisions_db
+            .get(&rtxn, &(thread_id, message_id).into())
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?
+            .unwrap_or_default())
+    }
+
+    async fn get_message_revision(
+        &self,
+        tenant_id: Uuid,
+        thread_id: Uuid,
+        message_id: Uuid,
+        revision: u32,
+    ) -> Result<MessageRevision, DatabaseError> {
+        self.list_message_revisions(tenant_id, thread_id, message_id)
+            .await?
+            .into_iter()
+            .find(|r| r.revision == revision)
+            .ok_or(DatabaseError::NotFound)
+    }
+
+    async fn update_thread(
+        &self,
+        tenant_id: Uuid,
+        thread_id: Uuid,
+        update: UpdateThread,
+    ) -> Result<Thread, DatabaseError> {
+        let mut wtxn = self
+            .env
+            .write_txn()
+            .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+
+        if let Some(mut thread) = self.get_tenant_thread(&wtxn, tenant_id, thread_id)? {
+            thread.update(update);
+            self.threads_db
+                .put(&mut wtxn, &thread_id.into(), &thread)
+                .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+            wtxn.commit()
+                .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+            Ok(thread)
+        } else {
+            Err(DatabaseError::NotFound)
+        }
+    }
+
+    async fn list_threads(
+        &self,
+        tenant_id: Uuid,
+        query: &ListThreads,
+    ) -> Result<ListThreadsResponse, DatabaseError> {
+        let rtxn = self
+            .env
+            .read_txn()
+            .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+
+        // Without filters the tenant's count is the total; without metadata
+        // filters the tenant's range of the creation time index gives it.
+        let (created_after, created_before) = Self::creation_time_limits(query);
+        let creation_range = Self::exclusive_range(tenant_id, created_after, created_before);
+        let total =
+            if query.metadata.is_empty() && created_after.is_none() && created_before.is_none() {
+                self.tenant_thread_counts_db
+                    .get(&rtxn, &tenant_id.into())
+                    .map_err(|e| DatabaseError::QueryError(e.to_string()))?
+                    .unwrap_or(0) as usize
+            } else if query.metadata.is_empty() {
+                self.thread_creation_time_db
+                    .range(&rtxn, &creation_range)
+                    .map_err(|e| DatabaseError::QueryError(e.to_string()))?
+                    .count()
+            } else {
+                let mut total = 0;
+                for entry in self
+                    .thread_creation_time_db
+                    .range(&rtxn, &creation_range)
+                    .map_err(|e| DatabaseError::QueryError(e.to_string()))?
+                {
+                    let (key, ()) = entry.map_err(|e| DatabaseError::QueryError(e.to_string()))?;
+                    let (_, _, thread_id) = key.0;
+                    let thread = self
+                        .threads_db
+                        .get(&rtxn, &thread_id.into())
+                        .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
+                    if thread.is_some_and(|thread| query.matches(&thread)) {
+                        total += 1;
+                    }
+                }
+                total
+            };
+        let limit = query.limit.unwrap_or(total);
+
+        // Walk the index of the sort key from the cursor, narrowed to the creation
+        // time range when that is the index being walked.
+        let (mut lower, mut upper) = match query.order {
+            SortOrder::Asc => (query.cursor, None),
+            SortOrder::Desc => (None, query.cursor),
+        };
+        if query.sort == ThreadSort::CreatedAt {
+            lower = lower.max(created_after);
+            upper = upper.into_iter().chain(created_before).min();
+        }
+        let range = Self::exclusive_range(tenant_id, lower, upper);
+        let index = self.thread_time_index(query.sort);
+        let entries: Box<dyn Iterator<Item = heed::Result<(HeedTenantTimestampUuid, ())>>> =
+            match query.order {
+                SortOrder::Asc => Box::new(
+                    index
+                        .range(&rtxn, &range)
+                        .map_err(|e| DatabaseError::QueryError(e.to_string()))?,
+                ),
+                SortOrder::Desc => Box::new(
+                    index
+                        .rev_range(&rtxn, &range)
+                        .map_err(|e| DatabaseError::QueryError(e.to_string()))?,
+                ),
+            };
+
+        let mut threads = Vec::new();
+        let mut next_cursor = None;
+        for entry in entries {
+            let (key, ()) = entry.map_err(|e| DatabaseError::QueryError(e.to_string()))?;
+            let (_, _, thread_id) = key.0;
+            let Some(thread) = self
+                .threads_db
+                .get(&rtxn, &thread_id.into())
+                .map_err(|e| DatabaseError::QueryError(e.to_string()))?
+            else {
+                continue;
+            };
+            if !query.matches(&thread) {
+                continue;
+            }
+            if threads.len() == limit {
+                next_cursor = threads
+                    .last()
+                    .map(|thread: &Thread| thread.cursor(query.sort));
+                break;
+            }
+            threads.push(thread);
+        }
+
+        Ok(ListThreadsResponse {
+            threads,
+            total,
+            limit,
+            next_cursor,
+        })
+    }
+
+    async fn get_thread(&self, tenant_id: Uuid, thread_id: Uuid) -> Result<Thread, DatabaseError> {
+        let rtxn = self
+            .env
+            .read_txn()
+            .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+        self.get_tenant_thread(&rtxn, tenant_id, thread_id)?
+            .ok_or(DatabaseError::NotFound)
+    }
+
+    async fn get_thread_messages(
+        &self,
+        tenant_id: Uuid,
+        thread_id: Uuid,
+        query: &ListMessages,
+    ) -> Result<ThreadMessagesResponse, DatabaseError> {
+        let rtxn = self
+            .env
+            .read_txn()
+            .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+
+        if self
+            .get_tenant_thread(&rtxn, tenant_id, thread_id)?
+            .is_none()
+        {
+            return Err(DatabaseError::NotFound);
+        }
+
+        let total = self
+            .thread_message_counts_db
+            .get(&rtxn, &thread_id.into())
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?
+            .unwrap_or(0) as usize;
+        let offset = query.offset.unwrap_or(0);
+
+        // Only index keys are walked to find the page; messages are read for the
+        // page alone. One extra key tells whether another page follows.
+        let range = Self::message_time_range(thread_id, query.after, query.before);
+        let entries: Box<dyn Iterator<Item = heed::Result<(HeedMessageCreationTimeId, ())>>> =
+            match query.order {
+                SortOrder::Asc => Box::new(
+                    self.message_creation_time_db
+                        .range(&rtxn, &range)
+                        .map_err(|e| DatabaseError::QueryError(e.to_string()))?,
+                ),
+                SortOrder::Desc => Box::new(
+                    self.message_creation_time_db
+                        .rev_range(&rtxn, &range)
+                        .map_err(|e| DatabaseError::QueryError(e.to_string()))?,
+                ),
+            };
+        let mut message_ids = entries
+            .skip(offset)
+            .take(
+                query
+                    .limit
+                    .map_or(usize::MAX, |limit| limit.saturating_add(1)),
+            )
+            .map(|entry| entry.map(|(HeedUuidTimestampUuid((_, _, message_id)), ())| message_id))
+            .collect::<heed::Result<Vec<Uuid>>>()
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
+        let limit = query.limit.unwrap_or(message_ids.len());
+        let has_more = message_ids.len() > limit;
+        message_ids.truncate(limit);
+
+        let messages = message_ids
+            .into_iter()
+            .map(|message_id| {
+                self.messages_db
+                    .get(&rtxn, &(thread_id, message_id).into())
+                    .map_err(|e| DatabaseError::QueryError(e.to_string()))?
+                    .ok_or_else(|| {
+                        DatabaseError::InternalError(format!(
+                            "message {} is indexed but missing",
+                            message_id
+                        ))
+                    })
+            })
+            .collect::<Result<Vec<Message>, DatabaseError>>()?;
+        let next_cursor = messages.last().filter(|_| has_more).map(Message::cursor);
+
+        Ok(ThreadMessagesResponse {
+            messages,
+            total,
+            offset,
+            limit,
+            next_cursor,
+        })
+    }
+
+    async fn debug_state(&self) -> Result<serde_json::Value, DatabaseError> {
+        let rtxn = self
+            .env
+            .read_txn()
+            .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+
+        let threads: Vec<(Uuid, Thread)> = self
+            .threads_db
+            .iter(&rtxn)
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?
+            .flatten()
+            .map(|(k, thread)| (k.0, thread))
+            .collect();
+        let messages: Vec<((Uuid, Uuid), Message)> = self
+            .messages_db
+            .iter(&rtxn)
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?
+            .flatten()
+            .map(|(k, message)| ((k.0 .0, k.0 .1), message))
+            .collect();
+        let thread_messages: Vec<(Uuid, Vec<Uuid>)> = self
+            .thread_messages_db
+            .iter(&rtxn)
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?
+            .flatten()
+            .map(|(k, v)| (k.0, v))
+            .collect();
+        let message_revisions: Vec<((Uuid, Uuid), Vec<MessageRevision>)> = self
+            .message_revisions_db
+            .iter(&rtxn)
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?
+            .flatten()
+            .map(|(k, v)| ((k.0 .0, k.0 .1), v))
+            .collect();
+        let embeddings: Vec<(Uuid, Embedding)> = self
+            .embeddings_db
+            .iter(&rtxn)
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?
+            .flatten()
+            .map(|(k, v)| (k.0, v))
+            .collect();
+        let thread_creation_times: Vec<(Uuid, u64, Uuid)> = self
+            .thread_creation_time_db
+            .iter(&rtxn)
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?
+            .flatten()
+            .map(|(k, _)| (k.0 .0, k.0 .1, k.0 .2))
+            .collect();
+        let thread_activity_times: Vec<(Uuid, u64, Uuid)> = self
+            .thread_activity_time_db
+            .iter(&rtxn)
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?
+            .flatten()
+            .map(|(k, _)| (k.0 .0, k.0 .1, k.0 .2))
+            .collect();
+        let message_creation_times: Vec<(Uuid, u64, Uuid)> = self
+            .message_creation_time_db
+            .iter(&rtxn)
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?
+            .flatten()
+            .map(|(k, _)| (k.0 .0, k.0 .1, k.0 .2))
+            .collect();
+        let message_embeddings: Vec<(Uuid, (Uuid, Embedding))> = self
+            .message_embeddings_db
+            .iter(&rtxn)
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?
+            .flatten()
+            .map(|(k, v)| (k.0, v))
+            .collect();
+        let blobs: Vec<(Uuid, Blob)> = self
+            .blobs_db
+            .iter(&rtxn)
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?
+            .flatten()
+            .map(|(k, blob)| (k.0 .0, blob))
+            .collect();
+        let jobs: Vec<Job> = self
+            .jobs_db
+            .iter(&rtxn)
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?
+            .flatten()
+            .map(|(_, job)| job)
+            .collect();
+        let webhooks: Vec<Webhook> = self
+            .webhooks_db
+            .iter(&rtxn)
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?
+            .flatten()
+            .map(|(_, webhook)| webhook)
+            .collect();
+        let webhook_deliveries: Vec<WebhookDelivery> = self
+            .webhook_deliveries_db
+            .iter(&rtxn)
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?
+            .flatten()
+            .map(|(_, delivery)| delivery)
+            .collect();
+        let tenants: Vec<Tenant> = self
+            .tenants_db
+            .iter(&rtxn)
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?
+            .flatten()
+            .map(|(_, tenant)| tenant)
+            .collect();
+        let api_keys: Vec<ApiKey> = self
+            .api_keys_db
+            .iter(&rtxn)
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?
+            .flatten()
+            .map(|(_, api_key)| api_key)
+            .collect();
+        let thread_index: Vec<(Uuid, HnswNode)> = self
+            .thread_index
+            .nodes_db
+            .iter(&rtxn)
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?
+            .flatten()
+            .map(|(k, v)| (k.0, v))
+            .collect();
+        let thread_index_entry_point: Option<HnswEntryPoint> = self
+            .thread_index
+            .entry_point_db
+            .get(&rtxn, heed_index::ENTRY_POINT_KEY)
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
+        let message_index: Vec<(Uuid, HnswNode)> = self
+            .message_index
+            .nodes_db
+            .iter(&rtxn)
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?
+            .flatten()
+            .map(|(k, v)| (k.0, v))
+            .collect();
+        let message_index_entry_point: Option<HnswEntryPoint> = self
+            .message_index
+            .entry_point_db
+            .get(&rtxn, heed_index::ENTRY_POINT_KEY)
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
+
+        Ok(serde_json::json!({
+            "threads": threads,
+            "messages": messages,
+            "thread_messages": thread_messages,
+            "message_revisions": message_revisions,
+            "embeddings": embeddings,
+            "thread_creation_times": thread_creation_times,
+            "thread_activity_times": thread_activity_times,
+            "message_creation_times": message_creation_times,
+            "message_embeddings": message_embeddings,
+            "jobs": jobs,
+            "blobs": blobs,
+            "webhooks": webhooks,
+            "webhook_deliveries": webhook_deliveries,
+            "tenants": tenants,
+            "api_keys": api_keys,
+            "thread_index": thread_index,
+            "thread_index_entry_point": thread_index_entry_point,
+            "message_index": message_index,
+            "message_index_entry_point": message_index_entry_point
+        }))
+    }
+
+    async fn delete_message(
+        &self,
+        tenant_id: Uuid,
+        thread_id: Uuid,
+        message_id: Uuid,
+    ) -> Result<(), DatabaseError> {
+        let mut wtxn = self
+            .env
+            .write_txn()
+            .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+
+        let Some(thread) = self.get_tenant_thread(&wtxn, tenant_id, thread_id)? else {
+            return Err(DatabaseError::NotFound);
+        };
+        if self
+            .messages_db
+            .get(&wtxn, &(thread_id, message_id).into())
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?
+            .is_some()
+        {
+            self.delete_message_internal(&mut wtxn, tenant_id, thread_id, message_id)?;
+            let last_message_at = self.latest_message_time(&wtxn, thread_id)?;
+            self.set_last_message_at(&mut wtxn, thread, last_message_at)?;
+            wtxn.commit()
+                .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+            Ok(())
+        } else {
+            Err(DatabaseError::NotFound)
+        }
+    }
+
+    async fn get_message(
+        &self,
+        tenant_id: Uuid,
+        thread_id: Uuid,
+        message_id: Uuid,
+    ) -> Result<Message, DatabaseError> {
+        let rtxn = self
+            .env
+            .read_txn()
+            .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+        if self
+            .get_tenant_thread(&rtxn, tenant_id, thread_id)?
+            .is_none()
+        {
+            return Err(DatabaseError::NotFound);
+        }
+        self.messages_db
+            .get(&rtxn, &(thread_id, message_id).into())
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?
+            .ok_or(DatabaseError::NotFound)
+    }
+
+    async fn put_blob(
+        &self,
+        tenant_id: Uuid,
+        blob: Blob,
+        data: Vec<u8>,
+    ) -> Result<Blob, DatabaseError> {
+        let mut wtxn = self
+            .env
+            .write_txn()
+            .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+
+        let key = (tenant_id, blob.hash.clone()).into();
+        if let Some(mut existing) = self
+            .blobs_db
+            .get(&wtxn, &key)
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?
+        {
+            existing.uploaded_at = blob.uploaded_at;
+            self.blobs_db
+                .put(&mut wtxn, &key, &existing)
+                .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+            wtxn.commit()
+                .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+            return Ok(existing);
+        }
+
+        self.blobs_db
+            .put(&mut wtxn, &key, &blob)
+            .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+        self.blob_data_db
+            .put(&mut wtxn, &key, &data)
+            .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+        wtxn.commit()
+            .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+        Ok(blob)
+    }
+
+    async fn get_blob(
+        &self,
+        tenant_id: Uuid,
+        hash: &str,
+    ) -> Result<(Blob, Vec<u8>), DatabaseError> {
+        let rtxn = self
+            .env
+            .read_txn()
+            .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+
+        let key = (tenant_id, hash.to_string()).into();
+        let blob = self
+            .blobs_db
+            .get(&rtxn, &key)
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?
+            .ok_or(DatabaseError::NotFound)?;
+        let data = self
+            .blob_data_db
+            .get(&rtxn, &key)
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?
+            .ok_or(DatabaseError::NotFound)?;
+        Ok((blob, data.to_vec()))
+    }
+
+    async fn delete_unreferenced_blobs(&self, uploaded_before: u64) -> Result<u64, DatabaseError> {
+        let mut wtxn = self
+            .env
+            .write_txn()
+            .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+
+        let keys: Vec<(Uuid, String)> = self
+            .blobs_db
+            .iter(&wtxn)
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?
+            .flatten()
+            .filter(|(_, blob)| blob.is_collectable(uploaded_before))
+            .map(|(k, _)| k.0)
+            .collect();
+        for key in &keys {
+            let key = key.clone().into();
+            self.blobs_db
+                .delete(&mut wtxn, &key)
+                .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+            self.blob_data_db
+                .delete(&mut wtxn, &key)
+                .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+        }
+
+        wtxn.commit()
+            .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+        Ok(keys.len() as u64)
+    }
+
+    async fn save_job(&self, job: &Job) -> Result<(), DatabaseError> {
+        let mut wtxn = self
+            .env
+            .write_txn()
+            .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+
+        if self
+            .get_tenant_thread(&wtxn, job.tenant_id, job.thread_id)?
+            .is_none()
+        {
+            return Err(DatabaseError::NotFound);
+        }
 
+        self.jobs_db
+            .put(&mut wtxn, &(job.thread_id, job.id()).into(), job)
+            .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
         wtxn.commit()
             .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
         Ok(())
     }
 
-    async fn create_thread(&self) -> Result<Thread, DatabaseError> {
-        let thread = Thread::new();
+    async fn delete_job(
+        &self,
+        tenant_id: Uuid,
+        thread_id: Uuid,
+        job_id: Uuid,
+    ) -> Result<(), DatabaseError> {
+        let mut wtxn = self
+            .env
+            .write_txn()
+            .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+
+        if self
+            .get_tenant_thread(&wtxn, tenant_id, thread_id)?
+            .is_some()
+            && self
+                .jobs_db
+                .delete(&mut wtxn, &(thread_id, job_id).into())
+                .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?
+        {
+            wtxn.commit()
+                .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+            Ok(())
+        } else {
+            Err(DatabaseError::NotFound)
+        }
+    }
+
+    async fn get_pending_jobs(&self) -> Result<Vec<Job>, DatabaseError> {
+        let rtxn = self
+            .env
+            .read_txn()
+            .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+        let mut jobs: Vec<Job> = self
+            .jobs_db
+            .iter(&rtxn)
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?
+            .flatten()
+            .map(|(_, job)| job)
+            .filter(|job| job.status == JobStatus::Pending)
+            .collect();
+        jobs.sort_by_key(|job| job.created_at);
+        Ok(jobs)
+    }
+
+    async fn get_thread_jobs(
+        &self,
+        tenant_id: Uuid,
+        thread_id: Uuid,
+    ) -> Result<Vec<Job>, DatabaseError> {
+        let rtxn = self
+            .env
+            .read_txn()
+            .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+
+        if self
+            .get_tenant_thread(&rtxn, tenant_id, thread_id)?
+            .is_none()
+        {
+            return Err(DatabaseError::NotFound);
+        }
+
+        let mut jobs = self.get_thread_jobs_internal(&rtxn, thread_id)?;
+        jobs.sort_by_key(|job| job.created_at);
+        Ok(jobs)
+    }
+
+    async fn save_webhook(&self, webhook: &Webhook) -> Result<(), DatabaseError> {
         let mut wtxn = self
             .env
             .write_txn()
             .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
 
-        self.create_thread_internal(&mut wtxn, &thread)?;
-
         if self
-            .threads_db
-            .get(&wtxn, &thread.id().into())
+            .tenants_db
+            .get(&wtxn, &webhook.tenant_id.into())
             .map_err(|e| DatabaseError::QueryError(e.to_string()))?
             .is_none()
         {
-            return Err(DatabaseError::OperationFailed(
-                "Thread not found after insertion".to_string(),
-            ));
+            return Err(DatabaseError::NotFound);
         }
 
+        self.webhooks_db
+            .put(&mut wtxn, &webhook.id().into(), webhook)
+            .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
         wtxn.commit()
             .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
-        Ok(thread)
+        Ok(())
     }
 
-    async fn delete_thread(&self, thread_id: Uuid) -> Result<(), DatabaseError> {
-        let mut wtxn = self
+    async fn get_webhook(
+        &self,
+        tenant_id: Uuid,
+        webhook_id: Uuid,
+    ) -> Result<Webhook, DatabaseError> {
+        let rtxn = self
             .env
-            .write_txn()
+            .read_txn()
             .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+        self.get_tenant_webhook(&rtxn, tenant_id, webhook_id)?
+            .ok_or(DatabaseError::NotFound)
+    }
 
-        if self
-            .threads_db
-            .get(&wtxn, &thread_id.into())
+    async fn list_webhooks(&self, tenant_id: Uuid) -> Result<Vec<Webhook>, DatabaseError> {
+        let rtxn = self
+            .env
+            .read_txn()
+            .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+        let mut webhooks: Vec<Webhook> = self
+            .webhooks_db
+            .iter(&rtxn)
             .map_err(|e| DatabaseError::QueryError(e.to_string()))?
-            .is_some()
-        {
-            self.delete_thread_internal(&mut wtxn, thread_id)?;
-            wtxn.commit()
-                .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
-            Ok(())
-        } else {
-            Err(DatabaseError::NotFound)
-        }
+            .flatten()
+            .map(|(_, webhook)| webhook)
+            .filter(|webhook| webhook.tenant_id == tenant_id)
+            .collect();
+        webhooks.sort_by_key(|webhook| webhook.created_at);
+        Ok(webhooks)
     }
 
-    async fn create_message(
-        &self,
-        thread_id: Uuid,
-        input: CreateMessage,
-    ) -> Result<Message, DatabaseError> {
+    async fn delete_webhook(&self, tenant_id: Uuid, webhook_id: Uuid) -> Result<(), DatabaseError> {
         let mut wtxn = self
             .env
             .write_txn()
             .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
 
         if self
-            .threads_db
-            .get(&wtxn, &thread_id.into())
-            .map_err(|e| DatabaseError::QueryError(e.to_string()))?
+            .get_tenant_webhook(&wtxn, tenant_id, webhook_id)?
             .is_none()
         {
             return Err(DatabaseError::NotFound);
         }
-
-        let message = input.into_message(thread_id);
-        self.create_message_internal(&mut wtxn, &message)?;
+        self.delete_webhook_internal(&mut wtxn, webhook_id)?;
 
         wtxn.commit()
             .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
-        Ok(message)
+        Ok(())
     }
 
-    async fn update_message(
-        &self,
-        thread_id: Uuid,
-        message_id: Uuid,
-        content: UpdateMessage,
-    ) -> Result<Message, DatabaseError> {
+    async fn save_webhook_delivery(&self, delivery: &WebhookDelivery) -> Result<(), DatabaseError> {
         let mut wtxn = self
             .env
             .write_txn()
             .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
 
-        if let Some(mut message) = self
-            .messages_db
-            .get(&wtxn, &(thread_id, message_id).into())
+        if self
+            .webhooks_db
+            .get(&wtxn, &delivery.webhook_id.into())
             .map_err(|e| DatabaseError::QueryError(e.to_string()))?
+            .is_none()
         {
-            message.update_content(content);
-            self.messages_db
-                .put(&mut wtxn, &(thread_id, message_id).into(), &message)
-                .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
-            wtxn.commit()
-                .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
-            Ok(message)
-        } else {
-            Err(DatabaseError::NotFound)
+            return Err(DatabaseError::NotFound);
         }
+
+        self.webhook_deliveries_db
+            .put(
+                &mut wtxn,
+                &(delivery.webhook_id, delivery.id()).into(),
+                delivery,
+            )
+            .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+        wtxn.commit()
+            .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+        Ok(())
     }
 
-    async fn update_thread(
+    async fn list_webhook_deliveries(
         &self,
-        thread_id: Uuid,
-        update: UpdateThread,
-    ) -> Result<Thread, DatabaseError> {
-        let mut wtxn = self
+        tenant_id: Uuid,
+        webhook_id: Uuid,
+    ) -> Result<Vec<WebhookDelivery>, DatabaseError> {
+        let rtxn = self
             .env
-            .write_txn()
+            .read_txn()
             .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
 
-        if let Some(mut thread) = self
-            .threads_db
-            .get(&wtxn, &thread_id.into())
-            .map_err(|e| DatabaseError::QueryError(e.to_string()))?
+        if self
+            .get_tenant_webhook(&rtxn, tenant_id, webhook_id)?
+            .is_none()
         {
-            thread.set_title(update.title);
-            self.threads_db
-                .put(&mut wtxn, &thread_id.into(), &thread)
-                .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
-            wtxn.commit()
-                .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
-            Ok(thread)
-        } else {
-            Err(DatabaseError::NotFound)
+            return Err(DatabaseError::NotFound);
         }
+
+        let mut deliveries = self.get_webhook_deliveries_internal(&rtxn, webhook_id)?;
+        deliveries.sort_by_key(|delivery| delivery.created_at);
+        Ok(deliveries)
     }
 
-    async fn list_threads(&self) -> Result<Vec<Thread>, DatabaseError> {
+    async fn get_pending_webhook_deliveries(&self) -> Result<Vec<WebhookDelivery>, DatabaseError> {
         let rtxn = self
             .env
             .read_txn()
             .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
-        let threads = self
-            .threads_db
+        let mut deliveries: Vec<WebhookDelivery> = self
+            .webhook_deliveries_db
             .iter(&rtxn)
-            .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
-        let threads: Vec<Thread> = threads.flatten().map(|(_, thread)| thread).collect();
-        Ok(threads)
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?
+            .flatten()
+            .map(|(_, delivery)| delivery)
+            .filter(|delivery| delivery.status == DeliveryStatus::Pending)
+            .collect();
+        deliveries.sort_by_key(|delivery| delivery.created_at);
+        Ok(deliveries)
+    }
+
+    async fn save_tenant(&self, tenant: &Tenant) -> Result<(), DatabaseError> {
+        let mut wtxn = self
+            .env
+            .write_txn()
+            .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+        self.tenants_db
+            .put(&mut wtxn, &tenant.id().into(), tenant)
+            .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+        wtxn.commit()
+            .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+        Ok(())
     }
 
-    async fn get_thread(&self, thread_id: Uuid) -> Result<Thread, DatabaseError> {
+    async fn get_tenant(&self, tenant_id: Uuid) -> Result<Tenant, DatabaseError> {
         let rtxn = self
             .env
             .read_txn()
             .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
-        self.threads_db
-            .get(&rtxn, &thread_id.into())
+        self.tenants_db
+            .get(&rtxn, &tenant_id.into())
             .map_err(|e| DatabaseError::QueryError(e.to_string()))?
             .ok_or(DatabaseError::NotFound)
     }
 
-    async fn get_thread_messages(
-        &self,
-        thread_id: Uuid,
-        limit: Option<usize>,
-        offset: Option<usize>,
-    ) -> Result<ThreadMessagesResponse, DatabaseError> {
+    async fn list_tenants(&self) -> Result<Vec<Tenant>, DatabaseError> {
         let rtxn = self
             .env
             .read_txn()
             .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+        let mut tenants: Vec<Tenant> = self
+            .tenants_db
+            .iter(&rtxn)
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?
+            .flatten()
+            .map(|(_, tenant)| tenant)
+            .collect();
+        tenants.sort_by_key(|tenant| tenant.created_at);
+        Ok(tenants)
+    }
+
+    async fn delete_tenant(&self, tenant_id: Uuid) -> Result<(), DatabaseError> {
+        let mut wtxn = self
+            .env
+            .write_txn()
+            .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
 
         if self
-            .threads_db
-            .get(&rtxn, &thread_id.into())
+            .tenants_db
+            .get(&wtxn, &tenant_id.into())
             .map_err(|e| DatabaseError::QueryError(e.to_string()))?
             .is_none()
         {
             return Err(DatabaseError::NotFound);
         }
-
-        let message_ids = self
-            .thread_messages_db
-            .get(&rtxn, &thread_id.into())
+        if self
+            .tenant_thread_counts_db
+            .get(&wtxn, &tenant_id.into())
             .map_err(|e| DatabaseError::QueryError(e.to_string()))?
-            .unwrap_or_default();
-
-        let messages: Vec<Message> = message_ids
-            .iter()
-            .filter_map(|&id| {
-                self.messages_db
-                    .get(&rtxn, &(thread_id, id).into())
-                    .ok()
-                    .and_then(|m| m)
-            })
-            .collect();
-
-        let (paginated_messages, total, offset, limit) =
-            Self::apply_pagination(messages, limit, offset);
-
-        Ok(ThreadMessagesResponse {
-            messages: paginated_messages,
-            total,
-            offset,
-            limit,
-        })
-    }
-
-    async fn debug_state(&self) -> Result<serde_json::Value, DatabaseError> {
-        let rtxn = self
-            .env
-            .read_txn()
+            .unwrap_or(0)
+            > 0
+        {
+            return Err(DatabaseError::Conflict(format!(
+                "tenant {} still owns threads",
+                tenant_id
+            )));
+        }
+        self.tenant_thread_counts_db
+            .delete(&mut wtxn, &tenant_id.into())
             .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
 
-        let threads: Vec<(Uuid, Thread)> = self
-            .threads_db
-            .iter(&rtxn)
+        let key_hashes: Vec<String> = self
+            .api_keys_db
+            .iter(&wtxn)
             .map_err(|e| DatabaseError::QueryError(e.to_string()))?
             .flatten()
-            .map(|(k, thread)| (k.0, thread))
+            .filter(|(_, api_key)| api_key.tenant_id == tenant_id)
+            .map(|(key_hash, _)| key_hash.to_string())
             .collect();
-        let messages: Vec<((Uuid, Uuid), Message)> = self
-            .messages_db
-            .iter(&rtxn)
+        for key_hash in key_hashes {
+            self.api_keys_db
+                .delete(&mut wtxn, &key_hash)
+                .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+        }
+        let webhook_ids: Vec<Uuid> = self
+            .webhooks_db
+            .iter(&wtxn)
             .map_err(|e| DatabaseError::QueryError(e.to_string()))?
             .flatten()
-            .map(|(k, message)| ((k.0 .0, k.0 .1), message))
+            .filter(|(_, webhook)| webhook.tenant_id == tenant_id)
+            .map(|(_, webhook)| webhook.id())
             .collect();
-        let thread_messages: Vec<(Uuid, Vec<Uuid>)> = self
-            .thread_messages_db
-            .iter(&rtxn)
+        for webhook_id in webhook_ids {
+            self.delete_webhook_internal(&mut wtxn, webhook_id)?;
+        }
+        let blob_keys: Vec<(Uuid, String)> = self
+            .blobs_db
+            .prefix_iter(&wtxn, &(tenant_id, String::new()).into())
             .map_err(|e| DatabaseError::QueryError(e.to_string()))?
             .flatten()
-            .map(|(k, v)| (k.0, v))
+            .map(|(k, _)| k.0)
             .collect();
-        let embeddings: Vec<(Uuid, Embedding)> = self
-            .embeddings_db
-            .iter(&rtxn)
+        for key in blob_keys {
+            let key = key.into();
+            self.blobs_db
+                .delete(&mut wtxn, &key)
+                .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+            self.blob_data_db
+                .delete(&mut wtxn, &key)
+                .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+        }
+        self.tenants_db
+            .delete(&mut wtxn, &tenant_id.into())
+            .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+
+        wtxn.commit()
+            .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+        Ok(())
+    }
+
+    async fn save_api_key(&self, api_key: &ApiKey) -> Result<(), DatabaseError> {
+        let mut wtxn = self
+            .env
+            .write_txn()
+            .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+
+        if self
+            .tenants_db
+            .get(&wtxn, &api_key.tenant_id.into())
             .map_err(|e| DatabaseError::QueryError(e.to_string()))?
-            .flatten()
-            .map(|(k, v)| (k.0, v))
-            .collect();
-        let thread_creation_times: Vec<(u64, Uuid)> = self
-            .thread_creation_time_db
-            .iter(&rtxn)
+            .is_none()
+        {
+            return Err(DatabaseError::NotFound);
+        }
+
+        self.api_keys_db
+            .put(&mut wtxn, &api_key.key_hash, api_key)
+            .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+        wtxn.commit()
+            .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+        Ok(())
+    }
+
+    async fn get_api_key_by_hash(&self, key_hash: &str) -> Result<ApiKey, DatabaseError> {
+        let rtxn = self
+            .env
+            .read_txn()
+            .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+        self.api_keys_db
+            .get(&rtxn, key_hash)
             .map_err(|e| DatabaseError::QueryError(e.to_string()))?
-            .flatten()
-            .map(|(k, _)| (k.0 .0, k.0 .1))
-            .collect();
-        let message_creation_times: Vec<(Uuid, u64, Uuid)> = self
-            .message_creation_time_db
+            .ok_or(DatabaseError::NotFound)
+    }
+
+    async fn list_api_keys(&self, tenant_id: Uuid) -> Result<Vec<ApiKey>, DatabaseError> {
+        let rtxn = self
+            .env
+            .read_txn()
+            .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+
+        if self
+            .tenants_db
+            .get(&rtxn, &tenant_id.into())
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?
+            .is_none()
+        {
+            return Err(DatabaseError::NotFound);
+        }
+
+        let mut api_keys: Vec<ApiKey> = self
+            .api_keys_db
             .iter(&rtxn)
             .map_err(|e| DatabaseError::QueryError(e.to_string()))?
             .flatten()
-            .map(|(k, _)| (k.0 .0, k.0 .1, k.0 .2))
+            .map(|(_, api_key)| api_key)
+            .filter(|api_key| api_key.tenant_id == tenant_id)
             .collect();
-
-        Ok(serde_json::json!({
-            "threads": threads,
-            "messages": messages,
-            "thread_messages": thread_messages,
-            "embeddings": embeddings,
-            "thread_creation_times": thread_creation_times,
-            "message_creation_times": message_creation_times
-        }))
+        api_keys.sort_by_key(|api_key| api_key.created_at);
+        Ok(api_keys)
     }
 
-    async fn delete_message(&self, thread_id: Uuid, message_id: Uuid) -> Result<(), DatabaseError> {
+    async fn delete_api_key(&self, tenant_id: Uuid, key_id: Uuid) -> Result<(), DatabaseError> {
         let mut wtxn = self
             .env
             .write_txn()
             .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
 
-        if self
-            .messages_db
-            .get(&wtxn, &(thread_id, message_id).into())
+        let Some(key_hash) = self
+            .api_keys_db
+            .iter(&wtxn)
             .map_err(|e| DatabaseError::QueryError(e.to_string()))?
-            .is_some()
-        {
-            self.delete_message_internal(&mut wtxn, thread_id, message_id)?;
-            wtxn.commit()
-                .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
-            Ok(())
-        } else {
-            Err(DatabaseError::NotFound)
-        }
+            .flatten()
+            .find(|(_, api_key)| api_key.id() == key_id && api_key.tenant_id == tenant_id)
+            .map(|(key_hash, _)| key_hash.to_string())
+        else {
+            return Err(DatabaseError::NotFound);
+        };
+        self.api_keys_db
+            .delete(&mut wtxn, &key_hash)
+            .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+
+        wtxn.commit()
+            .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+        Ok(())
     }
 }
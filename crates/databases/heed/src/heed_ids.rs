@@ -100,16 +100,61 @@ impl<'a> BytesDecode<'a> for HeedTimestampUuid {
     }
 }
 
+/// An id followed by a string, such as a tenant and the hash of one of its blobs.
 #[derive(Debug)]
-pub struct HeedMessageCreationTimeId(pub (Uuid, u64, Uuid));
+pub struct HeedUuidString(pub (Uuid, String));
 
-impl From<(Uuid, u64, Uuid)> for HeedMessageCreationTimeId {
+impl From<(Uuid, String)> for HeedUuidString {
+    fn from(key: (Uuid, String)) -> Self {
+        Self(key)
+    }
+}
+
+impl<'a> BytesEncode<'a> for HeedUuidString {
+    type EItem = Self;
+
+    fn bytes_encode(item: &'a Self::EItem) -> Result<Cow<'a, [u8]>, BoxedError> {
+        let mut bytes = Vec::with_capacity(16 + item.0 .1.len());
+        bytes.extend_from_slice(item.0 .0.as_bytes());
+        bytes.extend_from_slice(item.0 .1.as_bytes());
+        Ok(Cow::Owned(bytes))
+    }
+}
+
+impl<'a> BytesDecode<'a> for HeedUuidString {
+    type DItem = HeedUuidString;
+
+    fn bytes_decode(bytes: &'a [u8]) -> Result<Self::DItem, BoxedError> {
+        if bytes.len() < 16 {
+            return Err(BoxedError::from(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "Invalid byte length for HeedUuidString",
+            )));
+        }
+        let uuid = Uuid::from_slice(&bytes[..16])?;
+        let string = std::str::from_utf8(&bytes[16..])?.to_string();
+        Ok(HeedUuidString((uuid, string)))
+    }
+}
+
+/// An id, a timestamp and another id, ordered in that sequence.
+#[derive(Debug)]
+pub struct HeedUuidTimestampUuid(pub (Uuid, u64, Uuid));
+
+/// Key of the message time index: the thread, then the creation time and the message.
+pub type HeedMessageCreationTimeId = HeedUuidTimestampUuid;
+
+/// Key of the thread time indexes: the tenant, then the time and the thread, so
+/// that each tenant's threads sit in one range.
+pub type HeedTenantTimestampUuid = HeedUuidTimestampUuid;
+
+impl From<(Uuid, u64, Uuid)> for HeedUuidTimestampUuid {
     fn from(uuid: (Uuid, u64, Uuid)) -> Self {
         Self(uuid)
     }
 }
 
-impl<'a> BytesEncode<'a> for HeedMessageCreationTimeId {
+impl<'a> BytesEncode<'a> for HeedUuidTimestampUuid {
     type EItem = Self;
 
     fn bytes_encode(item: &'a Self::EItem) -> Result<Cow<'a, [u8]>, BoxedError> {
@@ -121,14 +166,14 @@ impl<'a> BytesEncode<'a> for HeedMessageCreationTimeId {
     }
 }
 
-impl<'a> BytesDecode<'a> for HeedMessageCreationTimeId {
-    type DItem = HeedMessageCreationTimeId;
+impl<'a> BytesDecode<'a> for HeedUuidTimestampUuid {
+    type DItem = HeedUuidTimestampUuid;
 
     fn bytes_decode(bytes: &'a [u8]) -> Result<Self::DItem, BoxedError> {
         if bytes.len() != 40 {
             return Err(BoxedError::from(std::io::Error::new(
                 std::io::ErrorKind::InvalidData,
-                "Invalid byte length for HeedMessageCreationTimeId",
+                "Invalid byte length for HeedUuidTimestampUuid",
             )));
         }
         let u1 = Uuid::from_bytes(bytes[..16].try_into()?);
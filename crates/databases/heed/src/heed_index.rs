@@ -0,0 +1,125 @@
+use heed::{
+    types::{SerdeJson, Str},
+    Database, RoTxn, RwTxn,
+};
+use synx_database::DatabaseError;
+use synx_vector_index::{HnswEntryPoint, HnswNode, HnswStorage, HnswStorageMut};
+use uuid::Uuid;
+
+use crate::{heed_ids::HeedUuid, SynxHeedDatabase};
+
+pub const ENTRY_POINT_KEY: &str = "entry_point";
+
+/// Resolves the vector of an indexed item from wherever the backend stores it.
+pub type VectorLookup =
+    fn(&SynxHeedDatabase, &RoTxn, Uuid) -> Result<Option<Vec<f32>>, DatabaseError>;
+
+#[derive(Debug)]
+pub struct HeedIndex {
+    pub nodes_db: Database<HeedUuid, SerdeJson<HnswNode>>,
+    pub entry_point_db: Database<Str, SerdeJson<HnswEntryPoint>>,
+    pub vector: VectorLookup,
+}
+
+pub struct HeedIndexReader<'a, 't> {
+    pub db: &'a SynxHeedDatabase,
+    pub index: &'a HeedIndex,
+    pub rtxn: &'a RoTxn<'t>,
+}
+
+pub struct HeedIndexWriter<'a, 't> {
+    pub db: &'a SynxHeedDatabase,
+    pub index: &'a HeedIndex,
+    pub wtxn: &'a mut RwTxn<'t>,
+}
+
+impl HeedIndex {
+    fn node(&self, rtxn: &RoTxn, id: Uuid) -> Result<Option<HnswNode>, DatabaseError> {
+        self.nodes_db
+            .get(rtxn, &id.into())
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))
+    }
+
+    fn entry_point(&self, rtxn: &RoTxn) -> Result<Option<HnswEntryPoint>, DatabaseError> {
+        self.entry_point_db
+            .get(rtxn, ENTRY_POINT_KEY)
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))
+    }
+}
+
+impl HnswStorage for HeedIndexReader<'_, '_> {
+    type Error = DatabaseError;
+
+    fn vector(&self, id: Uuid) -> Result<Option<Vec<f32>>, DatabaseError> {
+        (self.index.vector)(self.db, self.rtxn, id)
+    }
+
+    fn node(&self, id: Uuid) -> Result<Option<HnswNode>, DatabaseError> {
+        self.index.node(self.rtxn, id)
+    }
+
+    fn entry_point(&self) -> Result<Option<HnswEntryPoint>, DatabaseError> {
+        self.index.entry_point(self.rtxn)
+    }
+}
+
+impl HnswStorage for HeedIndexWriter<'_, '_> {
+    type Error = DatabaseError;
+
+    fn vector(&self, id: Uuid) -> Result<Option<Vec<f32>>, DatabaseError> {
+        (self.index.vector)(self.db, self.wtxn, id)
+    }
+
+    fn node(&self, id: Uuid) -> Result<Option<HnswNode>, DatabaseError> {
+        self.index.node(self.wtxn, id)
+    }
+
+    fn entry_point(&self) -> Result<Option<HnswEntryPoint>, DatabaseError> {
+        self.index.entry_point(self.wtxn)
+    }
+}
+
+impl HnswStorageMut for HeedIndexWriter<'_, '_> {
+    fn put_node(&mut self, id: Uuid, node: &HnswNode) -> Result<(), DatabaseError> {
+        self.index
+            .nodes_db
+            .put(self.wtxn, &id.into(), node)
+            .map_err(|e| DatabaseError::OperationFailed(e.to_string()))
+    }
+
+    fn delete_node(&mut self, id: Uuid) -> Result<(), DatabaseError> {
+        self.index
+            .nodes_db
+            .delete(self.wtxn, &id.into())
+            .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+        Ok(())
+    }
+
+    fn set_entry_point(
+        &mut self,
+        entry_point: Option<HnswEntryPoint>,
+    ) -> Result<(), DatabaseError> {
+        match entry_point {
+            Some(entry_point) => self
+                .index
+                .entry_point_db
+                .put(self.wtxn, ENTRY_POINT_KEY, &entry_point)
+                .map_err(|e| DatabaseError::OperationFailed(e.to_string())),
+            None => self
+                .index
+                .entry_point_db
+                .delete(self.wtxn, ENTRY_POINT_KEY)
+                .map(|_| ())
+                .map_err(|e| DatabaseError::OperationFailed(e.to_string())),
+        }
+    }
+
+    fn any_node(&self) -> Result<Option<(Uuid, HnswNode)>, DatabaseError> {
+        Ok(self
+            .index
+            .nodes_db
+            .first(self.wtxn)
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?
+            .map(|(id, node)| (id.0, node)))
+    }
+}
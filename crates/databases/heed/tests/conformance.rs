@@ -0,0 +1,19 @@
+use std::sync::Arc;
+
+use synx_heed_database::{heed::EnvOpenOptions, SynxHeedDatabase};
+use tempfile::TempDir;
+
+fn heed() -> (Arc<SynxHeedDatabase>, TempDir) {
+    let dir = tempfile::tempdir().unwrap();
+    let env = unsafe {
+        EnvOpenOptions::new()
+            .map_size(64 * 1024 * 1024)
+            .max_dbs(32)
+            .open(dir.path())
+            .unwrap()
+    };
+    let database = SynxHeedDatabase::new(Arc::new(env), true).unwrap();
+    (Arc::new(database), dir)
+}
+
+synx_database_conformance::db_conformance_tests!(heed());
@@ -5,10 +5,21 @@ use std::{
 
 use synx_database::{DatabaseError, Db};
 use synx_domain::{
+    api_key::ApiKey,
+    blob::Blob,
+    content::Content,
     embedding::Embedding,
-    message::{CreateMessage, Message, ThreadMessagesResponse, UpdateMessage},
-    thread::{Thread, UpdateThread},
+    job::{Job, JobStatus},
+    message::{
+        CreateMessage, ListMessages, Message, MessageRevision, ThreadMessagesResponse,
+        UpdateMessage,
+    },
+    metadata::{self, Metadata},
+    tenant::Tenant,
+    thread::{ListThreads, ListThreadsResponse, SummaryCursor, Thread, UpdateThread},
+    webhook::{DeliveryStatus, Webhook, WebhookDelivery},
 };
+use synx_vector_index::{Hnsw, HnswEntryPoint, HnswNode, HnswStorage, HnswStorageMut};
 use tokio::sync::Mutex;
 use uuid::Uuid;
 
@@ -16,7 +27,141 @@ use uuid::Uuid;
 pub struct SynxInMemory {
     threads: Arc<Mutex<HashMap<Uuid, Thread>>>,
     messages: Arc<Mutex<HashMap<Uuid, Message>>>,
-    thread_messages: Arc<Mutex<HashMap<Uuid, HashSet<Uuid>>>>,
+    thread_messages: Arc<Mutex<HashMap<Uuid, Vec<Uuid>>>>,
+    message_revisions: Arc<Mutex<HashMap<Uuid, Vec<MessageRevision>>>>,
+    thread_index: Arc<Mutex<VectorIndex>>,
+    message_index: Arc<Mutex<VectorIndex>>,
+    jobs: Arc<Mutex<HashMap<Uuid, Job>>>,
+    /// One store per tenant.
+    blobs: Arc<Mutex<HashMap<Uuid, BlobStore>>>,
+    webhooks: Arc<Mutex<HashMap<Uuid, Webhook>>>,
+    webhook_deliveries: Arc<Mutex<HashMap<Uuid, WebhookDelivery>>>,
+    tenants: Arc<Mutex<HashMap<Uuid, Tenant>>>,
+    api_keys: Arc<Mutex<HashMap<Uuid, ApiKey>>>,
+    hnsw: Hnsw,
+}
+
+#[derive(Default, serde::Serialize)]
+struct BlobStore {
+    blobs: HashMap<String, Blob>,
+    #[serde(skip)]
+    data: HashMap<String, Vec<u8>>,
+}
+
+impl BlobStore {
+    /// Fails if a blob of `content` is missing.
+    fn check(&self, content: &Content) -> Result<(), DatabaseError> {
+        match content
+            .blob_hashes()
+            .into_iter()
+            .find(|hash| !self.blobs.contains_key(*hash))
+        {
+            Some(missing) => Err(DatabaseError::InvalidInput(format!(
+                "blob {} does not exist",
+                missing
+            ))),
+            None => Ok(()),
+        }
+    }
+
+    /// Counts a reference to each blob of `content`, failing if one is missing.
+    fn retain(&mut self, content: &Content) -> Result<(), DatabaseError> {
+        self.check(content)?;
+        for hash in content.blob_hashes() {
+            if let Some(blob) = self.blobs.get_mut(hash) {
+                blob.ref_count += 1;
+            }
+        }
+        Ok(())
+    }
+
+    /// Drops a reference to each blob of `content`, removing blobs left unreferenced
+    /// past their grace period.
+    fn release(&mut self, content: &Content) {
+        let cutoff = Blob::collection_cutoff();
+        for hash in content.blob_hashes() {
+            if let Some(blob) = self.blobs.get_mut(hash) {
+                blob.ref_count = blob.ref_count.saturating_sub(1);
+                if blob.is_collectable(cutoff) {
+                    self.blobs.remove(hash);
+                    self.data.remove(hash);
+                }
+            }
+        }
+    }
+
+    /// Removes the blobs collectable at `cutoff`, returning how many.
+    fn collect(&mut self, cutoff: u64) -> u64 {
+        let before = self.blobs.len();
+        self.blobs.retain(|_, blob| !blob.is_collectable(cutoff));
+        let blobs = &self.blobs;
+        self.data.retain(|hash, _| blobs.contains_key(hash));
+        (before - self.blobs.len()) as u64
+    }
+}
+
+#[derive(Default, serde::Serialize)]
+struct VectorIndex {
+    vectors: HashMap<Uuid, Vec<f32>>,
+    nodes: HashMap<Uuid, HnswNode>,
+    entry_point: Option<HnswEntryPoint>,
+}
+
+impl VectorIndex {
+    fn insert(&mut self, hnsw: &Hnsw, id: Uuid, vector: Vec<f32>) -> Result<(), DatabaseError> {
+        self.vectors.insert(id, vector.clone());
+        hnsw.insert(self, id, &vector)
+    }
+
+    fn remove(&mut self, hnsw: &Hnsw, id: Uuid) -> Result<(), DatabaseError> {
+        hnsw.remove(self, id)?;
+        self.vectors.remove(&id);
+        Ok(())
+    }
+}
+
+impl HnswStorage for VectorIndex {
+    type Error = DatabaseError;
+
+    fn vector(&self, id: Uuid) -> Result<Option<Vec<f32>>, DatabaseError> {
+        Ok(self.vectors.get(&id).cloned())
+    }
+
+    fn node(&self, id: Uuid) -> Result<Option<HnswNode>, DatabaseError> {
+        Ok(self.nodes.get(&id).cloned())
+    }
+
+    fn entry_point(&self) -> Result<Option<HnswEntryPoint>, DatabaseError> {
+        Ok(self.entry_point)
+    }
+}
+
+impl HnswStorageMut for VectorIndex {
+    fn put_node(&mut self, id: Uuid, node: &HnswNode) -> Result<(), DatabaseError> {
+        self.nodes.insert(id, node.clone());
+        Ok(())
+    }
+
+    fn delete_node(&mut self, id: Uuid) -> Result<(), DatabaseError> {
+        self.nodes.remove(&id);
+        Ok(())
+    }
+
+    fn set_entry_point(
+        &mut self,
+        entry_point: Option<HnswEntryPoint>,
+    ) -> Result<(), DatabaseError> {
+        self.entry_point = entry_point;
+        Ok(())
+    }
+
+    fn any_node(&self) -> Result<Option<(Uuid, HnswNode)>, DatabaseError> {
+        Ok(self
+            .nodes
+            .iter()
+            .next()
+            .map(|(id, node)| (*id, node.clone())))
+    }
 }
 
 #[allow(unused)]
@@ -26,6 +171,19 @@ impl SynxInMemory {
             threads: Arc::new(Mutex::new(HashMap::new())),
             messages: Arc::new(Mutex::new(HashMap::new())),
             thread_messages: Arc::new(Mutex::new(HashMap::new())),
+            message_revisions: Arc::new(Mutex::new(HashMap::new())),
+            thread_index: Arc::new(Mutex::new(VectorIndex::default())),
+            message_index: Arc::new(Mutex::new(VectorIndex::default())),
+            jobs: Arc::new(Mutex::new(HashMap::new())),
+            blobs: Arc::new(Mutex::new(HashMap::new())),
+            webhooks: Arc::new(Mutex::new(HashMap::new())),
+            webhook_deliveries: Arc::new(Mutex::new(HashMap::new())),
+            tenants: Arc::new(Mutex::new(HashMap::from([(
+                Tenant::DEFAULT_ID,
+                Tenant::default_tenant(),
+            )]))),
+            api_keys: Arc::new(Mutex::new(HashMap::new())),
+            hnsw: Hnsw::default(),
         }
     }
 }
@@ -36,105 +194,332 @@ impl Db for SynxInMemory {
         let threads = self.threads.lock().await;
         let messages = self.messages.lock().await;
         let thread_messages = self.thread_messages.lock().await;
+        let message_revisions = self.message_revisions.lock().await;
+        let thread_index = self.thread_index.lock().await;
+        let message_index = self.message_index.lock().await;
+        let jobs = self.jobs.lock().await;
+        let blobs = self.blobs.lock().await;
+        let webhooks = self.webhooks.lock().await;
+        let webhook_deliveries = self.webhook_deliveries.lock().await;
+        let tenants = self.tenants.lock().await;
+        let api_keys = self.api_keys.lock().await;
 
         Ok(serde_json::json!({
             "threads": threads.clone(),
             "messages": messages.clone(),
             "thread_messages": thread_messages.clone(),
+            "message_revisions": message_revisions.clone(),
+            "thread_index": &*thread_index,
+            "message_index": &*message_index,
+            "jobs": jobs.clone(),
+            "blobs": &*blobs,
+            "webhooks": webhooks.clone(),
+            "webhook_deliveries": webhook_deliveries.clone(),
+            "tenants": tenants.clone(),
+            "api_keys": api_keys.clone(),
         }))
     }
 
     async fn get_threads_with_embeddings(
         &self,
+        tenant_id: Uuid,
         thread_ids: &[Uuid],
     ) -> Result<Vec<Thread>, DatabaseError> {
         let threads = self.threads.lock().await;
-        thread_ids
+        Ok(thread_ids
             .iter()
-            .filter_map(|id| threads.get(id).cloned())
-            .collect::<Vec<Thread>>()
+            .filter_map(|id| threads.get(id))
+            .filter(|thread| thread.tenant_id == tenant_id)
+            .cloned()
+            .collect())
+    }
+
+    async fn search_thread_embeddings(
+        &self,
+        tenant_id: Uuid,
+        embedding: &Embedding,
+        thread_ids: Option<&[Uuid]>,
+        metadata: &Metadata,
+        limit: usize,
+    ) -> Result<Vec<(Thread, f32)>, DatabaseError> {
+        let threads = self.threads.lock().await;
+        let thread_index = self.thread_index.lock().await;
+        let thread_ids: Option<HashSet<Uuid>> =
+            thread_ids.map(|thread_ids| thread_ids.iter().copied().collect());
+
+        let vector = embedding.to_vec();
+        let accepts = |id: &Uuid| {
+            thread_ids
+                .as_ref()
+                .is_none_or(|thread_ids| thread_ids.contains(id))
+                && threads.get(id).is_some_and(|thread| {
+                    thread.tenant_id == tenant_id && metadata::matches(&thread.metadata, metadata)
+                })
+        };
+
+        let mut results = self.hnsw.search(&*thread_index, &vector, limit, &accepts)?;
+        if results.len() < limit {
+            results = self.hnsw.search_exact(
+                &*thread_index,
+                &vector,
+                limit,
+                threads.keys().copied().filter(accepts),
+            )?;
+        }
+
+        Ok(results
             .into_iter()
-            .map(|mut thread| {
-                if thread.embedding.is_none() {
-                    thread.embedding = Some(Embedding::from(vec![0.0; 1536]));
-                }
-                Ok(thread)
-            })
-            .collect()
+            .filter_map(|(id, score)| threads.get(&id).cloned().map(|thread| (thread, score)))
+            .collect())
     }
 
     async fn update_thread_summary_and_embedding(
         &self,
+        tenant_id: Uuid,
         thread_id: Uuid,
+        expected_cursor: Option<SummaryCursor>,
+        cursor: SummaryCursor,
         summary: String,
         embedding: Embedding,
     ) -> Result<(), DatabaseError> {
         let mut threads = self.threads.lock().await;
-        if let Some(thread) = threads.get_mut(&thread_id) {
-            thread.set_summary(summary);
-            thread.set_embedding(embedding);
-            Ok(())
-        } else {
-            Err(DatabaseError::NotFound)
+        let vector = embedding.to_vec();
+        let thread = threads
+            .get_mut(&thread_id)
+            .filter(|thread| thread.tenant_id == tenant_id)
+            .ok_or(DatabaseError::NotFound)?;
+        if thread.summary_cursor != expected_cursor {
+            return Err(DatabaseError::Conflict(format!(
+                "summary of thread {} was updated concurrently",
+                thread_id
+            )));
         }
+        thread.set_summary(summary, cursor);
+        thread.set_embedding(embedding);
+
+        self.thread_index
+            .lock()
+            .await
+            .insert(&self.hnsw, thread_id, vector)
     }
 
-    async fn create_thread(&self) -> Result<Thread, DatabaseError> {
-        let thread = Thread::new();
+    async fn search_message_embeddings(
+        &self,
+        tenant_id: Uuid,
+        embedding: &Embedding,
+        thread_ids: Option<&[Uuid]>,
+        limit: usize,
+    ) -> Result<Vec<(Message, f32)>, DatabaseError> {
+        let threads = self.threads.lock().await;
+        let messages = self.messages.lock().await;
+        let message_index = self.message_index.lock().await;
+        let thread_ids: Option<HashSet<Uuid>> =
+            thread_ids.map(|thread_ids| thread_ids.iter().copied().collect());
+
+        let vector = embedding.to_vec();
+        let accepts = |id: &Uuid| {
+            messages.get(id).is_some_and(|message| {
+                thread_ids
+                    .as_ref()
+                    .is_none_or(|thread_ids| thread_ids.contains(&message.thread_id))
+                    && threads
+                        .get(&message.thread_id)
+                        .is_some_and(|thread| thread.tenant_id == tenant_id)
+            })
+        };
+
+        let mut results = self
+            .hnsw
+            .search(&*message_index, &vector, limit, &accepts)?;
+        if results.len() < limit {
+            results = self.hnsw.search_exact(
+                &*message_index,
+                &vector,
+                limit,
+                messages.keys().copied().filter(accepts),
+            )?;
+        }
+
+        Ok(results
+            .into_iter()
+            .filter_map(|(id, score)| messages.get(&id).cloned().map(|message| (message, score)))
+            .collect())
+    }
+
+    async fn update_message_embedding(
+        &self,
+        tenant_id: Uuid,
+        thread_id: Uuid,
+        message_id: Uuid,
+        embedding: Option<Embedding>,
+    ) -> Result<(), DatabaseError> {
+        let threads = self.threads.lock().await;
+        if threads
+            .get(&thread_id)
+            .is_none_or(|thread| thread.tenant_id != tenant_id)
+        {
+            return Err(DatabaseError::NotFound);
+        }
+        let messages = self.messages.lock().await;
+        if messages
+            .get(&message_id)
+            .is_none_or(|message| message.thread_id != thread_id)
+        {
+            return Err(DatabaseError::NotFound);
+        }
+
+        let mut message_index = self.message_index.lock().await;
+        match embedding {
+            Some(embedding) => message_index.insert(&self.hnsw, message_id, embedding.to_vec()),
+            None => message_index.remove(&self.hnsw, message_id),
+        }
+    }
+
+    async fn create_thread(&self, tenant_id: Uuid) -> Result<Thread, DatabaseError> {
+        let thread = Thread::new(tenant_id);
         let mut threads = self.threads.lock().await;
         threads.insert(thread.id(), thread.clone());
         self.thread_messages
             .lock()
             .await
-            .insert(thread.id(), HashSet::new());
+            .insert(thread.id(), Vec::new());
         Ok(thread)
     }
 
-    async fn delete_thread(&self, thread_id: Uuid) -> Result<(), DatabaseError> {
+    async fn delete_thread(&self, tenant_id: Uuid, thread_id: Uuid) -> Result<(), DatabaseError> {
         let mut threads = self.threads.lock().await;
-        if threads.remove(&thread_id).is_none() {
+        if threads
+            .get(&thread_id)
+            .is_none_or(|thread| thread.tenant_id != tenant_id)
+        {
             return Err(DatabaseError::NotFound);
         }
+        threads.remove(&thread_id);
+
+        self.thread_index
+            .lock()
+            .await
+            .remove(&self.hnsw, thread_id)?;
 
         let mut messages = self.messages.lock().await;
         let mut thread_messages = self.thread_messages.lock().await;
+        let mut message_revisions = self.message_revisions.lock().await;
+        let mut blobs = self.blobs.lock().await;
+        let blobs = blobs.entry(tenant_id).or_default();
+        let mut message_index = self.message_index.lock().await;
 
         if let Some(message_ids) = thread_messages.remove(&thread_id) {
             for message_id in message_ids {
-                messages.remove(&message_id);
+                if let Some(message) = messages.remove(&message_id) {
+                    blobs.release(&message.content);
+                }
+                for revision in message_revisions.remove(&message_id).unwrap_or_default() {
+                    blobs.release(&revision.content);
+                }
+                message_index.remove(&self.hnsw, message_id)?;
             }
         }
 
+        self.jobs
+            .lock()
+            .await
+            .retain(|_, job| job.thread_id != thread_id);
+
         Ok(())
     }
 
     async fn create_message(
         &self,
+        tenant_id: Uuid,
         thread_id: Uuid,
         input: CreateMessage,
     ) -> Result<Message, DatabaseError> {
-        self.threads
+        let mut threads = self.threads.lock().await;
+        let thread = threads
+            .get_mut(&thread_id)
+            .filter(|thread| thread.tenant_id == tenant_id)
+            .ok_or(DatabaseError::NotFound)?;
+
+        let mut messages = self.messages.lock().await;
+        if let Some(parent_id) = input.parent_id {
+            if messages
+                .get(&parent_id)
+                .is_none_or(|parent| parent.thread_id != thread_id)
+            {
+                return Err(DatabaseError::InvalidInput(format!(
+                    "parent message {} is not in thread {}",
+                    parent_id, thread_id
+                )));
+            }
+        }
+
+        self.blobs
             .lock()
             .await
-            .get(&thread_id)
-            .ok_or(DatabaseError::NotFound)?;
+            .entry(tenant_id)
+            .or_default()
+            .retain(&input.content)?;
 
-        let message = input.into_message(thread_id);
+        let message = input.into_message(thread);
+        thread.set_last_message_at(Some(message.created_at));
         let message_id = message.id();
-        let mut messages = self.messages.lock().await;
         messages.insert(message_id, message.clone());
 
         let mut thread_messages = self.thread_messages.lock().await;
         thread_messages
             .entry(thread_id)
-            .or_insert_with(HashSet::new)
-            .insert(message_id);
+            .or_default()
+            .push(message_id);
 
         Ok(message)
     }
 
+    async fn import_threads(
+        &self,
+        imported: Vec<(Thread, Vec<Message>)>,
+    ) -> Result<(), DatabaseError> {
+        let mut threads = self.threads.lock().await;
+        let mut messages = self.messages.lock().await;
+        let mut thread_messages = self.thread_messages.lock().await;
+        let mut blobs = self.blobs.lock().await;
+
+        // Checked all at once so that a missing blob leaves nothing behind.
+        let mut contents: HashMap<Uuid, Content> = HashMap::new();
+        for (thread, messages) in &imported {
+            contents
+                .entry(thread.tenant_id)
+                .or_insert_with(|| Content(Vec::new()))
+                .0
+                .extend(
+                    messages
+                        .iter()
+                        .flat_map(|message| message.content.0.iter().cloned()),
+                );
+        }
+        for (tenant_id, content) in &contents {
+            blobs.entry(*tenant_id).or_default().check(content)?;
+        }
+        for (tenant_id, content) in &contents {
+            blobs.entry(*tenant_id).or_default().retain(content)?;
+        }
+
+        for (thread, imported_messages) in imported {
+            thread_messages.insert(
+                thread.id(),
+                imported_messages.iter().map(Message::id).collect(),
+            );
+            for message in imported_messages {
+                messages.insert(message.id(), message);
+            }
+            threads.insert(thread.id(), thread);
+        }
+
+        Ok(())
+    }
+
     async fn update_message(
         &self,
+        tenant_id: Uuid,
         thread_id: Uuid,
         message_id: Uuid,
         content: UpdateMessage,
@@ -143,38 +528,102 @@ impl Db for SynxInMemory {
             .lock()
             .await
             .get(&thread_id)
+            .filter(|thread| thread.tenant_id == tenant_id)
             .ok_or(DatabaseError::NotFound)?;
 
         let mut messages = self.messages.lock().await;
         let message = messages
             .get_mut(&message_id)
+            .filter(|message| message.thread_id == thread_id)
             .ok_or(DatabaseError::NotFound)?;
 
-        message.update_content(content);
+        self.blobs
+            .lock()
+            .await
+            .entry(tenant_id)
+            .or_default()
+            .retain(&content.content)?;
+        let mut message_revisions = self.message_revisions.lock().await;
+        let revisions = message_revisions.entry(message_id).or_default();
+        revisions.push(message.update_content(content, revisions.len() as u32 + 1));
         Ok(message.clone())
     }
 
-    async fn list_threads(&self) -> Result<Vec<Thread>, DatabaseError> {
+    async fn list_message_revisions(
+        &self,
+        tenant_id: Uuid,
+        thread_id: Uuid,
+        message_id: Uuid,
+    ) -> Result<Vec<MessageRevision>, DatabaseError> {
+        self.threads
+            .lock()
+            .await
+            .get(&thread_id)
+            .filter(|thread| thread.tenant_id == tenant_id)
+            .ok_or(DatabaseError::NotFound)?;
+        let messages = self.messages.lock().await;
+        messages
+            .get(&message_id)
+            .filter(|message| message.thread_id == thread_id)
+            .ok_or(DatabaseError::NotFound)?;
+
+        Ok(self
+            .message_revisions
+            .lock()
+            .await
+            .get(&message_id)
+            .cloned()
+            .unwrap_or_default())
+    }
+
+    async fn get_message_revision(
+        &self,
+        tenant_id: Uuid,
+        thread_id: Uuid,
+        message_id: Uuid,
+        revision: u32,
+    ) -> Result<MessageRevision, DatabaseError> {
+        self.list_message_revisions(tenant_id, thread_id, message_id)
+            .await?
+            .into_iter()
+            .find(|r| r.revision == revision)
+            .ok_or(DatabaseError::NotFound)
+    }
+
+    async fn list_threads(
+        &self,
+        tenant_id: Uuid,
+        query: &ListThreads,
+    ) -> Result<ListThreadsResponse, DatabaseError> {
         let threads = self.threads.lock().await;
-        Ok(threads.values().cloned().collect())
+        Ok(query.paginate(
+            threads
+                .values()
+                .filter(|thread| thread.tenant_id == tenant_id)
+                .cloned(),
+        ))
     }
 
-    async fn get_thread(&self, thread_id: Uuid) -> Result<Thread, DatabaseError> {
+    async fn get_thread(&self, tenant_id: Uuid, thread_id: Uuid) -> Result<Thread, DatabaseError> {
         let threads = self.threads.lock().await;
         threads
             .get(&thread_id)
+            .filter(|thread| thread.tenant_id == tenant_id)
             .cloned()
             .ok_or(DatabaseError::NotFound)
     }
 
     async fn get_thread_messages(
         &self,
+        tenant_id: Uuid,
         thread_id: Uuid,
-        limit: Option<usize>,
-        offset: Option<usize>,
+        query: &ListMessages,
     ) -> Result<ThreadMessagesResponse, DatabaseError> {
         let threads = self.threads.lock().await;
-        if !threads.contains_key(&thread_id) {
+        if threads
+            .get(&thread_id)
+            .is_none_or(|thread| thread.tenant_id != tenant_id)
+        {
             return Err(DatabaseError::NotFound);
         }
 
@@ -182,57 +631,413 @@ impl Db for SynxInMemory {
         let messages = self.messages.lock().await;
 
         let message_ids = thread_messages.get(&thread_id).cloned().unwrap_or_default();
-        let mut thread_messages: Vec<Message> = message_ids
+        let thread_messages: Vec<Message> = message_ids
             .iter()
             .filter_map(|id| messages.get(id).cloned())
             .collect();
 
-        thread_messages.sort_by(|a, b| a.created_at().cmp(&b.created_at()));
+        Ok(query.paginate(thread_messages))
+    }
 
-        let total = thread_messages.len();
-        let offset = offset.unwrap_or(0);
-        let limit = limit.unwrap_or(total);
+    async fn delete_message(
+        &self,
+        tenant_id: Uuid,
+        thread_id: Uuid,
+        message_id: Uuid,
+    ) -> Result<(), DatabaseError> {
+        let mut threads = self.threads.lock().await;
+        let thread = threads
+            .get_mut(&thread_id)
+            .filter(|thread| thread.tenant_id == tenant_id)
+            .ok_or(DatabaseError::NotFound)?;
 
-        let paginated_messages = thread_messages
-            .into_iter()
-            .skip(offset)
-            .take(limit)
-            .collect();
+        let mut messages = self.messages.lock().await;
+        if messages
+            .get(&message_id)
+            .is_none_or(|message| message.thread_id != thread_id)
+        {
+            return Err(DatabaseError::NotFound);
+        }
+        let revisions = self.message_revisions.lock().await.remove(&message_id);
+        let mut tenant_blobs = self.blobs.lock().await;
+        let blobs = tenant_blobs.entry(tenant_id).or_default();
+        if let Some(message) = messages.remove(&message_id) {
+            blobs.release(&message.content);
+        }
+        for revision in revisions.unwrap_or_default() {
+            blobs.release(&revision.content);
+        }
+        drop(tenant_blobs);
 
-        Ok(ThreadMessagesResponse {
-            messages: paginated_messages,
-            total,
-            offset,
-            limit,
-        })
-    }
+        if let Some(message_ids) = self.thread_messages.lock().await.get_mut(&thread_id) {
+            message_ids.retain(|&id| id != message_id);
+            thread.set_last_message_at(
+                message_ids
+                    .iter()
+                    .filter_map(|id| messages.get(id))
+                    .map(|message| message.created_at)
+                    .max(),
+            );
+        }
 
-    async fn delete_message(&self, thread_id: Uuid, message_id: Uuid) -> Result<(), DatabaseError> {
-        self.threads
+        self.message_index
             .lock()
             .await
-            .get(&thread_id)
-            .ok_or(DatabaseError::NotFound)?;
-
-        let mut messages = self.messages.lock().await;
-        messages
-            .remove(&message_id)
-            .ok_or(DatabaseError::NotFound)?;
+            .remove(&self.hnsw, message_id)?;
 
         Ok(())
     }
 
     async fn update_thread(
         &self,
+        tenant_id: Uuid,
         thread_id: Uuid,
         update: UpdateThread,
     ) -> Result<Thread, DatabaseError> {
         let mut threads = self.threads.lock().await;
-        if let Some(thread) = threads.get_mut(&thread_id) {
-            thread.set_title(update.title);
+        if let Some(thread) = threads
+            .get_mut(&thread_id)
+            .filter(|thread| thread.tenant_id == tenant_id)
+        {
+            thread.update(update);
             Ok(thread.clone())
         } else {
             Err(DatabaseError::NotFound)
         }
     }
+
+    async fn get_message(
+        &self,
+        tenant_id: Uuid,
+        thread_id: Uuid,
+        message_id: Uuid,
+    ) -> Result<Message, DatabaseError> {
+        self.threads
+            .lock()
+            .await
+            .get(&thread_id)
+            .filter(|thread| thread.tenant_id == tenant_id)
+            .ok_or(DatabaseError::NotFound)?;
+        let messages = self.messages.lock().await;
+        messages
+            .get(&message_id)
+            .filter(|message| message.thread_id == thread_id)
+            .cloned()
+            .ok_or(DatabaseError::NotFound)
+    }
+
+    async fn put_blob(
+        &self,
+        tenant_id: Uuid,
+        blob: Blob,
+        data: Vec<u8>,
+    ) -> Result<Blob, DatabaseError> {
+        let mut blobs = self.blobs.lock().await;
+        let blobs = blobs.entry(tenant_id).or_default();
+        if let Some(existing) = blobs.blobs.get_mut(&blob.hash) {
+            existing.uploaded_at = blob.uploaded_at;
+            return Ok(existing.clone());
+        }
+
+        blobs.data.insert(blob.hash.clone(), data);
+        blobs.blobs.insert(blob.hash.clone(), blob.clone());
+        Ok(blob)
+    }
+
+    async fn get_blob(
+        &self,
+        tenant_id: Uuid,
+        hash: &str,
+    ) -> Result<(Blob, Vec<u8>), DatabaseError> {
+        let blobs = self.blobs.lock().await;
+        let Some(blobs) = blobs.get(&tenant_id) else {
+            return Err(DatabaseError::NotFound);
+        };
+        match (blobs.blobs.get(hash), blobs.data.get(hash)) {
+            (Some(blob), Some(data)) => Ok((blob.clone(), data.clone())),
+            _ => Err(DatabaseError::NotFound),
+        }
+    }
+
+    async fn delete_unreferenced_blobs(&self, uploaded_before: u64) -> Result<u64, DatabaseError> {
+        Ok(self
+            .blobs
+            .lock()
+            .await
+            .values_mut()
+            .map(|blobs| blobs.collect(uploaded_before))
+            .sum())
+    }
+
+    async fn save_job(&self, job: &Job) -> Result<(), DatabaseError> {
+        self.threads
+            .lock()
+            .await
+            .get(&job.thread_id)
+            .filter(|thread| thread.tenant_id == job.tenant_id)
+            .ok_or(DatabaseError::NotFound)?;
+
+        self.jobs.lock().await.insert(job.id(), job.clone());
+        Ok(())
+    }
+
+    async fn delete_job(
+        &self,
+        tenant_id: Uuid,
+        thread_id: Uuid,
+        job_id: Uuid,
+    ) -> Result<(), DatabaseError> {
+        let mut jobs = self.jobs.lock().await;
+        if jobs
+            .get(&job_id)
+            .is_none_or(|job| job.tenant_id != tenant_id || job.thread_id != thread_id)
+        {
+            return Err(DatabaseError::NotFound);
+        }
+
+        jobs.remove(&job_id);
+        Ok(())
+    }
+
+    async fn get_pending_jobs(&self) -> Result<Vec<Job>, DatabaseError> {
+        let jobs = self.jobs.lock().await;
+        let mut jobs: Vec<Job> = jobs
+            .values()
+            .filter(|job| job.status == JobStatus::Pending)
+            .cloned()
+            .collect();
+        jobs.sort_by_key(|job| job.created_at);
+        Ok(jobs)
+    }
+
+    async fn get_thread_jobs(
+        &self,
+        tenant_id: Uuid,
+        thread_id: Uuid,
+    ) -> Result<Vec<Job>, DatabaseError> {
+        self.threads
+            .lock()
+            .await
+            .get(&thread_id)
+            .filter(|thread| thread.tenant_id == tenant_id)
+            .ok_or(DatabaseError::NotFound)?;
+
+        let jobs = self.jobs.lock().await;
+        let mut jobs: Vec<Job> = jobs
+            .values()
+            .filter(|job| job.thread_id == thread_id)
+            .cloned()
+            .collect();
+        jobs.sort_by_key(|job| job.created_at);
+        Ok(jobs)
+    }
+
+    async fn save_webhook(&self, webhook: &Webhook) -> Result<(), DatabaseError> {
+        let tenants = self.tenants.lock().await;
+        if !tenants.contains_key(&webhook.tenant_id) {
+            return Err(DatabaseError::NotFound);
+        }
+        self.webhooks
+            .lock()
+            .await
+            .insert(webhook.id(), webhook.clone());
+        Ok(())
+    }
+
+    async fn get_webhook(
+        &self,
+        tenant_id: Uuid,
+        webhook_id: Uuid,
+    ) -> Result<Webhook, DatabaseError> {
+        self.webhooks
+            .lock()
+            .await
+            .get(&webhook_id)
+            .filter(|webhook| webhook.tenant_id == tenant_id)
+            .cloned()
+            .ok_or(DatabaseError::NotFound)
+    }
+
+    async fn list_webhooks(&self, tenant_id: Uuid) -> Result<Vec<Webhook>, DatabaseError> {
+        let mut webhooks: Vec<Webhook> = self
+            .webhooks
+            .lock()
+            .await
+            .values()
+            .filter(|webhook| webhook.tenant_id == tenant_id)
+            .cloned()
+            .collect();
+        webhooks.sort_by_key(|webhook| webhook.created_at);
+        Ok(webhooks)
+    }
+
+    async fn delete_webhook(&self, tenant_id: Uuid, webhook_id: Uuid) -> Result<(), DatabaseError> {
+        let mut webhooks = self.webhooks.lock().await;
+        if webhooks
+            .get(&webhook_id)
+            .is_none_or(|webhook| webhook.tenant_id != tenant_id)
+        {
+            return Err(DatabaseError::NotFound);
+        }
+        webhooks.remove(&webhook_id);
+        self.webhook_deliveries
+            .lock()
+            .await
+            .retain(|_, delivery| delivery.webhook_id != webhook_id);
+        Ok(())
+    }
+
+    async fn save_webhook_delivery(&self, delivery: &WebhookDelivery) -> Result<(), DatabaseError> {
+        let webhooks = self.webhooks.lock().await;
+        if !webhooks.contains_key(&delivery.webhook_id) {
+            return Err(DatabaseError::NotFound);
+        }
+        self.webhook_deliveries
+            .lock()
+            .await
+            .insert(delivery.id(), delivery.clone());
+        Ok(())
+    }
+
+    async fn list_webhook_deliveries(
+        &self,
+        tenant_id: Uuid,
+        webhook_id: Uuid,
+    ) -> Result<Vec<WebhookDelivery>, DatabaseError> {
+        self.webhooks
+            .lock()
+            .await
+            .get(&webhook_id)
+            .filter(|webhook| webhook.tenant_id == tenant_id)
+            .ok_or(DatabaseError::NotFound)?;
+
+        let deliveries = self.webhook_deliveries.lock().await;
+        let mut deliveries: Vec<WebhookDelivery> = deliveries
+            .values()
+            .filter(|delivery| delivery.webhook_id == webhook_id)
+            .cloned()
+            .collect();
+        deliveries.sort_by_key(|delivery| delivery.created_at);
+        Ok(deliveries)
+    }
+
+    async fn get_pending_webhook_deliveries(&self) -> Result<Vec<WebhookDelivery>, DatabaseError> {
+        let deliveries = self.webhook_deliveries.lock().await;
+        let mut deliveries: Vec<WebhookDelivery> = deliveries
+            .values()
+            .filter(|delivery| delivery.status == DeliveryStatus::Pending)
+            .cloned()
+            .collect();
+        deliveries.sort_by_key(|delivery| delivery.created_at);
+        Ok(deliveries)
+    }
+
+    async fn save_tenant(&self, tenant: &Tenant) -> Result<(), DatabaseError> {
+        self.tenants
+            .lock()
+            .await
+            .insert(tenant.id(), tenant.clone());
+        Ok(())
+    }
+
+    async fn get_tenant(&self, tenant_id: Uuid) -> Result<Tenant, DatabaseError> {
+        self.tenants
+            .lock()
+            .await
+            .get(&tenant_id)
+            .cloned()
+            .ok_or(DatabaseError::NotFound)
+    }
+
+    async fn list_tenants(&self) -> Result<Vec<Tenant>, DatabaseError> {
+        let mut tenants: Vec<Tenant> = self.tenants.lock().await.values().cloned().collect();
+        tenants.sort_by_key(|tenant| tenant.created_at);
+        Ok(tenants)
+    }
+
+    async fn delete_tenant(&self, tenant_id: Uuid) -> Result<(), DatabaseError> {
+        let mut tenants = self.tenants.lock().await;
+        if !tenants.contains_key(&tenant_id) {
+            return Err(DatabaseError::NotFound);
+        }
+        if self
+            .threads
+            .lock()
+            .await
+            .values()
+            .any(|thread| thread.tenant_id == tenant_id)
+        {
+            return Err(DatabaseError::Conflict(format!(
+                "tenant {} still owns threads",
+                tenant_id
+            )));
+        }
+
+        tenants.remove(&tenant_id);
+        self.api_keys
+            .lock()
+            .await
+            .retain(|_, api_key| api_key.tenant_id != tenant_id);
+        self.webhooks
+            .lock()
+            .await
+            .retain(|_, webhook| webhook.tenant_id != tenant_id);
+        self.webhook_deliveries
+            .lock()
+            .await
+            .retain(|_, delivery| delivery.tenant_id != tenant_id);
+        self.blobs.lock().await.remove(&tenant_id);
+        Ok(())
+    }
+
+    async fn save_api_key(&self, api_key: &ApiKey) -> Result<(), DatabaseError> {
+        let tenants = self.tenants.lock().await;
+        if !tenants.contains_key(&api_key.tenant_id) {
+            return Err(DatabaseError::NotFound);
+        }
+        self.api_keys
+            .lock()
+            .await
+            .insert(api_key.id(), api_key.clone());
+        Ok(())
+    }
+
+    async fn get_api_key_by_hash(&self, key_hash: &str) -> Result<ApiKey, DatabaseError> {
+        self.api_keys
+            .lock()
+            .await
+            .values()
+            .find(|api_key| api_key.key_hash == key_hash)
+            .cloned()
+            .ok_or(DatabaseError::NotFound)
+    }
+
+    async fn list_api_keys(&self, tenant_id: Uuid) -> Result<Vec<ApiKey>, DatabaseError> {
+        self.tenants
+            .lock()
+            .await
+            .get(&tenant_id)
+            .ok_or(DatabaseError::NotFound)?;
+
+        let api_keys = self.api_keys.lock().await;
+        let mut api_keys: Vec<ApiKey> = api_keys
+            .values()
+            .filter(|api_key| api_key.tenant_id == tenant_id)
+            .cloned()
+            .collect();
+        api_keys.sort_by_key(|api_key| api_key.created_at);
+        Ok(api_keys)
+    }
+
+    async fn delete_api_key(&self, tenant_id: Uuid, key_id: Uuid) -> Result<(), DatabaseError> {
+        let mut api_keys = self.api_keys.lock().await;
+        if api_keys
+            .get(&key_id)
+            .is_none_or(|api_key| api_key.tenant_id != tenant_id)
+        {
+            return Err(DatabaseError::NotFound);
+        }
+        api_keys.remove(&key_id);
+        Ok(())
+    }
 }
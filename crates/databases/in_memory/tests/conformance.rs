@@ -0,0 +1,5 @@
+use std::sync::Arc;
+
+use synx_in_memory_database::SynxInMemory;
+
+synx_database_conformance::db_conformance_tests!(Arc::new(SynxInMemory::new()));
@@ -0,0 +1,1622 @@
+mod sqlite_index;
+mod sqlite_schema;
+mod sqlite_types;
+
+use std::{collections::HashSet, path::Path};
+
+pub use rusqlite;
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use sqlite_index::{SqliteIndex, SqliteIndexKind};
+use sqlite_types::{SqlEmbedding, SqlJson, SqlUuid};
+use synx_database::{DatabaseError, Db};
+use synx_domain::{
+    api_key::ApiKey,
+    blob::Blob,
+    content::Content,
+    embedding::Embedding,
+    job::{Job, JobStatus},
+    message::{
+        CreateMessage, ListMessages, Message, MessageRevision, Role, ThreadMessagesResponse,
+        UpdateMessage,
+    },
+    metadata::{self, Metadata},
+    tenant::Tenant,
+    thread::{
+        ListThreads, ListThreadsResponse, SortOrder, SummaryCursor, Thread, ThreadSort,
+        UpdateThread,
+    },
+    webhook::{DeliveryStatus, Webhook, WebhookDelivery},
+};
+use synx_vector_index::{Hnsw, HnswEntryPoint, HnswNode};
+use tokio::sync::Mutex;
+use uuid::Uuid;
+
+const THREAD_COLUMNS: &str =
+    "id, title, summary, summary_cursor, metadata, created_at, updated_at, last_message_at, tenant_id";
+const MESSAGE_COLUMNS: &str =
+    "id, thread_id, role, content, created_at, updated_at, metadata, parent_id";
+const REVISION_COLUMNS: &str = "message_id, revision, content, created_at, replaced_at";
+const BLOB_COLUMNS: &str = "hash, mime_type, size, created_at, uploaded_at, ref_count";
+
+#[derive(Debug)]
+pub struct SynxSqliteDatabase {
+    connection: Mutex<Connection>,
+    hnsw: Hnsw,
+}
+
+impl SynxSqliteDatabase {
+    pub fn new(mut connection: Connection) -> Result<Self, DatabaseError> {
+        sqlite_schema::migrate(&mut connection)?;
+
+        Ok(Self {
+            connection: Mutex::new(connection),
+            hnsw: Hnsw::default(),
+        })
+    }
+
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, DatabaseError> {
+        let connection =
+            Connection::open(path).map_err(|e| DatabaseError::ConnectionError(e.to_string()))?;
+        connection
+            .pragma_update(None, "journal_mode", "WAL")
+            .map_err(|e| DatabaseError::ConnectionError(e.to_string()))?;
+
+        Self::new(connection)
+    }
+
+    fn index(connection: &Connection, kind: SqliteIndexKind) -> SqliteIndex<'_> {
+        SqliteIndex { connection, kind }
+    }
+
+    fn thread_from_row(row: &Row) -> rusqlite::Result<Thread> {
+        Ok(Thread {
+            id: row.get::<_, SqlUuid>(0)?.0,
+            title: row.get(1)?,
+            summary: row.get(2)?,
+            summary_cursor: row
+                .get::<_, Option<SqlJson<SummaryCursor>>>(3)?
+                .map(|cursor| cursor.0),
+            metadata: row.get::<_, SqlJson<Metadata>>(4)?.0,
+            created_at: row.get(5)?,
+            updated_at: row.get(6)?,
+            last_message_at: row.get(7)?,
+            tenant_id: row.get::<_, SqlUuid>(8)?.0,
+            embedding: None,
+        })
+    }
+
+    fn message_from_row(row: &Row) -> rusqlite::Result<Message> {
+        Ok(Message {
+            id: row.get::<_, SqlUuid>(0)?.0,
+            thread_id: row.get::<_, SqlUuid>(1)?.0,
+            role: Role::from(row.get::<_, String>(2)?),
+            content: row.get::<_, SqlJson<Content>>(3)?.0,
+            created_at: row.get(4)?,
+            updated_at: row.get(5)?,
+            metadata: row.get::<_, SqlJson<Metadata>>(6)?.0,
+            parent_id: row.get::<_, Option<SqlUuid>>(7)?.map(|id| id.0),
+        })
+    }
+
+    fn revision_from_row(row: &Row) -> rusqlite::Result<MessageRevision> {
+        Ok(MessageRevision {
+            message_id: row.get::<_, SqlUuid>(0)?.0,
+            revision: row.get(1)?,
+            content: row.get::<_, SqlJson<Content>>(2)?.0,
+            created_at: row.get(3)?,
+            replaced_at: row.get(4)?,
+        })
+    }
+
+    fn query_revisions(
+        connection: &Connection,
+        sql: &str,
+        params: impl rusqlite::Params,
+    ) -> Result<Vec<MessageRevision>, DatabaseError> {
+        connection
+            .prepare_cached(sql)
+            .and_then(|mut statement| {
+                statement
+                    .query_map(params, Self::revision_from_row)?
+                    .collect()
+            })
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))
+    }
+
+    fn blob_from_row(row: &Row) -> rusqlite::Result<Blob> {
+        Ok(Blob {
+            hash: row.get(0)?,
+            mime_type: row.get(1)?,
+            size: row.get(2)?,
+            created_at: row.get(3)?,
+            uploaded_at: row.get(4)?,
+            ref_count: row.get(5)?,
+        })
+    }
+
+    /// Counts a reference to each of the tenant's blobs of `content`, failing if
+    /// one is missing.
+    fn retain_blobs(
+        connection: &Connection,
+        tenant_id: Uuid,
+        content: &Content,
+    ) -> Result<(), DatabaseError> {
+        for hash in content.blob_hashes() {
+            let updated = connection
+                .execute(
+                    "UPDATE blobs SET ref_count = ref_count + 1 WHERE tenant_id = ?1 AND hash = ?2",
+                    params![SqlUuid(tenant_id), hash],
+                )
+                .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+            if updated == 0 {
+                return Err(DatabaseError::InvalidInput(format!(
+                    "blob {} does not exist",
+                    hash
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Drops the blob references of the tenant's messages whose `column` is `id`,
+    /// revisions included, removing blobs left unreferenced past their grace period.
+    fn release_blobs(
+        connection: &Connection,
+        tenant_id: Uuid,
+        column: &str,
+        id: Uuid,
+    ) -> Result<(), DatabaseError> {
+        let contents: Vec<Content> = connection
+            .prepare_cached(&format!(
+                "SELECT content FROM messages WHERE {column} = ?1 UNION ALL SELECT message_revisions.content FROM message_revisions JOIN messages ON messages.id = message_revisions.message_id WHERE messages.{column} = ?1"
+            ))
+            .and_then(|mut statement| {
+                statement
+                    .query_map([SqlUuid(id)], |row| row.get::<_, SqlJson<Content>>(0))?
+                    .map(|content| content.map(|content| content.0))
+                    .collect()
+            })
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
+
+        let cutoff = Blob::collection_cutoff();
+        for content in &contents {
+            for hash in content.blob_hashes() {
+                connection
+                    .execute(
+                        "UPDATE blobs SET ref_count = ref_count - 1 WHERE tenant_id = ?1 AND hash = ?2",
+                        params![SqlUuid(tenant_id), hash],
+                    )
+                    .and_then(|_| {
+                        connection.execute(
+                            "DELETE FROM blobs WHERE tenant_id = ?1 AND hash = ?2 AND ref_count <= 0 AND uploaded_at < ?3",
+                            params![SqlUuid(tenant_id), hash, cutoff],
+                        )
+                    })
+                    .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+            }
+        }
+        Ok(())
+    }
+
+    fn job_status(status: JobStatus) -> &'static str {
+        match status {
+            JobStatus::Pending => "pending",
+            JobStatus::Failed => "failed",
+        }
+    }
+
+    fn delivery_status(status: DeliveryStatus) -> &'static str {
+        match status {
+            DeliveryStatus::Pending => "pending",
+            DeliveryStatus::Succeeded => "succeeded",
+            DeliveryStatus::Failed => "failed",
+        }
+    }
+
+    /// The thread, unless it is missing or owned by another tenant.
+    fn get_thread_internal(
+        connection: &Connection,
+        tenant_id: Uuid,
+        thread_id: Uuid,
+    ) -> Result<Option<Thread>, DatabaseError> {
+        connection
+            .prepare_cached(&format!(
+                "SELECT {THREAD_COLUMNS} FROM threads WHERE tenant_id = ?1 AND id = ?2"
+            ))
+            .and_then(|mut statement| {
+                statement
+                    .query_row(
+                        params![SqlUuid(tenant_id), SqlUuid(thread_id)],
+                        Self::thread_from_row,
+                    )
+                    .optional()
+            })
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))
+    }
+
+    fn get_thread_with_embedding(
+        connection: &Connection,
+        tenant_id: Uuid,
+        thread_id: Uuid,
+    ) -> Result<Option<Thread>, DatabaseError> {
+        connection
+            .prepare_cached(&format!(
+                "SELECT {THREAD_COLUMNS}, embedding FROM threads WHERE tenant_id = ?1 AND id = ?2"
+            ))
+            .and_then(|mut statement| {
+                statement
+                    .query_row(params![SqlUuid(tenant_id), SqlUuid(thread_id)], |row| {
+                        let mut thread = Self::thread_from_row(row)?;
+                        thread.embedding =
+                            row.get::<_, Option<SqlEmbedding>>(9)?.map(Embedding::from);
+                        Ok(thread)
+                    })
+                    .optional()
+            })
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))
+    }
+
+    /// The message, unless it is missing or its thread is owned by another tenant.
+    fn get_message_internal(
+        connection: &Connection,
+        tenant_id: Uuid,
+        thread_id: Uuid,
+        message_id: Uuid,
+    ) -> Result<Option<Message>, DatabaseError> {
+        connection
+            .prepare_cached(&format!(
+                "SELECT {MESSAGE_COLUMNS} FROM messages WHERE thread_id = ?1 AND id = ?2 AND thread_id IN (SELECT id FROM threads WHERE tenant_id = ?3)"
+            ))
+            .and_then(|mut statement| {
+                statement
+                    .query_row(
+                        params![SqlUuid(thread_id), SqlUuid(message_id), SqlUuid(tenant_id)],
+                        Self::message_from_row,
+                    )
+                    .optional()
+            })
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))
+    }
+
+    fn message_thread_id(
+        connection: &Connection,
+        tenant_id: Uuid,
+        message_id: Uuid,
+    ) -> Result<Option<Uuid>, DatabaseError> {
+        connection
+            .prepare_cached("SELECT thread_id FROM messages WHERE id = ?1 AND thread_id IN (SELECT id FROM threads WHERE tenant_id = ?2)")
+            .and_then(|mut statement| {
+                statement
+                    .query_row(params![SqlUuid(message_id), SqlUuid(tenant_id)], |row| {
+                        row.get::<_, SqlUuid>(0)
+                    })
+                    .optional()
+            })
+            .map(|thread_id| thread_id.map(|thread_id| thread_id.0))
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))
+    }
+
+    fn query_jobs(
+        connection: &Connection,
+        sql: &str,
+        params: impl rusqlite::Params,
+    ) -> Result<Vec<Job>, DatabaseError> {
+        connection
+            .prepare_cached(sql)
+            .and_then(|mut statement| {
+                statement
+                    .query_map(params, |row| row.get::<_, SqlJson<Job>>(0))?
+                    .map(|job| job.map(|job| job.0))
+                    .collect::<rusqlite::Result<Vec<Job>>>()
+            })
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))
+    }
+
+    fn query_webhooks(
+        connection: &Connection,
+        sql: &str,
+        params: impl rusqlite::Params,
+    ) -> Result<Vec<Webhook>, DatabaseError> {
+        connection
+            .prepare_cached(sql)
+            .and_then(|mut statement| {
+                statement
+                    .query_map(params, |row| row.get::<_, SqlJson<Webhook>>(0))?
+                    .map(|webhook| webhook.map(|webhook| webhook.0))
+                    .collect::<rusqlite::Result<Vec<Webhook>>>()
+            })
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))
+    }
+
+    fn query_webhook_deliveries(
+        connection: &Connection,
+        sql: &str,
+        params: impl rusqlite::Params,
+    ) -> Result<Vec<WebhookDelivery>, DatabaseError> {
+        connection
+            .prepare_cached(sql)
+            .and_then(|mut statement| {
+                statement
+                    .query_map(params, |row| row.get::<_, SqlJson<WebhookDelivery>>(0))?
+                    .map(|delivery| delivery.map(|delivery| delivery.0))
+                    .collect::<rusqlite::Result<Vec<WebhookDelivery>>>()
+            })
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))
+    }
+
+    fn query_tenants(
+        connection: &Connection,
+        sql: &str,
+        params: impl rusqlite::Params,
+    ) -> Result<Vec<Tenant>, DatabaseError> {
+        connection
+            .prepare_cached(sql)
+            .and_then(|mut statement| {
+                statement
+                    .query_map(params, |row| row.get::<_, SqlJson<Tenant>>(0))?
+                    .map(|tenant| tenant.map(|tenant| tenant.0))
+                    .collect::<rusqlite::Result<Vec<Tenant>>>()
+            })
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))
+    }
+
+    fn query_api_keys(
+        connection: &Connection,
+        sql: &str,
+        params: impl rusqlite::Params,
+    ) -> Result<Vec<ApiKey>, DatabaseError> {
+        connection
+            .prepare_cached(sql)
+            .and_then(|mut statement| {
+                statement
+                    .query_map(params, |row| row.get::<_, SqlJson<ApiKey>>(0))?
+                    .map(|api_key| api_key.map(|api_key| api_key.0))
+                    .collect::<rusqlite::Result<Vec<ApiKey>>>()
+            })
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))
+    }
+
+    fn query_ids(
+        connection: &Connection,
+        sql: &str,
+        params: impl rusqlite::Params,
+    ) -> Result<Vec<Uuid>, DatabaseError> {
+        connection
+            .prepare_cached(sql)
+            .and_then(|mut statement| {
+                statement
+                    .query_map(params, |row| row.get::<_, SqlUuid>(0))?
+                    .map(|id| id.map(|id| id.0))
+                    .collect::<rusqlite::Result<Vec<Uuid>>>()
+            })
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))
+    }
+
+    /// Removes a message from the index while its embedding is still readable, so
+    /// its neighbours can be reconnected.
+    fn unindex_message(
+        &self,
+        connection: &Connection,
+        message_id: Uuid,
+    ) -> Result<(), DatabaseError> {
+        self.hnsw.remove(
+            &mut Self::index(connection, SqliteIndexKind::Message),
+            message_id,
+        )
+    }
+}
+
+#[async_trait::async_trait]
+impl Db for SynxSqliteDatabase {
+    async fn get_threads_with_embeddings(
+        &self,
+        tenant_id: Uuid,
+        thread_ids: &[Uuid],
+    ) -> Result<Vec<Thread>, DatabaseError> {
+        let connection = self.connection.lock().await;
+        thread_ids
+            .iter()
+            .filter_map(|&id| {
+                Self::get_thread_with_embedding(&connection, tenant_id, id).transpose()
+            })
+            .collect()
+    }
+
+    async fn search_thread_embeddings(
+        &self,
+        tenant_id: Uuid,
+        embedding: &Embedding,
+        thread_ids: Option<&[Uuid]>,
+        metadata: &Metadata,
+        limit: usize,
+    ) -> Result<Vec<(Thread, f32)>, DatabaseError> {
+        let connection = self.connection.lock().await;
+        let thread_ids: Option<HashSet<Uuid>> =
+            thread_ids.map(|thread_ids| thread_ids.iter().copied().collect());
+
+        let index = Self::index(&connection, SqliteIndexKind::Thread);
+        let vector = embedding.to_vec();
+        let accepts = |id: &Uuid| {
+            thread_ids
+                .as_ref()
+                .is_none_or(|thread_ids| thread_ids.contains(id))
+                && matches!(
+                    Self::get_thread_internal(&connection, tenant_id, *id),
+                    Ok(Some(thread)) if metadata::matches(&thread.metadata, metadata)
+                )
+        };
+
+        let mut results = self.hnsw.search(&index, &vector, limit, &accepts)?;
+        if results.len() < limit {
+            let candidates = match &thread_ids {
+                Some(thread_ids) => thread_ids.iter().copied().collect(),
+                None => Self::query_ids(
+                    &connection,
+                    "SELECT id FROM threads WHERE tenant_id = ?1 AND embedding IS NOT NULL",
+                    [SqlUuid(tenant_id)],
+                )?,
+            };
+            results = self.hnsw.search_exact(
+                &index,
+                &vector,
+                limit,
+                candidates.into_iter().filter(accepts),
+            )?;
+        }
+
+        results
+            .into_iter()
+            .filter_map(|(id, score)| {
+                Self::get_thread_with_embedding(&connection, tenant_id, id)
+                    .transpose()
+                    .map(|thread| thread.map(|thread| (thread, score)))
+            })
+            .collect()
+    }
+
+    async fn update_thread_summary_and_embedding(
+        &self,
+        tenant_id: Uuid,
+        thread_id: Uuid,
+        expected_cursor: Option<SummaryCursor>,
+        cursor: SummaryCursor,
+        summary: String,
+        embedding: Embedding,
+    ) -> Result<(), DatabaseError> {
+        let mut connection = self.connection.lock().await;
+        let tx = connection
+            .transaction()
+            .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+
+        let thread =
+            Self::get_thread_internal(&tx, tenant_id, thread_id)?.ok_or(DatabaseError::NotFound)?;
+        if thread.summary_cursor != expected_cursor {
+            return Err(DatabaseError::Conflict(format!(
+                "summary of thread {} was updated concurrently",
+                thread_id
+            )));
+        }
+
+        tx.execute(
+            "UPDATE threads SET summary = ?2, summary_cursor = ?3, embedding = ?4 WHERE id = ?1",
+            params![
+                SqlUuid(thread_id),
+                summary,
+                SqlJson(cursor),
+                SqlEmbedding::from(&embedding)
+            ],
+        )
+        .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+        self.hnsw.insert(
+            &mut Self::index(&tx, SqliteIndexKind::Thread),
+            thread_id,
+            &embedding.to_vec(),
+        )?;
+
+        tx.commit()
+            .map_err(|e| DatabaseError::OperationFailed(e.to_string()))
+    }
+
+    async fn search_message_embeddings(
+        &self,
+        tenant_id: Uuid,
+        embedding: &Embedding,
+        thread_ids: Option<&[Uuid]>,
+        limit: usize,
+    ) -> Result<Vec<(Message, f32)>, DatabaseError> {
+        let connection = self.connection.lock().await;
+        let thread_ids: Option<HashSet<Uuid>> =
+            thread_ids.map(|thread_ids| thread_ids.iter().copied().collect());
+
+        let index = Self::index(&connection, SqliteIndexKind::Message);
+        let vector = embedding.to_vec();
+        let accepts = |id: &Uuid| {
+            matches!(
+                Self::message_thread_id(&connection, tenant_id, *id),
+                Ok(Some(thread_id)) if thread_ids
+                    .as_ref()
+                    .is_none_or(|thread_ids| thread_ids.contains(&thread_id))
+            )
+        };
+
+        let mut results = self.hnsw.search(&index, &vector, limit, &accepts)?;
+        if results.len() < limit {
+            let candidates = match &thread_ids {
+                Some(thread_ids) => {
+                    let mut candidates = Vec::new();
+                    for &thread_id in thread_ids {
+                        candidates.extend(Self::query_ids(
+                            &connection,
+                            "SELECT id FROM messages WHERE thread_id = ?1 AND embedding IS NOT NULL",
+                            [SqlUuid(thread_id)],
+                        )?);
+                    }
+                    candidates
+                }
+                None => Self::query_ids(
+                    &connection,
+                    "SELECT id FROM messages WHERE embedding IS NOT NULL AND thread_id IN (SELECT id FROM threads WHERE tenant_id = ?1)",
+                    [SqlUuid(tenant_id)],
+                )?,
+            };
+            results = self.hnsw.search_exact(
+                &index,
+                &vector,
+                limit,
+                candidates.into_iter().filter(accepts),
+            )?;
+        }
+
+        let mut messages = Vec::with_capacity(results.len());
+        for (message_id, score) in results {
+            let Some(thread_id) = Self::message_thread_id(&connection, tenant_id, message_id)?
+            else {
+                continue;
+            };
+            if let Some(message) =
+                Self::get_message_internal(&connection, tenant_id, thread_id, message_id)?
+            {
+                messages.push((message, score));
+            }
+        }
+
+        Ok(messages)
+    }
+
+    async fn update_message_embedding(
+        &self,
+        tenant_id: Uuid,
+        thread_id: Uuid,
+        message_id: Uuid,
+        embedding: Option<Embedding>,
+    ) -> Result<(), DatabaseError> {
+        let mut connection = self.connection.lock().await;
+        let tx = connection
+            .transaction()
+            .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+
+        if Self::get_message_internal(&tx, tenant_id, thread_id, message_id)?.is_none() {
+            return Err(DatabaseError::NotFound);
+        }
+
+        match embedding {
+            Some(embedding) => {
+                tx.execute(
+                    "UPDATE messages SET embedding = ?2 WHERE id = ?1",
+                    params![SqlUuid(message_id), SqlEmbedding::from(&embedding)],
+                )
+                .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+                self.hnsw.insert(
+                    &mut Self::index(&tx, SqliteIndexKind::Message),
+                    message_id,
+                    &embedding.to_vec(),
+                )?;
+            }
+            None => {
+                self.unindex_message(&tx, message_id)?;
+                tx.execute(
+                    "UPDATE messages SET embedding = NULL WHERE id = ?1",
+                    [SqlUuid(message_id)],
+                )
+                .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+            }
+        }
+
+        tx.commit()
+            .map_err(|e| DatabaseError::OperationFailed(e.to_string()))
+    }
+
+    async fn create_thread(&self, tenant_id: Uuid) -> Result<Thread, DatabaseError> {
+        let thread = Thread::new(tenant_id);
+        let connection = self.connection.lock().await;
+
+        connection
+            .execute(
+                "INSERT INTO threads (id, title, summary, metadata, created_at, tenant_id) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                params![
+                    SqlUuid(thread.id()),
+                    thread.title,
+                    thread.summary,
+                    SqlJson(&thread.metadata),
+                    thread.created_at,
+                    SqlUuid(thread.tenant_id)
+                ],
+            )
+            .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+
+        Ok(thread)
+    }
+
+    async fn delete_thread(&self, tenant_id: Uuid, thread_id: Uuid) -> Result<(), DatabaseError> {
+        let mut connection = self.connection.lock().await;
+        let tx = connection
+            .transaction()
+            .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+
+        if Self::get_thread_internal(&tx, tenant_id, thread_id)?.is_none() {
+            return Err(DatabaseError::NotFound);
+        }
+
+        let message_ids: Vec<Uuid> = tx
+            .prepare_cached(
+                "SELECT id FROM messages WHERE thread_id = ?1 AND embedding IS NOT NULL",
+            )
+            .and_then(|mut statement| {
+                statement
+                    .query_map([SqlUuid(thread_id)], |row| row.get::<_, SqlUuid>(0))?
+                    .map(|id| id.map(|id| id.0))
+                    .collect()
+            })
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
+        for message_id in message_ids {
+            self.unindex_message(&tx, message_id)?;
+        }
+        self.hnsw
+            .remove(&mut Self::index(&tx, SqliteIndexKind::Thread), thread_id)?;
+
+        Self::release_blobs(&tx, tenant_id, "thread_id", thread_id)?;
+        // Messages and jobs go with the thread through their foreign keys.
+        tx.execute("DELETE FROM threads WHERE id = ?1", [SqlUuid(thread_id)])
+            .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+
+        tx.commit()
+            .map_err(|e| DatabaseError::OperationFailed(e.to_string()))
+    }
+
+    async fn create_message(
+        &self,
+        tenant_id: Uuid,
+        thread_id: Uuid,
+        input: CreateMessage,
+    ) -> Result<Message, DatabaseError> {
+        let mut connection = self.connection.lock().await;
+        let tx = connection
+            .transaction()
+            .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+
+        let thread =
+            Self::get_thread_internal(&tx, tenant_id, thread_id)?.ok_or(DatabaseError::NotFound)?;
+        if let Some(parent_id) = input.parent_id {
+            if Self::get_message_internal(&tx, tenant_id, thread_id, parent_id)?.is_none() {
+                return Err(DatabaseError::InvalidInput(format!(
+                    "parent message {} is not in thread {}",
+                    parent_id, thread_id
+                )));
+            }
+        }
+        Self::retain_blobs(&tx, tenant_id, &input.content)?;
+        let message = input.into_message(&thread);
+        tx.execute(
+            "INSERT INTO messages (id, thread_id, role, content, created_at, metadata, parent_id) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                SqlUuid(message.id()),
+                SqlUuid(thread_id),
+                message.role.as_str(),
+                SqlJson(&message.content),
+                message.created_at,
+                SqlJson(&message.metadata),
+                message.parent_id.map(SqlUuid)
+            ],
+        )
+        .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+        tx.execute(
+            "UPDATE threads SET last_message_at = ?2 WHERE id = ?1",
+            params![SqlUuid(thread_id), message.created_at],
+        )
+        .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+
+        tx.commit()
+            .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+        Ok(message)
+    }
+
+    async fn import_threads(
+        &self,
+        threads: Vec<(Thread, Vec<Message>)>,
+    ) -> Result<(), DatabaseError> {
+        let mut connection = self.connection.lock().await;
+        let tx = connection
+            .transaction()
+            .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+
+        for (thread, messages) in &threads {
+            tx.execute(
+                "INSERT INTO threads (id, title, summary, metadata, created_at, last_message_at, tenant_id) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                params![
+                    SqlUuid(thread.id()),
+                    thread.title,
+                    thread.summary,
+                    SqlJson(&thread.metadata),
+                    thread.created_at,
+                    thread.last_message_at,
+                    SqlUuid(thread.tenant_id)
+                ],
+            )
+            .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+            for message in messages {
+                Self::retain_blobs(&tx, thread.tenant_id, &message.content)?;
+                tx.execute(
+                    "INSERT INTO messages (id, thread_id, role, content, created_at, metadata, parent_id) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                    params![
+                        SqlUuid(message.id()),
+                        SqlUuid(message.thread_id),
+                        message.role.as_str(),
+                        SqlJson(&message.content),
+                        message.created_at,
+                        SqlJson(&message.metadata),
+                        message.parent_id.map(SqlUuid)
+                    ],
+                )
+                .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+            }
+        }
+
+        tx.commit()
+            .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+        Ok(())
+    }
+
+    async fn update_message(
+        &self,
+        tenant_id: Uuid,
+        thread_id: Uuid,
+        message_id: Uuid,
+        content: UpdateMessage,
+    ) -> Result<Message, DatabaseError> {
+        let mut connection = self.connection.lock().await;
+        let tx = connection
+            .transaction()
+            .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+
+        let mut message = Self::get_message_internal(&tx, tenant_id, thread_id, message_id)?
+            .ok_or(DatabaseError::NotFound)?;
+        Self::retain_blobs(&tx, tenant_id, &content.content)?;
+        let revision: u32 = tx
+            .query_row(
+                "SELECT COALESCE(MAX(revision), 0) + 1 FROM message_revisions WHERE message_id = ?1",
+                [SqlUuid(message_id)],
+                |row| row.get(0),
+            )
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
+        let previous = message.update_content(content, revision);
+        tx.execute(
+            &format!(
+                "INSERT INTO message_revisions ({REVISION_COLUMNS}) VALUES (?1, ?2, ?3, ?4, ?5)"
+            ),
+            params![
+                SqlUuid(message_id),
+                previous.revision,
+                SqlJson(&previous.content),
+                previous.created_at,
+                previous.replaced_at
+            ],
+        )
+        .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+        tx.execute(
+            "UPDATE messages SET content = ?2, updated_at = ?3 WHERE id = ?1",
+            params![
+                SqlUuid(message_id),
+                SqlJson(&message.content),
+                message.updated_at
+            ],
+        )
+        .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+
+        tx.commit()
+            .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+        Ok(message)
+    }
+
+    async fn list_message_revisions(
+        &self,
+        tenant_id: Uuid,
+        thread_id: Uuid,
+        message_id: Uuid,
+    ) -> Result<Vec<MessageRevision>, DatabaseError> {
+        let connection = self.connection.lock().await;
+        if Self::get_message_internal(&connection, tenant_id, thread_id, message_id)?.is_none() {
+            return Err(DatabaseError::NotFound);
+        }
+
+        Self::query_revisions(
+            &connection,
+            &format!(
+                "SELECT {REVISION_COLUMNS} FROM message_revisions WHERE message_id = ?1 ORDER BY revision"
+            ),
+            [SqlUuid(message_id)],
+        )
+    }
+
+    async fn get_message_revision(
+        &self,
+        tenant_id: Uuid,
+        thread_id: Uuid,
+        message_id: Uuid,
+        revision: u32,
+    ) -> Result<MessageRevision, DatabaseError> {
+        let connection = self.connection.lock().await;
+        if Self::get_message_internal(&connection, tenant_id, thread_id, message_id)?.is_none() {
+            return Err(DatabaseError::NotFound);
+        }
+
+        Self::query_revisions(
+            &connection,
+            &format!(
+                "SELECT {REVISION_COLUMNS} FROM message_revisions WHERE message_id = ?1 AND revision = ?2"
+            ),
+            params![SqlUuid(message_id), revision],
+        )?
+        .pop()
+        .ok_or(DatabaseError::NotFound)
+    }
+
+    async fn update_thread(
+        &self,
+        tenant_id: Uuid,
+        thread_id: Uuid,
+        update: UpdateThread,
+    ) -> Result<Thread, DatabaseError> {
+        let mut connection = self.connection.lock().await;
+        let tx = connection
+            .transaction()
+            .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+
+        let mut thread =
+            Self::get_thread_internal(&tx, tenant_id, thread_id)?.ok_or(DatabaseError::NotFound)?;
+        thread.update(update);
+        tx.execute(
+            "UPDATE threads SET title = ?2, metadata = ?3, updated_at = ?4 WHERE id = ?1",
+            params![
+                SqlUuid(thread_id),
+                thread.title,
+                SqlJson(&thread.metadata),
+                thread.updated_at
+            ],
+        )
+        .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+
+        tx.commit()
+            .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+        Ok(thread)
+    }
+
+    async fn list_threads(
+        &self,
+        tenant_id: Uuid,
+        query: &ListThreads,
+    ) -> Result<ListThreadsResponse, DatabaseError> {
+        let connection = self.connection.lock().await;
+        let created_range = "tenant_id = ?1 AND (?2 IS NULL OR created_at > ?2) AND (?3 IS NULL OR created_at < ?3)";
+
+        // Metadata is matched in Rust, so filtered listings are paged there too.
+        if !query.metadata.is_empty() {
+            let threads = connection
+                .prepare_cached(&format!(
+                    "SELECT {THREAD_COLUMNS} FROM threads WHERE {created_range}"
+                ))
+                .and_then(|mut statement| {
+                    statement
+                        .query_map(
+                            params![
+                                SqlUuid(tenant_id),
+                                query.created_after,
+                                query.created_before
+                            ],
+                            Self::thread_from_row,
+                        )?
+                        .collect::<rusqlite::Result<Vec<Thread>>>()
+                })
+                .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
+            return Ok(query.paginate(threads));
+        }
+
+        let total: usize = connection
+            .query_row(
+                &format!("SELECT COUNT(*) FROM threads WHERE {created_range}"),
+                params![
+                    SqlUuid(tenant_id),
+                    query.created_after,
+                    query.created_before
+                ],
+                |row| row.get(0),
+            )
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
+        let limit = query.limit.unwrap_or(total);
+
+        let key = match query.sort {
+            ThreadSort::CreatedAt => "created_at",
+            ThreadSort::LastActivity => "COALESCE(last_message_at, created_at)",
+        };
+        let (past, direction) = match query.order {
+            SortOrder::Asc => (">", "ASC"),
+            SortOrder::Desc => ("<", "DESC"),
+        };
+        // One extra row tells whether another page follows.
+        let mut threads = connection
+            .prepare_cached(&format!(
+                "SELECT {THREAD_COLUMNS} FROM threads WHERE {created_range} AND (?4 IS NULL OR ({key}, id) {past} (?4, ?5)) ORDER BY {key} {direction}, id {direction} LIMIT ?6"
+            ))
+            .and_then(|mut statement| {
+                statement
+                    .query_map(
+                        params![
+                            SqlUuid(tenant_id),
+                            query.created_after,
+                            query.created_before,
+                            query.cursor.map(|cursor| cursor.timestamp),
+                            query.cursor.map(|cursor| SqlUuid(cursor.thread_id)),
+                            limit.saturating_add(1).min(i64::MAX as usize)
+                        ],
+                        Self::thread_from_row,
+                    )?
+                    .collect::<rusqlite::Result<Vec<Thread>>>()
+            })
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
+
+        let next_cursor = if threads.len() > limit {
+            threads.truncate(limit);
+            threads.last().map(|thread| thread.cursor(query.sort))
+        } else {
+            None
+        };
+
+        Ok(ListThreadsResponse {
+            threads,
+            total,
+            limit,
+            next_cursor,
+        })
+    }
+
+    async fn get_thread(&self, tenant_id: Uuid, thread_id: Uuid) -> Result<Thread, DatabaseError> {
+        let connection = self.connection.lock().await;
+        Self::get_thread_internal(&connection, tenant_id, thread_id)?.ok_or(DatabaseError::NotFound)
+    }
+
+    async fn get_thread_messages(
+        &self,
+        tenant_id: Uuid,
+        thread_id: Uuid,
+        query: &ListMessages,
+    ) -> Result<ThreadMessagesResponse, DatabaseError> {
+        let connection = self.connection.lock().await;
+
+        if Self::get_thread_internal(&connection, tenant_id, thread_id)?.is_none() {
+            return Err(DatabaseError::NotFound);
+        }
+
+        let total: usize = connection
+            .query_row(
+                "SELECT COUNT(*) FROM messages WHERE thread_id = ?1",
+                [SqlUuid(thread_id)],
+                |row| row.get(0),
+            )
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
+        let offset = query.offset.unwrap_or(0);
+
+        let direction = match query.order {
+            SortOrder::Asc => "ASC",
+            SortOrder::Desc => "DESC",
+        };
+        // One extra row tells whether another page follows.
+        let mut messages = connection
+            .prepare_cached(&format!(
+                "SELECT {MESSAGE_COLUMNS} FROM messages WHERE thread_id = ?1 AND (?2 IS NULL OR (created_at, id) > (?2, ?3)) AND (?4 IS NULL OR (created_at, id) < (?4, ?5)) ORDER BY created_at {direction}, id {direction} LIMIT ?6 OFFSET ?7"
+            ))
+            .and_then(|mut statement| {
+                statement
+                    .query_map(
+                        params![
+                            SqlUuid(thread_id),
+                            query.after.map(|after| after.created_at),
+                            query.after.map(|after| SqlUuid(after.message_id)),
+                            query.before.map(|before| before.created_at),
+                            query.before.map(|before| SqlUuid(before.message_id)),
+                            query
+                                .limit
+                                .map_or(-1, |limit| limit.saturating_add(1).min(i64::MAX as usize) as i64),
+                            offset
+                        ],
+                        Self::message_from_row,
+                    )?
+                    .collect::<rusqlite::Result<Vec<Message>>>()
+            })
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
+
+        let limit = query.limit.unwrap_or(messages.len());
+        let next_cursor = query.next_cursor(&messages, limit);
+        messages.truncate(limit);
+
+        Ok(ThreadMessagesResponse {
+            messages,
+            total,
+            offset,
+            limit,
+            next_cursor,
+        })
+    }
+
+    async fn debug_state(&self) -> Result<serde_json::Value, DatabaseError> {
+        let connection = self.connection.lock().await;
+
+        let threads: Vec<(Thread, Option<Vec<f32>>)> = connection
+            .prepare(&format!(
+                "SELECT {THREAD_COLUMNS}, embedding FROM threads ORDER BY created_at, rowid"
+            ))
+            .and_then(|mut statement| {
+                statement
+                    .query_map([], |row| {
+                        Ok((
+                            Self::thread_from_row(row)?,
+                            row.get::<_, Option<SqlEmbedding>>(9)?
+                                .map(|embedding| embedding.0),
+                        ))
+                    })?
+                    .collect()
+            })
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
+        let messages: Vec<(Message, Option<Vec<f32>>)> = connection
+            .prepare(&format!(
+                "SELECT {MESSAGE_COLUMNS}, embedding FROM messages ORDER BY thread_id, created_at, rowid"
+            ))
+            .and_then(|mut statement| {
+                statement
+                    .query_map([], |row| {
+                        Ok((
+                            Self::message_from_row(row)?,
+                            row.get::<_, Option<SqlEmbedding>>(8)?
+                                .map(|embedding| embedding.0),
+                        ))
+                    })?
+                    .collect()
+            })
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
+        let message_revisions = Self::query_revisions(
+            &connection,
+            &format!(
+                "SELECT {REVISION_COLUMNS} FROM message_revisions ORDER BY message_id, revision"
+            ),
+            [],
+        )?;
+        let blobs: Vec<(Uuid, Blob)> = connection
+            .prepare(&format!(
+                "SELECT {BLOB_COLUMNS}, tenant_id FROM blobs ORDER BY tenant_id, hash"
+            ))
+            .and_then(|mut statement| {
+                statement
+                    .query_map([], |row| {
+                        Ok((row.get::<_, SqlUuid>(6)?.0, Self::blob_from_row(row)?))
+                    })?
+                    .collect()
+            })
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
+        let jobs = Self::query_jobs(&connection, "SELECT job FROM jobs ORDER BY created_at", [])?;
+        let webhooks = Self::query_webhooks(
+            &connection,
+            "SELECT webhook FROM webhooks ORDER BY created_at",
+            [],
+        )?;
+        let webhook_deliveries = Self::query_webhook_deliveries(
+            &connection,
+            "SELECT delivery FROM webhook_deliveries ORDER BY webhook_id, created_at",
+            [],
+        )?;
+        let tenants = Self::query_tenants(
+            &connection,
+            "SELECT tenant FROM tenants ORDER BY created_at",
+            [],
+        )?;
+        let api_keys = Self::query_api_keys(
+            &connection,
+            "SELECT api_key FROM api_keys ORDER BY tenant_id, created_at",
+            [],
+        )?;
+        let index_nodes: Vec<(String, Uuid, HnswNode)> = connection
+            .prepare("SELECT index_name, id, node FROM hnsw_nodes ORDER BY index_name, id")
+            .and_then(|mut statement| {
+                statement
+                    .query_map([], |row| {
+                        Ok((
+                            row.get(0)?,
+                            row.get::<_, SqlUuid>(1)?.0,
+                            row.get::<_, SqlJson<HnswNode>>(2)?.0,
+                        ))
+                    })?
+                    .collect()
+            })
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
+        let index_entry_points: Vec<(String, HnswEntryPoint)> = connection
+            .prepare("SELECT index_name, entry_point FROM hnsw_entry_points ORDER BY index_name")
+            .and_then(|mut statement| {
+                statement
+                    .query_map([], |row| {
+                        Ok((row.get(0)?, row.get::<_, SqlJson<HnswEntryPoint>>(1)?.0))
+                    })?
+                    .collect()
+            })
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
+
+        Ok(serde_json::json!({
+            "threads": threads,
+            "messages": messages,
+            "message_revisions": message_revisions,
+            "jobs": jobs,
+            "blobs": blobs,
+            "webhooks": webhooks,
+            "webhook_deliveries": webhook_deliveries,
+            "tenants": tenants,
+            "api_keys": api_keys,
+            "index_nodes": index_nodes,
+            "index_entry_points": index_entry_points
+        }))
+    }
+
+    async fn delete_message(
+        &self,
+        tenant_id: Uuid,
+        thread_id: Uuid,
+        message_id: Uuid,
+    ) -> Result<(), DatabaseError> {
+        let mut connection = self.connection.lock().await;
+        let tx = connection
+            .transaction()
+            .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+
+        if Self::get_message_internal(&tx, tenant_id, thread_id, message_id)?.is_none() {
+            return Err(DatabaseError::NotFound);
+        }
+
+        self.unindex_message(&tx, message_id)?;
+        Self::release_blobs(&tx, tenant_id, "id", message_id)?;
+        tx.execute("DELETE FROM messages WHERE id = ?1", [SqlUuid(message_id)])
+            .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+        tx.execute(
+            "UPDATE threads SET last_message_at = (SELECT MAX(created_at) FROM messages WHERE thread_id = ?1) WHERE id = ?1",
+            [SqlUuid(thread_id)],
+        )
+        .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+
+        tx.commit()
+            .map_err(|e| DatabaseError::OperationFailed(e.to_string()))
+    }
+
+    async fn get_message(
+        &self,
+        tenant_id: Uuid,
+        thread_id: Uuid,
+        message_id: Uuid,
+    ) -> Result<Message, DatabaseError> {
+        let connection = self.connection.lock().await;
+        Self::get_message_internal(&connection, tenant_id, thread_id, message_id)?
+            .ok_or(DatabaseError::NotFound)
+    }
+
+    async fn put_blob(
+        &self,
+        tenant_id: Uuid,
+        blob: Blob,
+        data: Vec<u8>,
+    ) -> Result<Blob, DatabaseError> {
+        let connection = self.connection.lock().await;
+        connection
+            .execute(
+                &format!(
+                    "INSERT INTO blobs ({BLOB_COLUMNS}, data, tenant_id) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+                     ON CONFLICT (tenant_id, hash) DO UPDATE SET uploaded_at = excluded.uploaded_at"
+                ),
+                params![
+                    blob.hash,
+                    blob.mime_type,
+                    blob.size,
+                    blob.created_at,
+                    blob.uploaded_at,
+                    blob.ref_count,
+                    data,
+                    SqlUuid(tenant_id)
+                ],
+            )
+            .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+
+        connection
+            .query_row(
+                &format!("SELECT {BLOB_COLUMNS} FROM blobs WHERE tenant_id = ?1 AND hash = ?2"),
+                params![SqlUuid(tenant_id), blob.hash],
+                Self::blob_from_row,
+            )
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))
+    }
+
+    async fn get_blob(
+        &self,
+        tenant_id: Uuid,
+        hash: &str,
+    ) -> Result<(Blob, Vec<u8>), DatabaseError> {
+        let connection = self.connection.lock().await;
+        connection
+            .query_row(
+                &format!(
+                    "SELECT {BLOB_COLUMNS}, data FROM blobs WHERE tenant_id = ?1 AND hash = ?2"
+                ),
+                params![SqlUuid(tenant_id), hash],
+                |row| Ok((Self::blob_from_row(row)?, row.get(6)?)),
+            )
+            .optional()
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?
+            .ok_or(DatabaseError::NotFound)
+    }
+
+    async fn delete_unreferenced_blobs(&self, uploaded_before: u64) -> Result<u64, DatabaseError> {
+        let connection = self.connection.lock().await;
+        connection
+            .execute(
+                "DELETE FROM blobs WHERE ref_count <= 0 AND uploaded_at < ?1",
+                [uploaded_before],
+            )
+            .map(|deleted| deleted as u64)
+            .map_err(|e| DatabaseError::OperationFailed(e.to_string()))
+    }
+
+    async fn save_job(&self, job: &Job) -> Result<(), DatabaseError> {
+        let connection = self.connection.lock().await;
+
+        if Self::get_thread_internal(&connection, job.tenant_id, job.thread_id)?.is_none() {
+            return Err(DatabaseError::NotFound);
+        }
+
+        connection
+            .execute(
+                "INSERT OR REPLACE INTO jobs (id, thread_id, status, created_at, job) VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![
+                    SqlUuid(job.id()),
+                    SqlUuid(job.thread_id),
+                    Self::job_status(job.status),
+                    job.created_at,
+                    SqlJson(job)
+                ],
+            )
+            .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+        Ok(())
+    }
+
+    async fn delete_job(
+        &self,
+        tenant_id: Uuid,
+        thread_id: Uuid,
+        job_id: Uuid,
+    ) -> Result<(), DatabaseError> {
+        let connection = self.connection.lock().await;
+
+        let deleted = connection
+            .execute(
+                "DELETE FROM jobs WHERE thread_id = ?1 AND id = ?2 AND thread_id IN (SELECT id FROM threads WHERE tenant_id = ?3)",
+                params![SqlUuid(thread_id), SqlUuid(job_id), SqlUuid(tenant_id)],
+            )
+            .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+        if deleted == 0 {
+            return Err(DatabaseError::NotFound);
+        }
+        Ok(())
+    }
+
+    async fn get_pending_jobs(&self) -> Result<Vec<Job>, DatabaseError> {
+        let connection = self.connection.lock().await;
+        Self::query_jobs(
+            &connection,
+            "SELECT job FROM jobs WHERE status = ?1 ORDER BY created_at",
+            [Self::job_status(JobStatus::Pending)],
+        )
+    }
+
+    async fn get_thread_jobs(
+        &self,
+        tenant_id: Uuid,
+        thread_id: Uuid,
+    ) -> Result<Vec<Job>, DatabaseError> {
+        let connection = self.connection.lock().await;
+
+        if Self::get_thread_internal(&connection, tenant_id, thread_id)?.is_none() {
+            return Err(DatabaseError::NotFound);
+        }
+
+        Self::query_jobs(
+            &connection,
+            "SELECT job FROM jobs WHERE thread_id = ?1 ORDER BY created_at",
+            [SqlUuid(thread_id)],
+        )
+    }
+
+    async fn save_webhook(&self, webhook: &Webhook) -> Result<(), DatabaseError> {
+        let connection = self.connection.lock().await;
+
+        if Self::query_tenants(
+            &connection,
+            "SELECT tenant FROM tenants WHERE id = ?1",
+            [SqlUuid(webhook.tenant_id)],
+        )?
+        .is_empty()
+        {
+            return Err(DatabaseError::NotFound);
+        }
+
+        // An upsert rather than INSERT OR REPLACE, which would cascade to the deliveries.
+        connection
+            .execute(
+                "INSERT INTO webhooks (id, tenant_id, created_at, webhook) VALUES (?1, ?2, ?3, ?4)
+                 ON CONFLICT (id) DO UPDATE SET webhook = excluded.webhook",
+                params![
+                    SqlUuid(webhook.id()),
+                    SqlUuid(webhook.tenant_id),
+                    webhook.created_at,
+                    SqlJson(webhook)
+                ],
+            )
+            .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+        Ok(())
+    }
+
+    async fn get_webhook(
+        &self,
+        tenant_id: Uuid,
+        webhook_id: Uuid,
+    ) -> Result<Webhook, DatabaseError> {
+        let connection = self.connection.lock().await;
+        Self::query_webhooks(
+            &connection,
+            "SELECT webhook FROM webhooks WHERE tenant_id = ?1 AND id = ?2",
+            params![SqlUuid(tenant_id), SqlUuid(webhook_id)],
+        )?
+        .pop()
+        .ok_or(DatabaseError::NotFound)
+    }
+
+    async fn list_webhooks(&self, tenant_id: Uuid) -> Result<Vec<Webhook>, DatabaseError> {
+        let connection = self.connection.lock().await;
+        Self::query_webhooks(
+            &connection,
+            "SELECT webhook FROM webhooks WHERE tenant_id = ?1 ORDER BY created_at",
+            [SqlUuid(tenant_id)],
+        )
+    }
+
+    async fn delete_webhook(&self, tenant_id: Uuid, webhook_id: Uuid) -> Result<(), DatabaseError> {
+        let connection = self.connection.lock().await;
+
+        // Deliveries go with the webhook through their foreign key.
+        let deleted = connection
+            .execute(
+                "DELETE FROM webhooks WHERE tenant_id = ?1 AND id = ?2",
+                params![SqlUuid(tenant_id), SqlUuid(webhook_id)],
+            )
+            .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+        if deleted == 0 {
+            return Err(DatabaseError::NotFound);
+        }
+        Ok(())
+    }
+
+    async fn save_webhook_delivery(&self, delivery: &WebhookDelivery) -> Result<(), DatabaseError> {
+        let connection = self.connection.lock().await;
+
+        if Self::query_webhooks(
+            &connection,
+            "SELECT webhook FROM webhooks WHERE id = ?1",
+            [SqlUuid(delivery.webhook_id)],
+        )?
+        .is_empty()
+        {
+            return Err(DatabaseError::NotFound);
+        }
+
+        connection
+            .execute(
+                "INSERT OR REPLACE INTO webhook_deliveries (id, webhook_id, status, created_at, delivery) VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![
+                    SqlUuid(delivery.id()),
+                    SqlUuid(delivery.webhook_id),
+                    Self::delivery_status(delivery.status),
+                    delivery.created_at,
+                    SqlJson(delivery)
+                ],
+            )
+            .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+        Ok(())
+    }
+
+    async fn list_webhook_deliveries(
+        &self,
+        tenant_id: Uuid,
+        webhook_id: Uuid,
+    ) -> Result<Vec<WebhookDelivery>, DatabaseError> {
+        let connection = self.connection.lock().await;
+
+        if Self::query_webhooks(
+            &connection,
+            "SELECT webhook FROM webhooks WHERE tenant_id = ?1 AND id = ?2",
+            params![SqlUuid(tenant_id), SqlUuid(webhook_id)],
+        )?
+        .is_empty()
+        {
+            return Err(DatabaseError::NotFound);
+        }
+
+        Self::query_webhook_deliveries(
+            &connection,
+            "SELECT delivery FROM webhook_deliveries WHERE webhook_id = ?1 ORDER BY created_at",
+            [SqlUuid(webhook_id)],
+        )
+    }
+
+    async fn get_pending_webhook_deliveries(&self) -> Result<Vec<WebhookDelivery>, DatabaseError> {
+        let connection = self.connection.lock().await;
+        Self::query_webhook_deliveries(
+            &connection,
+            "SELECT delivery FROM webhook_deliveries WHERE status = ?1 ORDER BY created_at",
+            [Self::delivery_status(DeliveryStatus::Pending)],
+        )
+    }
+
+    async fn save_tenant(&self, tenant: &Tenant) -> Result<(), DatabaseError> {
+        let connection = self.connection.lock().await;
+        // An upsert rather than INSERT OR REPLACE, which would cascade to the keys.
+        connection
+            .execute(
+                "INSERT INTO tenants (id, created_at, tenant) VALUES (?1, ?2, ?3)
+                 ON CONFLICT (id) DO UPDATE SET tenant = excluded.tenant",
+                params![SqlUuid(tenant.id()), tenant.created_at, SqlJson(tenant)],
+            )
+            .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+        Ok(())
+    }
+
+    async fn get_tenant(&self, tenant_id: Uuid) -> Result<Tenant, DatabaseError> {
+        let connection = self.connection.lock().await;
+        Self::query_tenants(
+            &connection,
+            "SELECT tenant FROM tenants WHERE id = ?1",
+            [SqlUuid(tenant_id)],
+        )?
+        .pop()
+        .ok_or(DatabaseError::NotFound)
+    }
+
+    async fn list_tenants(&self) -> Result<Vec<Tenant>, DatabaseError> {
+        let connection = self.connection.lock().await;
+        Self::query_tenants(
+            &connection,
+            "SELECT tenant FROM tenants ORDER BY created_at",
+            [],
+        )
+    }
+
+    async fn delete_tenant(&self, tenant_id: Uuid) -> Result<(), DatabaseError> {
+        let mut connection = self.connection.lock().await;
+        let tx = connection
+            .transaction()
+            .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+
+        let threads: usize = tx
+            .query_row(
+                "SELECT COUNT(*) FROM threads WHERE tenant_id = ?1",
+                [SqlUuid(tenant_id)],
+                |row| row.get(0),
+            )
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
+        if threads > 0 {
+            return Err(DatabaseError::Conflict(format!(
+                "tenant {} still owns threads",
+                tenant_id
+            )));
+        }
+
+        // Deliveries go with the webhooks and keys with the tenant through their
+        // foreign keys.
+        tx.execute(
+            "DELETE FROM webhooks WHERE tenant_id = ?1",
+            [SqlUuid(tenant_id)],
+        )
+        .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+        let deleted = tx
+            .execute("DELETE FROM tenants WHERE id = ?1", [SqlUuid(tenant_id)])
+            .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+        if deleted == 0 {
+            return Err(DatabaseError::NotFound);
+        }
+
+        tx.commit()
+            .map_err(|e| DatabaseError::OperationFailed(e.to_string()))
+    }
+
+    async fn save_api_key(&self, api_key: &ApiKey) -> Result<(), DatabaseError> {
+        let connection = self.connection.lock().await;
+
+        if Self::query_tenants(
+            &connection,
+            "SELECT tenant FROM tenants WHERE id = ?1",
+            [SqlUuid(api_key.tenant_id)],
+        )?
+        .is_empty()
+        {
+            return Err(DatabaseError::NotFound);
+        }
+
+        connection
+            .execute(
+                "INSERT OR REPLACE INTO api_keys (id, tenant_id, key_hash, created_at, api_key) VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![
+                    SqlUuid(api_key.id()),
+                    SqlUuid(api_key.tenant_id),
+                    api_key.key_hash,
+                    api_key.created_at,
+                    SqlJson(api_key)
+                ],
+            )
+            .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+        Ok(())
+    }
+
+    async fn get_api_key_by_hash(&self, key_hash: &str) -> Result<ApiKey, DatabaseError> {
+        let connection = self.connection.lock().await;
+        Self::query_api_keys(
+            &connection,
+            "SELECT api_key FROM api_keys WHERE key_hash = ?1",
+            [key_hash],
+        )?
+        .pop()
+        .ok_or(DatabaseError::NotFound)
+    }
+
+    async fn list_api_keys(&self, tenant_id: Uuid) -> Result<Vec<ApiKey>, DatabaseError> {
+        let connection = self.connection.lock().await;
+
+        if Self::query_tenants(
+            &connection,
+            "SELECT tenant FROM tenants WHERE id = ?1",
+            [SqlUuid(tenant_id)],
+        )?
+        .is_empty()
+        {
+            return Err(DatabaseError::NotFound);
+        }
+
+        Self::query_api_keys(
+            &connection,
+            "SELECT api_key FROM api_keys WHERE tenant_id = ?1 ORDER BY created_at",
+            [SqlUuid(tenant_id)],
+        )
+    }
+
+    async fn delete_api_key(&self, tenant_id: Uuid, key_id: Uuid) -> Result<(), DatabaseError> {
+        let connection = self.connection.lock().await;
+
+        let deleted = connection
+            .execute(
+                "DELETE FROM api_keys WHERE tenant_id = ?1 AND id = ?2",
+                params![SqlUuid(tenant_id), SqlUuid(key_id)],
+            )
+            .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+        if deleted == 0 {
+            return Err(DatabaseError::NotFound);
+        }
+        Ok(())
+    }
+}
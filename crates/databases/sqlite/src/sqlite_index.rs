@@ -0,0 +1,137 @@
+use rusqlite::{params, Connection, OptionalExtension};
+use synx_database::DatabaseError;
+use synx_vector_index::{HnswEntryPoint, HnswNode, HnswStorage, HnswStorageMut};
+use uuid::Uuid;
+
+use crate::sqlite_types::{SqlEmbedding, SqlJson, SqlUuid};
+
+#[derive(Debug, Clone, Copy)]
+pub enum SqliteIndexKind {
+    Thread,
+    Message,
+}
+
+impl SqliteIndexKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            SqliteIndexKind::Thread => "thread",
+            SqliteIndexKind::Message => "message",
+        }
+    }
+
+    fn vector_query(&self) -> &'static str {
+        match self {
+            SqliteIndexKind::Thread => "SELECT embedding FROM threads WHERE id = ?1",
+            SqliteIndexKind::Message => "SELECT embedding FROM messages WHERE id = ?1",
+        }
+    }
+}
+
+/// HNSW graph of one index, read and written through the given connection or
+/// transaction. Vectors are read from the `embedding` column of the indexed rows.
+pub struct SqliteIndex<'a> {
+    pub connection: &'a Connection,
+    pub kind: SqliteIndexKind,
+}
+
+impl HnswStorage for SqliteIndex<'_> {
+    type Error = DatabaseError;
+
+    fn vector(&self, id: Uuid) -> Result<Option<Vec<f32>>, DatabaseError> {
+        Ok(self
+            .connection
+            .prepare_cached(self.kind.vector_query())
+            .and_then(|mut statement| {
+                statement
+                    .query_row([SqlUuid(id)], |row| row.get::<_, Option<SqlEmbedding>>(0))
+                    .optional()
+            })
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))?
+            .flatten()
+            .map(|embedding| embedding.0))
+    }
+
+    fn node(&self, id: Uuid) -> Result<Option<HnswNode>, DatabaseError> {
+        self.connection
+            .prepare_cached("SELECT node FROM hnsw_nodes WHERE index_name = ?1 AND id = ?2")
+            .and_then(|mut statement| {
+                statement
+                    .query_row(params![self.kind.name(), SqlUuid(id)], |row| {
+                        row.get::<_, SqlJson<HnswNode>>(0)
+                    })
+                    .optional()
+            })
+            .map(|node| node.map(|node| node.0))
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))
+    }
+
+    fn entry_point(&self) -> Result<Option<HnswEntryPoint>, DatabaseError> {
+        self.connection
+            .prepare_cached("SELECT entry_point FROM hnsw_entry_points WHERE index_name = ?1")
+            .and_then(|mut statement| {
+                statement
+                    .query_row([self.kind.name()], |row| {
+                        row.get::<_, SqlJson<HnswEntryPoint>>(0)
+                    })
+                    .optional()
+            })
+            .map(|entry_point| entry_point.map(|entry_point| entry_point.0))
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))
+    }
+}
+
+impl HnswStorageMut for SqliteIndex<'_> {
+    fn put_node(&mut self, id: Uuid, node: &HnswNode) -> Result<(), DatabaseError> {
+        self.connection
+            .execute(
+                "INSERT OR REPLACE INTO hnsw_nodes (index_name, id, node) VALUES (?1, ?2, ?3)",
+                params![self.kind.name(), SqlUuid(id), SqlJson(node)],
+            )
+            .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+        Ok(())
+    }
+
+    fn delete_node(&mut self, id: Uuid) -> Result<(), DatabaseError> {
+        self.connection
+            .execute(
+                "DELETE FROM hnsw_nodes WHERE index_name = ?1 AND id = ?2",
+                params![self.kind.name(), SqlUuid(id)],
+            )
+            .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+        Ok(())
+    }
+
+    fn set_entry_point(
+        &mut self,
+        entry_point: Option<HnswEntryPoint>,
+    ) -> Result<(), DatabaseError> {
+        match entry_point {
+            Some(entry_point) => self.connection.execute(
+                "INSERT OR REPLACE INTO hnsw_entry_points (index_name, entry_point) VALUES (?1, ?2)",
+                params![self.kind.name(), SqlJson(entry_point)],
+            ),
+            None => self.connection.execute(
+                "DELETE FROM hnsw_entry_points WHERE index_name = ?1",
+                [self.kind.name()],
+            ),
+        }
+        .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+        Ok(())
+    }
+
+    fn any_node(&self) -> Result<Option<(Uuid, HnswNode)>, DatabaseError> {
+        self.connection
+            .query_row(
+                "SELECT id, node FROM hnsw_nodes WHERE index_name = ?1 LIMIT 1",
+                [self.kind.name()],
+                |row| {
+                    Ok((
+                        row.get::<_, SqlUuid>(0)?.0,
+                        row.get::<_, SqlJson<HnswNode>>(1)?.0,
+                    ))
+                },
+            )
+            .optional()
+            .map_err(|e| DatabaseError::QueryError(e.to_string()))
+    }
+}
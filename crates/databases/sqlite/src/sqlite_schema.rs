@@ -0,0 +1,180 @@
+use rusqlite::Connection;
+use synx_database::DatabaseError;
+
+/// Schema changes, applied in order. The number of applied migrations is kept
+/// in `PRAGMA user_version`, so entries must never be edited, only appended.
+const MIGRATIONS: &[&str] = &[
+    r#"
+    CREATE TABLE threads (
+        id TEXT PRIMARY KEY NOT NULL,
+        title TEXT,
+        summary TEXT,
+        summary_cursor TEXT,
+        embedding BLOB,
+        created_at INTEGER NOT NULL
+    );
+    CREATE INDEX threads_created_at ON threads (created_at, id);
+
+    CREATE TABLE messages (
+        id TEXT PRIMARY KEY NOT NULL,
+        thread_id TEXT NOT NULL REFERENCES threads (id) ON DELETE CASCADE,
+        role TEXT NOT NULL,
+        content TEXT NOT NULL,
+        created_at INTEGER NOT NULL,
+        embedding BLOB
+    );
+    CREATE INDEX messages_thread_created_at ON messages (thread_id, created_at, id);
+
+    CREATE TABLE jobs (
+        id TEXT PRIMARY KEY NOT NULL,
+        thread_id TEXT NOT NULL REFERENCES threads (id) ON DELETE CASCADE,
+        status TEXT NOT NULL,
+        created_at INTEGER NOT NULL,
+        job TEXT NOT NULL
+    );
+    CREATE INDEX jobs_thread_id ON jobs (thread_id, created_at);
+    CREATE INDEX jobs_status ON jobs (status, created_at);
+
+    CREATE TABLE hnsw_nodes (
+        index_name TEXT NOT NULL,
+        id TEXT NOT NULL,
+        node TEXT NOT NULL,
+        PRIMARY KEY (index_name, id)
+    );
+
+    CREATE TABLE hnsw_entry_points (
+        index_name TEXT PRIMARY KEY NOT NULL,
+        entry_point TEXT NOT NULL
+    );
+"#,
+    r#"
+    ALTER TABLE threads ADD COLUMN metadata TEXT NOT NULL DEFAULT '{}';
+    ALTER TABLE messages ADD COLUMN metadata TEXT NOT NULL DEFAULT '{}';
+"#,
+    r#"
+    ALTER TABLE threads ADD COLUMN last_message_at INTEGER;
+    UPDATE threads SET last_message_at = (
+        SELECT MAX(created_at) FROM messages WHERE messages.thread_id = threads.id
+    );
+    CREATE INDEX threads_last_activity ON threads (COALESCE(last_message_at, created_at), id);
+"#,
+    r#"
+    ALTER TABLE threads ADD COLUMN updated_at INTEGER;
+    ALTER TABLE messages ADD COLUMN updated_at INTEGER;
+"#,
+    r#"
+    CREATE TABLE message_revisions (
+        message_id TEXT NOT NULL REFERENCES messages (id) ON DELETE CASCADE,
+        revision INTEGER NOT NULL,
+        content TEXT NOT NULL,
+        created_at INTEGER NOT NULL,
+        replaced_at INTEGER NOT NULL,
+        PRIMARY KEY (message_id, revision)
+    );
+"#,
+    r#"
+    ALTER TABLE messages ADD COLUMN parent_id TEXT;
+"#,
+    r#"
+    CREATE TABLE blobs (
+        hash TEXT PRIMARY KEY NOT NULL,
+        mime_type TEXT,
+        size INTEGER NOT NULL,
+        created_at INTEGER NOT NULL,
+        ref_count INTEGER NOT NULL,
+        data BLOB NOT NULL
+    );
+"#,
+    r#"
+    CREATE TABLE webhooks (
+        id TEXT PRIMARY KEY NOT NULL,
+        created_at INTEGER NOT NULL,
+        webhook TEXT NOT NULL
+    );
+
+    CREATE TABLE webhook_deliveries (
+        id TEXT PRIMARY KEY NOT NULL,
+        webhook_id TEXT NOT NULL REFERENCES webhooks (id) ON DELETE CASCADE,
+        status TEXT NOT NULL,
+        created_at INTEGER NOT NULL,
+        delivery TEXT NOT NULL
+    );
+    CREATE INDEX webhook_deliveries_webhook_id ON webhook_deliveries (webhook_id, created_at);
+    CREATE INDEX webhook_deliveries_status ON webhook_deliveries (status, created_at);
+"#,
+    r#"
+    CREATE TABLE tenants (
+        id TEXT PRIMARY KEY NOT NULL,
+        created_at INTEGER NOT NULL,
+        tenant TEXT NOT NULL
+    );
+    INSERT INTO tenants (id, created_at, tenant) VALUES (
+        '00000000-0000-0000-0000-000000000000',
+        0,
+        '{"id":"00000000-0000-0000-0000-000000000000","name":"default","created_at":0}'
+    );
+
+    CREATE TABLE api_keys (
+        id TEXT PRIMARY KEY NOT NULL,
+        tenant_id TEXT NOT NULL REFERENCES tenants (id) ON DELETE CASCADE,
+        key_hash TEXT NOT NULL UNIQUE,
+        created_at INTEGER NOT NULL,
+        api_key TEXT NOT NULL
+    );
+    CREATE INDEX api_keys_tenant_id ON api_keys (tenant_id, created_at);
+
+    ALTER TABLE threads ADD COLUMN tenant_id TEXT NOT NULL
+        DEFAULT '00000000-0000-0000-0000-000000000000';
+    CREATE INDEX threads_tenant_created_at ON threads (tenant_id, created_at, id);
+    CREATE INDEX threads_tenant_last_activity
+        ON threads (tenant_id, COALESCE(last_message_at, created_at), id);
+    ALTER TABLE webhooks ADD COLUMN tenant_id TEXT NOT NULL
+        DEFAULT '00000000-0000-0000-0000-000000000000';
+    CREATE INDEX webhooks_tenant_id ON webhooks (tenant_id, created_at);
+"#,
+    r#"
+    CREATE TABLE tenant_blobs (
+        tenant_id TEXT NOT NULL REFERENCES tenants (id) ON DELETE CASCADE,
+        hash TEXT NOT NULL,
+        mime_type TEXT,
+        size INTEGER NOT NULL,
+        created_at INTEGER NOT NULL,
+        ref_count INTEGER NOT NULL,
+        data BLOB NOT NULL,
+        PRIMARY KEY (tenant_id, hash)
+    );
+    INSERT INTO tenant_blobs (tenant_id, hash, mime_type, size, created_at, ref_count, data)
+        SELECT '00000000-0000-0000-0000-000000000000', hash, mime_type, size, created_at,
+            ref_count, data
+        FROM blobs;
+    DROP TABLE blobs;
+    ALTER TABLE tenant_blobs RENAME TO blobs;
+"#,
+    r#"
+    ALTER TABLE blobs ADD COLUMN uploaded_at INTEGER NOT NULL DEFAULT 0;
+    UPDATE blobs SET uploaded_at = created_at;
+    CREATE INDEX blobs_unreferenced ON blobs (uploaded_at) WHERE ref_count <= 0;
+"#,
+];
+
+pub fn migrate(connection: &mut Connection) -> Result<(), DatabaseError> {
+    connection
+        .pragma_update(None, "foreign_keys", true)
+        .map_err(|e| DatabaseError::ConnectionError(e.to_string()))?;
+
+    let version: usize = connection
+        .pragma_query_value(None, "user_version", |row| row.get(0))
+        .map_err(|e| DatabaseError::QueryError(e.to_string()))?;
+
+    let tx = connection
+        .transaction()
+        .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+    for migration in MIGRATIONS.iter().skip(version) {
+        tx.execute_batch(migration)
+            .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+    }
+    tx.pragma_update(None, "user_version", MIGRATIONS.len())
+        .map_err(|e| DatabaseError::OperationFailed(e.to_string()))?;
+    tx.commit()
+        .map_err(|e| DatabaseError::OperationFailed(e.to_string()))
+}
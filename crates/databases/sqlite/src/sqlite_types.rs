@@ -0,0 +1,94 @@
+use rusqlite::types::{FromSql, FromSqlError, FromSqlResult, ToSql, ToSqlOutput, Value, ValueRef};
+use serde::{de::DeserializeOwned, Serialize};
+use synx_domain::embedding::Embedding;
+use uuid::Uuid;
+
+/// Uuid stored as hyphenated TEXT, so rows stay readable from the sqlite shell.
+#[derive(Debug, Clone, Copy)]
+pub struct SqlUuid(pub Uuid);
+
+impl From<Uuid> for SqlUuid {
+    fn from(uuid: Uuid) -> Self {
+        SqlUuid(uuid)
+    }
+}
+
+impl ToSql for SqlUuid {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::Owned(Value::Text(self.0.to_string())))
+    }
+}
+
+impl FromSql for SqlUuid {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        Uuid::parse_str(value.as_str()?)
+            .map(SqlUuid)
+            .map_err(|e| FromSqlError::Other(Box::new(e)))
+    }
+}
+
+/// Any serde value stored as JSON TEXT.
+#[derive(Debug)]
+pub struct SqlJson<T>(pub T);
+
+impl<T: Serialize> ToSql for SqlJson<T> {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        serde_json::to_string(&self.0)
+            .map(|json| ToSqlOutput::Owned(Value::Text(json)))
+            .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))
+    }
+}
+
+impl<T: DeserializeOwned> FromSql for SqlJson<T> {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        serde_json::from_str(value.as_str()?)
+            .map(SqlJson)
+            .map_err(|e| FromSqlError::Other(Box::new(e)))
+    }
+}
+
+/// Embedding stored as a BLOB of little-endian `f32`s.
+#[derive(Debug)]
+pub struct SqlEmbedding(pub Vec<f32>);
+
+impl From<&Embedding> for SqlEmbedding {
+    fn from(embedding: &Embedding) -> Self {
+        SqlEmbedding(embedding.to_vec())
+    }
+}
+
+impl From<SqlEmbedding> for Embedding {
+    fn from(embedding: SqlEmbedding) -> Self {
+        Embedding::from(embedding.0)
+    }
+}
+
+impl ToSql for SqlEmbedding {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        let bytes = self
+            .0
+            .iter()
+            .flat_map(|value| value.to_le_bytes())
+            .collect();
+        Ok(ToSqlOutput::Owned(Value::Blob(bytes)))
+    }
+}
+
+impl FromSql for SqlEmbedding {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        let bytes = value.as_blob()?;
+        if bytes.len() % 4 != 0 {
+            return Err(FromSqlError::InvalidBlobSize {
+                expected_size: bytes.len() - bytes.len() % 4,
+                blob_size: bytes.len(),
+            });
+        }
+
+        Ok(SqlEmbedding(
+            bytes
+                .chunks_exact(4)
+                .map(|chunk| f32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+                .collect(),
+        ))
+    }
+}
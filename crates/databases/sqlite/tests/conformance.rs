@@ -0,0 +1,10 @@
+use std::sync::Arc;
+
+use synx_sqlite_database::{rusqlite::Connection, SynxSqliteDatabase};
+
+fn sqlite() -> Arc<SynxSqliteDatabase> {
+    let connection = Connection::open_in_memory().unwrap();
+    Arc::new(SynxSqliteDatabase::new(connection).unwrap())
+}
+
+synx_database_conformance::db_conformance_tests!(sqlite());
@@ -0,0 +1,135 @@
+use std::{fmt, str::FromStr};
+
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::blob;
+
+/// Characters of a key kept in the clear, to tell keys apart in listings.
+const PREFIX_LEN: usize = 12;
+
+/// What a key may do within its tenant.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Scope {
+    /// Read threads, messages, blobs and webhooks.
+    #[serde(rename = "threads:read")]
+    ThreadsRead,
+    /// Create and change threads, messages, blobs and webhooks, and import.
+    #[serde(rename = "threads:write")]
+    ThreadsWrite,
+    /// Search threads and messages.
+    #[serde(rename = "search")]
+    Search,
+    /// Delete anything, and implies every other scope. On the default tenant it
+    /// also manages tenants and inspects the database.
+    #[serde(rename = "admin")]
+    Admin,
+}
+
+impl Scope {
+    pub const ALL: &'static [Scope] = &[
+        Scope::ThreadsRead,
+        Scope::ThreadsWrite,
+        Scope::Search,
+        Scope::Admin,
+    ];
+}
+
+impl fmt::Display for Scope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Scope::ThreadsRead => "threads:read",
+            Scope::ThreadsWrite => "threads:write",
+            Scope::Search => "search",
+            Scope::Admin => "admin",
+        })
+    }
+}
+
+impl FromStr for Scope {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "threads:read" => Ok(Scope::ThreadsRead),
+            "threads:write" => Ok(Scope::ThreadsWrite),
+            "search" => Ok(Scope::Search),
+            "admin" => Ok(Scope::Admin),
+            _ => Err(format!("unknown scope: {}", s)),
+        }
+    }
+}
+
+/// Scopes of keys issued without any, and of keys issued before scopes existed.
+fn default_scopes() -> Vec<Scope> {
+    vec![Scope::ThreadsRead, Scope::ThreadsWrite, Scope::Search]
+}
+
+/// A key acting for one tenant. Only the SHA-256 of the key is stored.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ApiKey {
+    pub id: Uuid,
+    pub tenant_id: Uuid,
+    pub name: Option<String>,
+    #[serde(default = "default_scopes")]
+    pub scopes: Vec<Scope>,
+    /// Start of the key, e.g. `synx_1a2b3c4`.
+    pub prefix: String,
+    /// Lowercase hex SHA-256 of the key; empty once redacted.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub key_hash: String,
+    pub created_at: u64,
+}
+
+impl ApiKey {
+    /// A new key for the tenant, returned in the clear alongside its record.
+    pub fn generate(tenant_id: Uuid, name: Option<String>, scopes: Vec<Scope>) -> (Self, String) {
+        let key = format!(
+            "synx_{}{}",
+            Uuid::new_v4().simple(),
+            Uuid::new_v4().simple()
+        );
+        let api_key = Self {
+            id: Uuid::new_v4(),
+            tenant_id,
+            name,
+            scopes,
+            prefix: key[..PREFIX_LEN].to_string(),
+            key_hash: Self::hash(&key),
+            created_at: Utc::now().timestamp_millis() as u64,
+        };
+        (api_key, key)
+    }
+
+    pub fn hash(key: &str) -> String {
+        blob::hash(key.as_bytes())
+    }
+
+    pub fn id(&self) -> Uuid {
+        self.id
+    }
+
+    /// The key without its hash, as listed to clients.
+    pub fn redacted(self) -> Self {
+        Self {
+            key_hash: String::new(),
+            ..self
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateApiKey {
+    pub name: Option<String>,
+    #[serde(default = "default_scopes")]
+    pub scopes: Vec<Scope>,
+}
+
+/// A key as returned when issued, the only time the key itself is shown.
+#[derive(Debug, Serialize)]
+pub struct IssuedApiKey {
+    #[serde(flatten)]
+    pub api_key: ApiKey,
+    pub key: String,
+}
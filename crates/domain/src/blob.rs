@@ -0,0 +1,51 @@
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+/// How long an unreferenced blob is kept after its last upload, so it can be
+/// referenced by a message written after the upload.
+pub const GRACE_PERIOD_MILLIS: u64 = 60 * 60 * 1_000;
+
+/// A binary attachment, stored once under the SHA-256 of its bytes.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Blob {
+    /// Lowercase hex SHA-256 of the data.
+    pub hash: String,
+    #[serde(rename = "mimeType")]
+    pub mime_type: Option<String>,
+    pub size: u64,
+    pub created_at: u64,
+    /// Refreshed each time the same data is uploaded again.
+    #[serde(default)]
+    pub uploaded_at: u64,
+    /// Number of references from message contents, revisions included. A blob
+    /// is removed once it has no reference left and its grace period is over.
+    pub ref_count: u64,
+}
+
+impl Blob {
+    pub fn new(data: &[u8], mime_type: Option<String>) -> Self {
+        let now = Utc::now().timestamp_millis() as u64;
+        Self {
+            hash: hash(data),
+            mime_type,
+            size: data.len() as u64,
+            created_at: now,
+            uploaded_at: now,
+            ref_count: 0,
+        }
+    }
+
+    /// Blobs last uploaded before this time are removed once unreferenced.
+    pub fn collection_cutoff() -> u64 {
+        (Utc::now().timestamp_millis() as u64).saturating_sub(GRACE_PERIOD_MILLIS)
+    }
+
+    pub fn is_collectable(&self, cutoff: u64) -> bool {
+        self.ref_count == 0 && self.uploaded_at < cutoff
+    }
+}
+
+pub fn hash(data: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(data))
+}
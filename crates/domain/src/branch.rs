@@ -0,0 +1,99 @@
+use std::collections::HashMap;
+
+use serde::Deserialize;
+use uuid::Uuid;
+
+use crate::message::Message;
+
+/// The messages of a thread seen as a tree.
+///
+/// A message's parent is its `parent_id` when that message is still in the
+/// thread, and otherwise the message created just before it, so threads written
+/// without `parent_id` form a single chain.
+pub struct MessageTree {
+    messages: Vec<Message>,
+    parents: Vec<Option<usize>>,
+    children: Vec<Vec<usize>>,
+}
+
+impl MessageTree {
+    pub fn new(mut messages: Vec<Message>) -> Self {
+        messages.sort_by_key(Message::cursor);
+        let positions: HashMap<Uuid, usize> = messages
+            .iter()
+            .enumerate()
+            .map(|(position, message)| (message.id(), position))
+            .collect();
+
+        let mut parents = Vec::with_capacity(messages.len());
+        let mut children = vec![Vec::new(); messages.len()];
+        for (position, message) in messages.iter().enumerate() {
+            let parent = message
+                .parent_id
+                .and_then(|parent_id| positions.get(&parent_id).copied())
+                .filter(|&parent| parent < position)
+                .or(position.checked_sub(1));
+            if let Some(parent) = parent {
+                children[parent].push(position);
+            }
+            parents.push(parent);
+        }
+
+        Self {
+            messages,
+            parents,
+            children,
+        }
+    }
+
+    /// The conversation from the root down to `message_id`, or `None` if the
+    /// message is not in the thread.
+    pub fn ancestors(&self, message_id: Uuid) -> Option<Vec<Message>> {
+        let position = self.position(message_id)?;
+        Some(self.path_to(position))
+    }
+
+    /// The branch through `message_id`: its ancestors, the message itself and its
+    /// most recent descendants. Without a message this is the active path, the
+    /// branch ending at the latest message.
+    pub fn branch(&self, message_id: Option<Uuid>) -> Option<Vec<Message>> {
+        let mut position = match message_id {
+            Some(message_id) => self.position(message_id)?,
+            None if self.messages.is_empty() => return Some(Vec::new()),
+            None => self.messages.len() - 1,
+        };
+        while let Some(&latest) = self.children[position].last() {
+            position = latest;
+        }
+        Some(self.path_to(position))
+    }
+
+    fn position(&self, message_id: Uuid) -> Option<usize> {
+        self.messages
+            .iter()
+            .position(|message| message.id() == message_id)
+    }
+
+    fn path_to(&self, position: usize) -> Vec<Message> {
+        let mut path = vec![self.messages[position].clone()];
+        let mut current = position;
+        while let Some(parent) = self.parents[current] {
+            path.push(self.messages[parent].clone());
+            current = parent;
+        }
+        path.reverse();
+        path
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct GetBranch {
+    /// Any message of the branch; the active path when absent.
+    pub through: Option<Uuid>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ForkThread {
+    /// Last message copied into the new thread.
+    pub at: Uuid,
+}
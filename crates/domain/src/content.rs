@@ -1,4 +1,5 @@
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 #[serde(tag = "type", rename_all = "snake_case")]
@@ -11,11 +12,57 @@ pub enum ContentKind {
         #[serde(rename = "mimeType")]
         mime_type: Option<String>,
     },
+    /// A call the assistant made to a tool.
+    ToolUse {
+        id: String,
+        name: String,
+        input: Value,
+    },
+    /// What the tool returned for the call `tool_use_id`.
+    ToolResult {
+        tool_use_id: String,
+        #[serde(default)]
+        content: Content,
+        #[serde(default)]
+        is_error: bool,
+    },
+    Document {
+        source: DocumentSource,
+        #[serde(rename = "mimeType")]
+        mime_type: Option<String>,
+    },
+    /// An attachment kept in the blob store, referred to by its hash.
+    Blob {
+        hash: String,
+        #[serde(rename = "mimeType")]
+        mime_type: Option<String>,
+    },
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum DocumentSource {
+    Base64 { data: String },
+    Url { url: String },
 }
 
-#[derive(Clone, Debug, Serialize)]
+#[derive(Clone, Debug, Default, Serialize)]
 pub struct Content(pub Vec<ContentKind>);
 
+impl Content {
+    /// Hashes of the blobs the content refers to, once per reference.
+    pub fn blob_hashes(&self) -> Vec<&str> {
+        self.0
+            .iter()
+            .flat_map(|content| match content {
+                ContentKind::Blob { hash, .. } => vec![hash.as_str()],
+                ContentKind::ToolResult { content, .. } => content.blob_hashes(),
+                _ => Vec::new(),
+            })
+            .collect()
+    }
+}
+
 impl From<String> for Content {
     fn from(text: String) -> Self {
         Content(vec![ContentKind::Text { text }])
@@ -40,6 +87,13 @@ impl ToString for Content {
             .map(|content| match content {
                 ContentKind::Text { text } => text.clone(),
                 ContentKind::Image { image, .. } => image.clone(),
+                ContentKind::ToolUse { name, input, .. } => format!("{}({})", name, input),
+                ContentKind::ToolResult { content, .. } => content.to_string(),
+                ContentKind::Document { source, .. } => match source {
+                    DocumentSource::Base64 { data } => data.clone(),
+                    DocumentSource::Url { url } => url.clone(),
+                },
+                ContentKind::Blob { hash, .. } => hash.clone(),
             })
             .collect::<Vec<String>>()
             .join("\n")
@@ -1,6 +1,14 @@
+pub mod api_key;
+pub mod blob;
+pub mod branch;
 pub mod content;
 pub mod embedding;
+pub mod import;
+pub mod job;
 pub mod message;
+pub mod metadata;
+pub mod tenant;
 pub mod thread;
+pub mod webhook;
 
 pub use uuid::Uuid;
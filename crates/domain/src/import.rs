@@ -0,0 +1,89 @@
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{
+    content::Content,
+    message::{Message, Role},
+    metadata::Metadata,
+    thread::Thread,
+};
+
+/// A conversation recorded elsewhere, with its original timestamps.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ImportedThread {
+    pub title: Option<String>,
+    #[serde(default)]
+    pub metadata: Metadata,
+    /// Milliseconds since the epoch; the first message's time when absent.
+    pub created_at: Option<u64>,
+    /// Oldest first.
+    #[serde(default)]
+    pub messages: Vec<ImportedMessage>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ImportedMessage {
+    pub role: Role,
+    pub content: Content,
+    #[serde(default)]
+    pub metadata: Metadata,
+    /// Milliseconds since the epoch; just after the previous message when absent.
+    pub created_at: Option<u64>,
+}
+
+impl ImportedThread {
+    /// The thread and its messages as they are stored for the tenant, under new ids.
+    ///
+    /// Messages keep their order: a creation time that is missing or not after
+    /// the previous message's is moved to just after it, as for messages created
+    /// through [`Thread::next_message_time`].
+    pub fn into_records(self, tenant_id: Uuid) -> (Thread, Vec<Message>) {
+        let mut thread = Thread::new(tenant_id);
+        thread.title = self.title;
+        thread.metadata = self.metadata;
+        thread.created_at = self
+            .created_at
+            .or_else(|| self.messages.first().and_then(|message| message.created_at))
+            .unwrap_or(thread.created_at);
+
+        let mut previous: Option<u64> = None;
+        let messages: Vec<Message> = self
+            .messages
+            .into_iter()
+            .map(|message| {
+                let next = previous.map(|previous| previous + 1);
+                let created_at = match (message.created_at, next) {
+                    (Some(created_at), Some(next)) => created_at.max(next),
+                    (Some(created_at), None) => created_at,
+                    (None, next) => next.unwrap_or(thread.created_at),
+                };
+                previous = Some(created_at);
+
+                Message {
+                    id: Uuid::new_v4(),
+                    thread_id: thread.id(),
+                    role: message.role,
+                    content: message.content,
+                    created_at,
+                    updated_at: None,
+                    metadata: message.metadata,
+                    parent_id: None,
+                }
+            })
+            .collect();
+        thread.set_last_message_at(previous);
+
+        (thread, messages)
+    }
+}
+
+impl ImportedMessage {
+    pub fn new(role: Role, content: Content, created_at: Option<u64>) -> Self {
+        Self {
+            role,
+            content,
+            metadata: Metadata::new(),
+            created_at,
+        }
+    }
+}
@@ -0,0 +1,53 @@
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum JobKind {
+    Summarize { message_id: Uuid },
+    EmbedMessage { message_id: Uuid },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobStatus {
+    Pending,
+    Failed,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Job {
+    pub id: Uuid,
+    /// Tenant of the thread, the default one for jobs queued before tenants existed.
+    #[serde(default)]
+    pub tenant_id: Uuid,
+    pub thread_id: Uuid,
+    pub kind: JobKind,
+    pub status: JobStatus,
+    pub attempts: u32,
+    pub last_error: Option<String>,
+    pub created_at: u64,
+    pub next_attempt_at: u64,
+}
+
+impl Job {
+    pub fn new(tenant_id: Uuid, thread_id: Uuid, kind: JobKind) -> Self {
+        let now = Utc::now().timestamp_millis() as u64;
+        Self {
+            id: Uuid::new_v4(),
+            tenant_id,
+            thread_id,
+            kind,
+            status: JobStatus::Pending,
+            attempts: 0,
+            last_error: None,
+            created_at: now,
+            next_attempt_at: now,
+        }
+    }
+
+    pub fn id(&self) -> Uuid {
+        self.id
+    }
+}
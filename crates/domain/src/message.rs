@@ -1,16 +1,30 @@
+use std::{fmt, str::FromStr};
+
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
-use crate::content::Content;
+use crate::{
+    content::Content,
+    metadata::Metadata,
+    thread::{SortOrder, SummaryCursor, Thread},
+};
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Message {
     pub id: Uuid,
     pub thread_id: Uuid,
-    pub role: String,
+    pub role: Role,
     pub content: Content,
     pub created_at: u64,
+    /// Last edit of the content, if any.
+    #[serde(default)]
+    pub updated_at: Option<u64>,
+    #[serde(default)]
+    pub metadata: Metadata,
+    /// Message this one answers or continues; see [`MessageTree`](crate::branch::MessageTree).
+    #[serde(default)]
+    pub parent_id: Option<Uuid>,
 }
 
 impl Message {
@@ -18,8 +32,25 @@ impl Message {
         self.id
     }
 
-    pub fn update_content(&mut self, new_content: UpdateMessage) {
-        self.content = new_content.content;
+    /// Replaces the content, returning the previous one as revision number `revision`.
+    pub fn update_content(&mut self, new_content: UpdateMessage, revision: u32) -> MessageRevision {
+        let replaced_at = Utc::now().timestamp_millis() as u64;
+        let previous = MessageRevision {
+            message_id: self.id,
+            revision,
+            content: std::mem::replace(&mut self.content, new_content.content),
+            created_at: self.updated_at.unwrap_or(self.created_at),
+            replaced_at,
+        };
+        self.updated_at = Some(replaced_at);
+        previous
+    }
+
+    pub fn cursor(&self) -> MessageCursor {
+        MessageCursor {
+            created_at: self.created_at,
+            message_id: self.id,
+        }
     }
 
     pub fn created_at(&self) -> DateTime<Utc> {
@@ -35,18 +66,26 @@ impl ToString for Message {
 
 #[derive(Serialize, Deserialize)]
 pub struct CreateMessage {
-    pub role: String,
+    pub role: Role,
     pub content: Content,
+    #[serde(default)]
+    pub metadata: Metadata,
+    /// Branch from this message instead of continuing after the latest one.
+    #[serde(default)]
+    pub parent_id: Option<Uuid>,
 }
 
 impl CreateMessage {
-    pub fn into_message(self, thread_id: Uuid) -> Message {
+    pub fn into_message(self, thread: &Thread) -> Message {
         Message {
             id: Uuid::new_v4(),
-            thread_id,
+            thread_id: thread.id(),
             role: self.role,
             content: self.content,
-            created_at: Utc::now().timestamp_millis() as u64,
+            created_at: thread.next_message_time(),
+            updated_at: None,
+            metadata: self.metadata,
+            parent_id: self.parent_id,
         }
     }
 }
@@ -56,10 +95,210 @@ pub struct UpdateMessage {
     pub content: Content,
 }
 
-#[derive(Serialize, Deserialize)]
+/// Author of a message, stored as its lowercase name. Any other name is a
+/// [`Role::Custom`], which servers only accept when it is on their allow-list.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(from = "String", into = "String")]
+pub enum Role {
+    User,
+    Assistant,
+    System,
+    Tool,
+    Custom(String),
+}
+
+impl Role {
+    pub fn as_str(&self) -> &str {
+        match self {
+            Role::User => "user",
+            Role::Assistant => "assistant",
+            Role::System => "system",
+            Role::Tool => "tool",
+            Role::Custom(name) => name,
+        }
+    }
+}
+
+impl fmt::Display for Role {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl From<String> for Role {
+    fn from(name: String) -> Self {
+        match name.as_str() {
+            "user" => Role::User,
+            "assistant" => Role::Assistant,
+            "system" => Role::System,
+            "tool" => Role::Tool,
+            _ => Role::Custom(name),
+        }
+    }
+}
+
+impl From<&str> for Role {
+    fn from(name: &str) -> Self {
+        Role::from(name.to_string())
+    }
+}
+
+impl From<Role> for String {
+    fn from(role: Role) -> Self {
+        match role {
+            Role::Custom(name) => name,
+            role => role.as_str().to_string(),
+        }
+    }
+}
+
+/// Content a message had before an edit. Revisions are numbered from 1 in
+/// the order they were replaced.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MessageRevision {
+    pub message_id: Uuid,
+    pub revision: u32,
+    pub content: Content,
+    /// When this content was written.
+    pub created_at: u64,
+    /// When the edit that replaced it happened.
+    pub replaced_at: u64,
+}
+
+/// Position of a message in its thread, serialized as `<created_at>.<id>`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct MessageCursor {
+    pub created_at: u64,
+    pub message_id: Uuid,
+}
+
+impl From<SummaryCursor> for MessageCursor {
+    fn from(cursor: SummaryCursor) -> Self {
+        Self {
+            created_at: cursor.created_at,
+            message_id: cursor.message_id,
+        }
+    }
+}
+
+impl fmt::Display for MessageCursor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}", self.created_at, self.message_id)
+    }
+}
+
+impl FromStr for MessageCursor {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("invalid message cursor: {}", s);
+        let (created_at, message_id) = s.split_once('.').ok_or_else(invalid)?;
+        Ok(Self {
+            created_at: created_at.parse().map_err(|_| invalid())?,
+            message_id: Uuid::parse_str(message_id).map_err(|_| invalid())?,
+        })
+    }
+}
+
+impl TryFrom<String> for MessageCursor {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<MessageCursor> for String {
+    fn from(cursor: MessageCursor) -> Self {
+        cursor.to_string()
+    }
+}
+
+/// Range, order and page of a thread's messages.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ListMessages {
+    /// Only messages strictly after this position.
+    pub after: Option<MessageCursor>,
+    /// Only messages strictly before this position.
+    pub before: Option<MessageCursor>,
+    /// Chronological by default; `desc` returns the newest messages first.
+    pub order: SortOrder,
+    pub offset: Option<usize>,
+    pub limit: Option<usize>,
+}
+
+impl Default for ListMessages {
+    fn default() -> Self {
+        Self {
+            after: None,
+            before: None,
+            order: SortOrder::Asc,
+            offset: None,
+            limit: None,
+        }
+    }
+}
+
+impl ListMessages {
+    pub fn page(limit: Option<usize>, offset: Option<usize>) -> Self {
+        Self {
+            limit,
+            offset,
+            ..Self::default()
+        }
+    }
+
+    pub fn contains(&self, position: MessageCursor) -> bool {
+        self.after.is_none_or(|after| position > after)
+            && self.before.is_none_or(|before| position < before)
+    }
+
+    /// Pages all messages of a thread held in memory; `total` is their count.
+    pub fn paginate(&self, mut messages: Vec<Message>) -> ThreadMessagesResponse {
+        let total = messages.len();
+        messages.sort_by_key(Message::cursor);
+        if self.order == SortOrder::Desc {
+            messages.reverse();
+        }
+
+        let offset = self.offset.unwrap_or(0);
+        let mut messages: Vec<Message> = messages
+            .into_iter()
+            .filter(|message| self.contains(message.cursor()))
+            .skip(offset)
+            .collect();
+        let limit = self.limit.unwrap_or(messages.len());
+        let next_cursor = self.next_cursor(&messages, limit);
+        messages.truncate(limit);
+
+        ThreadMessagesResponse {
+            messages,
+            total,
+            offset,
+            limit,
+            next_cursor,
+        }
+    }
+
+    /// Cursor of the last message of the page, when `messages` holds more than `limit` messages.
+    pub fn next_cursor(&self, messages: &[Message], limit: usize) -> Option<MessageCursor> {
+        messages
+            .get(limit)
+            .and(limit.checked_sub(1))
+            .map(|last| messages[last].cursor())
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
 pub struct ThreadMessagesResponse {
     pub messages: Vec<Message>,
     pub total: usize,
     pub offset: usize,
     pub limit: usize,
+    /// Cursor of the last message returned, if more messages follow it. Pass it
+    /// as `after` to continue an ascending listing, or as `before` for a
+    /// descending one.
+    pub next_cursor: Option<MessageCursor>,
 }
@@ -0,0 +1,19 @@
+use serde_json::Value;
+
+pub type Metadata = serde_json::Map<String, Value>;
+
+/// Whether `metadata` holds every key of `filter` with an equal value.
+///
+/// Filters built from query strings only carry strings, so a non-string value
+/// also matches its JSON text (`42` matches `"42"`, `true` matches `"true"`).
+pub fn matches(metadata: &Metadata, filter: &Metadata) -> bool {
+    filter
+        .iter()
+        .all(|(key, expected)| match (metadata.get(key), expected) {
+            (Some(actual), expected) if actual == expected => true,
+            (Some(actual), Value::String(expected)) if !actual.is_string() => {
+                serde_json::to_string(actual).is_ok_and(|actual| actual == *expected)
+            }
+            _ => false,
+        })
+}
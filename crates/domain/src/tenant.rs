@@ -0,0 +1,44 @@
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Owner of threads, webhooks and API keys. Nothing one tenant owns is visible
+/// to another.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Tenant {
+    pub id: Uuid,
+    pub name: String,
+    pub created_at: u64,
+}
+
+impl Tenant {
+    /// Owns everything written before tenants existed, and whatever is written
+    /// with the server's own API key.
+    pub const DEFAULT_ID: Uuid = Uuid::nil();
+
+    pub fn new(name: String) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            name,
+            created_at: Utc::now().timestamp_millis() as u64,
+        }
+    }
+
+    /// The tenant every store starts with.
+    pub fn default_tenant() -> Self {
+        Self {
+            id: Self::DEFAULT_ID,
+            name: "default".to_string(),
+            created_at: 0,
+        }
+    }
+
+    pub fn id(&self) -> Uuid {
+        self.id
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateTenant {
+    pub name: String,
+}
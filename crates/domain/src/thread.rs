@@ -1,23 +1,69 @@
+use std::{fmt, str::FromStr};
+
+use chrono::Utc;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
-use crate::embedding::Embedding;
+use crate::{
+    embedding::Embedding,
+    message::Message,
+    metadata::{self, Metadata},
+};
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Thread {
     pub id: Uuid,
+    /// Owning tenant; threads stored before tenants existed belong to the default one.
+    #[serde(default)]
+    pub tenant_id: Uuid,
     pub title: Option<String>,
     pub summary: Option<String>,
+    /// Last message folded into `summary`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub summary_cursor: Option<SummaryCursor>,
+    #[serde(default)]
+    pub metadata: Metadata,
+    /// Milliseconds since the epoch.
+    #[serde(default)]
+    pub created_at: u64,
+    /// Last change to the title or metadata, if any.
+    #[serde(default)]
+    pub updated_at: Option<u64>,
+    /// Creation time of the latest message, if the thread has any.
+    #[serde(default)]
+    pub last_message_at: Option<u64>,
     #[serde(skip)]
     pub embedding: Option<Embedding>,
 }
 
+/// Position of a message in a thread, ordered by creation time.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct SummaryCursor {
+    pub created_at: u64,
+    pub message_id: Uuid,
+}
+
+impl From<&Message> for SummaryCursor {
+    fn from(message: &Message) -> Self {
+        Self {
+            created_at: message.created_at,
+            message_id: message.id,
+        }
+    }
+}
+
 impl Thread {
-    pub fn new() -> Self {
+    pub fn new(tenant_id: Uuid) -> Self {
         Self {
             id: Uuid::new_v4(),
+            tenant_id,
             title: None,
             summary: None,
+            summary_cursor: None,
+            metadata: Metadata::new(),
+            created_at: Utc::now().timestamp_millis() as u64,
+            updated_at: None,
+            last_message_at: None,
             embedding: None,
         }
     }
@@ -30,8 +76,48 @@ impl Thread {
         self.title = title;
     }
 
-    pub fn set_summary(&mut self, summary: String) {
+    pub fn set_metadata(&mut self, metadata: Metadata) {
+        self.metadata = metadata;
+    }
+
+    /// Applies an update of the title and metadata, recording when it happened.
+    pub fn update(&mut self, update: UpdateThread) {
+        self.set_title(update.title);
+        self.set_metadata(update.metadata);
+        self.updated_at = Some(Utc::now().timestamp_millis() as u64);
+    }
+
+    pub fn set_last_message_at(&mut self, last_message_at: Option<u64>) {
+        self.last_message_at = last_message_at;
+    }
+
+    /// Time of the latest message, or of the thread's creation if it has none.
+    pub fn last_activity_at(&self) -> u64 {
+        self.last_message_at.unwrap_or(self.created_at)
+    }
+
+    /// Creation time for a new message: now, but always after the latest message,
+    /// so that creation times order a thread's messages without ties.
+    pub fn next_message_time(&self) -> u64 {
+        let now = Utc::now().timestamp_millis() as u64;
+        self.last_message_at
+            .map_or(now, |last_message_at| now.max(last_message_at + 1))
+    }
+
+    /// Position of the thread in a listing ordered by `sort`.
+    pub fn cursor(&self, sort: ThreadSort) -> ThreadCursor {
+        ThreadCursor {
+            timestamp: match sort {
+                ThreadSort::CreatedAt => self.created_at,
+                ThreadSort::LastActivity => self.last_activity_at(),
+            },
+            thread_id: self.id,
+        }
+    }
+
+    pub fn set_summary(&mut self, summary: String, cursor: SummaryCursor) {
         self.summary = Some(summary);
+        self.summary_cursor = Some(cursor);
     }
 
     pub fn set_embedding(&mut self, embedding: Embedding) {
@@ -42,4 +128,144 @@ impl Thread {
 #[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
 pub struct UpdateThread {
     pub title: Option<String>,
+    #[serde(default)]
+    pub metadata: Metadata,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ThreadSort {
+    #[default]
+    CreatedAt,
+    /// See [`Thread::last_activity_at`].
+    LastActivity,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SortOrder {
+    Asc,
+    #[default]
+    Desc,
+}
+
+/// Position of a thread in a listing: its sort timestamp, with the id breaking ties.
+///
+/// Serialized as `<timestamp>.<id>`, which is what clients pass back as `cursor`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct ThreadCursor {
+    pub timestamp: u64,
+    pub thread_id: Uuid,
+}
+
+impl fmt::Display for ThreadCursor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}", self.timestamp, self.thread_id)
+    }
+}
+
+impl FromStr for ThreadCursor {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("invalid thread cursor: {}", s);
+        let (timestamp, thread_id) = s.split_once('.').ok_or_else(invalid)?;
+        Ok(Self {
+            timestamp: timestamp.parse().map_err(|_| invalid())?,
+            thread_id: Uuid::parse_str(thread_id).map_err(|_| invalid())?,
+        })
+    }
+}
+
+impl TryFrom<String> for ThreadCursor {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<ThreadCursor> for String {
+    fn from(cursor: ThreadCursor) -> Self {
+        cursor.to_string()
+    }
+}
+
+/// Filters, order and page of a thread listing.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ListThreads {
+    /// Only threads whose metadata matches every entry; see [`metadata::matches`].
+    #[serde(skip)]
+    pub metadata: Metadata,
+    /// Only threads created strictly after this time, in milliseconds since the epoch.
+    pub created_after: Option<u64>,
+    /// Only threads created strictly before this time, in milliseconds since the epoch.
+    pub created_before: Option<u64>,
+    pub sort: ThreadSort,
+    pub order: SortOrder,
+    /// Resume after this thread, as returned in [`ListThreadsResponse::next_cursor`].
+    pub cursor: Option<ThreadCursor>,
+    pub limit: Option<usize>,
+}
+
+impl ListThreads {
+    /// Whether the thread passes the metadata and creation time filters.
+    pub fn matches(&self, thread: &Thread) -> bool {
+        self.created_after
+            .is_none_or(|after| thread.created_at > after)
+            && self
+                .created_before
+                .is_none_or(|before| thread.created_at < before)
+            && metadata::matches(&thread.metadata, &self.metadata)
+    }
+
+    /// Whether a thread at `position` comes after the cursor in the requested order.
+    pub fn is_past_cursor(&self, position: ThreadCursor) -> bool {
+        match (self.cursor, self.order) {
+            (None, _) => true,
+            (Some(cursor), SortOrder::Asc) => position > cursor,
+            (Some(cursor), SortOrder::Desc) => position < cursor,
+        }
+    }
+
+    /// Filters, sorts and pages threads held in memory.
+    pub fn paginate(&self, threads: impl IntoIterator<Item = Thread>) -> ListThreadsResponse {
+        let mut threads: Vec<Thread> = threads
+            .into_iter()
+            .filter(|thread| self.matches(thread))
+            .collect();
+        let total = threads.len();
+
+        threads.sort_by_key(|thread| thread.cursor(self.sort));
+        if self.order == SortOrder::Desc {
+            threads.reverse();
+        }
+        threads.retain(|thread| self.is_past_cursor(thread.cursor(self.sort)));
+
+        let limit = self.limit.unwrap_or(total);
+        let next_cursor = threads
+            .get(limit)
+            .and(limit.checked_sub(1))
+            .map(|last| threads[last].cursor(self.sort));
+        threads.truncate(limit);
+
+        ListThreadsResponse {
+            threads,
+            total,
+            limit,
+            next_cursor,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ListThreadsResponse {
+    pub threads: Vec<Thread>,
+    /// Number of threads passing the filters, across all pages.
+    pub total: usize,
+    pub limit: usize,
+    /// Cursor of the last thread returned, if more threads follow it.
+    pub next_cursor: Option<ThreadCursor>,
 }
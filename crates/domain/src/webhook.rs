@@ -0,0 +1,113 @@
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use uuid::Uuid;
+
+/// A URL notified of thread and message events.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Webhook {
+    pub id: Uuid,
+    /// Only events of this tenant's threads are delivered.
+    #[serde(default)]
+    pub tenant_id: Uuid,
+    pub url: String,
+    /// Event types delivered; every type when empty.
+    #[serde(default)]
+    pub events: Vec<String>,
+    /// Key of the HMAC-SHA256 signature sent with each payload. Stored, but only
+    /// shown to clients when the webhook is created.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub secret: Option<String>,
+    pub created_at: u64,
+}
+
+impl Webhook {
+    pub fn id(&self) -> Uuid {
+        self.id
+    }
+
+    pub fn accepts(&self, event: &str) -> bool {
+        self.events.is_empty() || self.events.iter().any(|accepted| accepted == event)
+    }
+
+    /// The webhook without its secret, as listed to clients.
+    pub fn redacted(self) -> Self {
+        Self {
+            secret: None,
+            ..self
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateWebhook {
+    pub url: String,
+    #[serde(default)]
+    pub events: Vec<String>,
+    /// Generated when absent.
+    pub secret: Option<String>,
+}
+
+impl CreateWebhook {
+    pub fn into_webhook(self, tenant_id: Uuid, secret: String) -> Webhook {
+        Webhook {
+            id: Uuid::new_v4(),
+            tenant_id,
+            url: self.url,
+            events: self.events,
+            secret: Some(secret),
+            created_at: Utc::now().timestamp_millis() as u64,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeliveryStatus {
+    Pending,
+    Succeeded,
+    Failed,
+}
+
+/// One event sent to one webhook, with the outcome of its latest attempt.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct WebhookDelivery {
+    pub id: Uuid,
+    #[serde(default)]
+    pub tenant_id: Uuid,
+    pub webhook_id: Uuid,
+    pub event: String,
+    /// The exact body posted, signed as is.
+    pub payload: Value,
+    pub status: DeliveryStatus,
+    pub attempts: u32,
+    pub response_status: Option<u16>,
+    pub last_error: Option<String>,
+    pub created_at: u64,
+    pub next_attempt_at: u64,
+    pub delivered_at: Option<u64>,
+}
+
+impl WebhookDelivery {
+    pub fn new(webhook: &Webhook, event: &str, payload: Value) -> Self {
+        let now = Utc::now().timestamp_millis() as u64;
+        Self {
+            id: Uuid::new_v4(),
+            tenant_id: webhook.tenant_id,
+            webhook_id: webhook.id(),
+            event: event.to_string(),
+            payload,
+            status: DeliveryStatus::Pending,
+            attempts: 0,
+            response_status: None,
+            last_error: None,
+            created_at: now,
+            next_attempt_at: now,
+            delivered_at: None,
+        }
+    }
+
+    pub fn id(&self) -> Uuid {
+        self.id
+    }
+}
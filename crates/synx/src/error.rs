@@ -0,0 +1,11 @@
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum SynxError {
+    #[error("Embedding failed: {0}")]
+    Embedding(String),
+    #[error("Summarization failed: {0}")]
+    Summarization(String),
+    #[error("Invalid input: {0}")]
+    InvalidInput(String),
+}
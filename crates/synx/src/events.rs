@@ -0,0 +1,139 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use ferrochain::futures::{stream, Stream};
+use serde::Serialize;
+use synx_domain::{message::Message, thread::SummaryCursor};
+use tokio::sync::broadcast::{self, error::RecvError};
+use uuid::Uuid;
+
+/// Names of every [`Event`], as used by webhook filters.
+pub const EVENT_TYPES: &[&str] = &[
+    "message.created",
+    "message.updated",
+    "message.deleted",
+    "summary.updated",
+    "thread.deleted",
+];
+
+/// Events of a thread kept for subscribers that fall behind.
+const EVENT_BUFFER: usize = 1024;
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(tag = "type")]
+pub enum Event {
+    #[serde(rename = "message.created")]
+    MessageCreated { thread_id: Uuid, message: Message },
+    #[serde(rename = "message.updated")]
+    MessageUpdated { thread_id: Uuid, message: Message },
+    #[serde(rename = "message.deleted")]
+    MessageDeleted { thread_id: Uuid, message_id: Uuid },
+    #[serde(rename = "summary.updated")]
+    SummaryUpdated {
+        thread_id: Uuid,
+        summary: String,
+        cursor: SummaryCursor,
+    },
+    #[serde(rename = "thread.deleted")]
+    ThreadDeleted { thread_id: Uuid },
+}
+
+impl Event {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Event::MessageCreated { .. } => "message.created",
+            Event::MessageUpdated { .. } => "message.updated",
+            Event::MessageDeleted { .. } => "message.deleted",
+            Event::SummaryUpdated { .. } => "summary.updated",
+            Event::ThreadDeleted { .. } => "thread.deleted",
+        }
+    }
+
+    pub fn thread_id(&self) -> Uuid {
+        match self {
+            Event::MessageCreated { thread_id, .. }
+            | Event::MessageUpdated { thread_id, .. }
+            | Event::MessageDeleted { thread_id, .. }
+            | Event::SummaryUpdated { thread_id, .. }
+            | Event::ThreadDeleted { thread_id } => *thread_id,
+        }
+    }
+}
+
+/// Ends a subscription that fell more than [`EVENT_BUFFER`] events behind; the
+/// subscriber should reload the thread before subscribing again.
+#[derive(Clone, Copy, Debug, Serialize)]
+pub struct Lagged {
+    pub missed: u64,
+}
+
+/// Fans out changes to whoever is listening at the time; nothing is stored.
+/// Each thread has its own channel, so a busy thread cannot push the events of
+/// another out of a subscriber's buffer.
+#[derive(Clone)]
+pub struct EventBus {
+    senders: Arc<Mutex<HashMap<Uuid, broadcast::Sender<Event>>>>,
+}
+
+impl EventBus {
+    pub fn new() -> Self {
+        Self {
+            senders: Arc::default(),
+        }
+    }
+
+    pub fn publish(&self, event: Event) {
+        let mut senders = self.senders.lock().unwrap();
+        let thread_id = event.thread_id();
+        let Some(sender) = senders.get(&thread_id) else {
+            return;
+        };
+
+        let deleted = matches!(event, Event::ThreadDeleted { .. });
+        // Sending only fails when nobody is subscribed anymore.
+        if sender.send(event).is_err() || deleted {
+            senders.remove(&thread_id);
+        }
+    }
+
+    /// Events of one thread from now on, ending after the thread is deleted, or
+    /// with [`Lagged`] once the subscriber falls too far behind.
+    pub fn subscribe(
+        &self,
+        thread_id: Uuid,
+    ) -> impl Stream<Item = Result<Event, Lagged>> + Send + 'static {
+        let receiver = {
+            let mut senders = self.senders.lock().unwrap();
+            senders.retain(|_, sender| sender.receiver_count() > 0);
+            senders
+                .entry(thread_id)
+                .or_insert_with(|| broadcast::channel(EVENT_BUFFER).0)
+                .subscribe()
+        };
+
+        stream::unfold(Some(receiver), move |receiver| async move {
+            let mut receiver = receiver?;
+            match receiver.recv().await {
+                Ok(event @ Event::ThreadDeleted { .. }) => Some((Ok(event), None)),
+                Ok(event) => Some((Ok(event), Some(receiver))),
+                Err(RecvError::Lagged(missed)) => {
+                    tracing::warn!(
+                        "Subscriber to thread {} missed {} events",
+                        thread_id,
+                        missed
+                    );
+                    Some((Err(Lagged { missed }), None))
+                }
+                Err(RecvError::Closed) => None,
+            }
+        })
+    }
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
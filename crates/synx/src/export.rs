@@ -0,0 +1,437 @@
+pub mod transcript;
+
+use std::collections::HashMap;
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use synx_domain::{
+    blob::Blob,
+    content::{Content, ContentKind, DocumentSource},
+    message::{Message, Role},
+};
+
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    /// `{system, messages}` for the Anthropic Messages API.
+    Anthropic,
+    /// `{messages}` for the OpenAI Chat Completions API.
+    OpenAi,
+    /// `{system, messages}` of ferrochain messages and content.
+    Ferrochain,
+}
+
+#[derive(Deserialize)]
+pub struct ExportRequest {
+    pub format: ExportFormat,
+}
+
+/// Inline images without a mime type are assumed to be PNG.
+const DEFAULT_IMAGE_TYPE: &str = "image/png";
+const DEFAULT_DOCUMENT_TYPE: &str = "application/pdf";
+
+/// Blob contents by hash, for inlining attachments.
+pub type Blobs = HashMap<String, (Blob, Vec<u8>)>;
+
+enum Source {
+    Base64 { media_type: String, data: String },
+    Url(String),
+}
+
+impl Source {
+    fn anthropic(&self) -> Value {
+        match self {
+            Source::Base64 { media_type, data } => {
+                json!({"type": "base64", "media_type": media_type, "data": data})
+            }
+            Source::Url(url) => json!({"type": "url", "url": url}),
+        }
+    }
+
+    fn url(&self) -> String {
+        match self {
+            Source::Base64 { media_type, data } => format!("data:{};base64,{}", media_type, data),
+            Source::Url(url) => url.clone(),
+        }
+    }
+}
+
+/// Content with blobs resolved and inline images parsed, in the terms every
+/// provider format is built from.
+enum Block {
+    Text(String),
+    Image(Source),
+    Document {
+        source: Source,
+        filename: String,
+    },
+    ToolUse {
+        id: String,
+        name: String,
+        input: Value,
+    },
+    ToolResult {
+        tool_use_id: String,
+        content: Vec<Block>,
+        is_error: bool,
+    },
+}
+
+fn blocks(content: &Content, blobs: &Blobs) -> Vec<Block> {
+    content
+        .0
+        .iter()
+        .filter_map(|content| match content {
+            ContentKind::Text { text } => Some(Block::Text(text.clone())),
+            ContentKind::Image { image, mime_type } => {
+                Some(Block::Image(image_source(image, mime_type.as_deref())))
+            }
+            ContentKind::Document { source, mime_type } => {
+                let media_type = mime_type.as_deref().unwrap_or(DEFAULT_DOCUMENT_TYPE);
+                Some(Block::Document {
+                    filename: filename("document", media_type),
+                    source: match source {
+                        DocumentSource::Base64 { data } => Source::Base64 {
+                            media_type: media_type.to_string(),
+                            data: data.clone(),
+                        },
+                        DocumentSource::Url { url } => Source::Url(url.clone()),
+                    },
+                })
+            }
+            ContentKind::Blob { hash, mime_type } => {
+                let Some((blob, data)) = blobs.get(hash) else {
+                    tracing::warn!("Blob {} is missing from the export", hash);
+                    return None;
+                };
+                let media_type = mime_type
+                    .clone()
+                    .or_else(|| blob.mime_type.clone())
+                    .unwrap_or_else(|| DEFAULT_DOCUMENT_TYPE.to_string());
+                let filename = filename(hash, &media_type);
+                let is_image = media_type.starts_with("image/");
+                let source = Source::Base64 {
+                    media_type,
+                    data: STANDARD.encode(data),
+                };
+                Some(if is_image {
+                    Block::Image(source)
+                } else {
+                    Block::Document { source, filename }
+                })
+            }
+            ContentKind::ToolUse { id, name, input } => Some(Block::ToolUse {
+                id: id.clone(),
+                name: name.clone(),
+                input: input.clone(),
+            }),
+            ContentKind::ToolResult {
+                tool_use_id,
+                content,
+                is_error,
+            } => Some(Block::ToolResult {
+                tool_use_id: tool_use_id.clone(),
+                content: blocks(content, blobs),
+                is_error: *is_error,
+            }),
+        })
+        .collect()
+}
+
+/// Inline images are a URL, a `data:` URL or bare base64.
+fn image_source(image: &str, mime_type: Option<&str>) -> Source {
+    if image.starts_with("http://") || image.starts_with("https://") {
+        return Source::Url(image.to_string());
+    }
+
+    if let Some((header, data)) = image
+        .strip_prefix("data:")
+        .and_then(|url| url.split_once(','))
+    {
+        if let Some(media_type) = header.strip_suffix(";base64") {
+            return Source::Base64 {
+                media_type: media_type.to_string(),
+                data: data.to_string(),
+            };
+        }
+    }
+
+    Source::Base64 {
+        media_type: mime_type.unwrap_or(DEFAULT_IMAGE_TYPE).to_string(),
+        data: image.to_string(),
+    }
+}
+
+/// `stem` with the extension of `media_type`, such as `pdf` for `application/pdf`,
+/// as OpenAI requires a filename for files.
+fn filename(stem: &str, media_type: &str) -> String {
+    let subtype = media_type
+        .split_once('/')
+        .map_or("bin", |(_, subtype)| subtype);
+    let extension = match subtype.split([';', '+']).next() {
+        Some("plain") => "txt",
+        Some("markdown") => "md",
+        Some(extension) if !extension.is_empty() => extension,
+        _ => "bin",
+    };
+    format!("{}.{}", stem, extension)
+}
+
+fn text(blocks: &[Block]) -> String {
+    blocks
+        .iter()
+        .filter_map(|block| match block {
+            Block::Text(text) => Some(text.as_str()),
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Providers without other roles get everything but the assistant's turns as user turns.
+fn user_or_assistant(role: &Role) -> &'static str {
+    match role {
+        Role::Assistant => "assistant",
+        _ => "user",
+    }
+}
+
+/// Appends to the previous message when it has the same role, as providers
+/// expect user and assistant turns to alternate.
+fn push_turn(messages: &mut Vec<Value>, role: &str, content: Vec<Value>) {
+    if content.is_empty() {
+        return;
+    }
+
+    match messages.last_mut() {
+        Some(last) if last["role"] == role => {
+            if let Some(previous) = last["content"].as_array_mut() {
+                previous.extend(content);
+            }
+        }
+        _ => messages.push(json!({"role": role, "content": content})),
+    }
+}
+
+/// Turns messages, oldest first, into the request body of `format`.
+pub fn export(format: ExportFormat, messages: &[Message], blobs: &Blobs) -> Value {
+    match format {
+        ExportFormat::Anthropic => anthropic(messages, blobs),
+        ExportFormat::OpenAi => openai(messages, blobs),
+        ExportFormat::Ferrochain => ferrochain(messages, blobs),
+    }
+}
+
+fn anthropic_block(block: &Block) -> Value {
+    match block {
+        Block::Text(text) => json!({"type": "text", "text": text}),
+        Block::Image(source) => json!({"type": "image", "source": source.anthropic()}),
+        Block::Document { source, .. } => {
+            json!({"type": "document", "source": source.anthropic()})
+        }
+        Block::ToolUse { id, name, input } => {
+            json!({"type": "tool_use", "id": id, "name": name, "input": input})
+        }
+        Block::ToolResult {
+            tool_use_id,
+            content,
+            is_error,
+        } => json!({
+            "type": "tool_result",
+            "tool_use_id": tool_use_id,
+            "content": content.iter().map(anthropic_block).collect::<Vec<_>>(),
+            "is_error": is_error,
+        }),
+    }
+}
+
+fn anthropic(messages: &[Message], blobs: &Blobs) -> Value {
+    let mut system = Vec::new();
+    let mut turns = Vec::new();
+    for message in messages {
+        let blocks = blocks(&message.content, blobs);
+        if message.role == Role::System {
+            system.push(text(&blocks));
+            continue;
+        }
+
+        push_turn(
+            &mut turns,
+            user_or_assistant(&message.role),
+            blocks.iter().map(anthropic_block).collect(),
+        );
+    }
+
+    let mut body = json!({"messages": turns});
+    if !system.is_empty() {
+        body["system"] = Value::String(system.join("\n\n"));
+    }
+    body
+}
+
+fn openai_part(block: &Block) -> Option<Value> {
+    match block {
+        Block::Text(text) => Some(json!({"type": "text", "text": text})),
+        Block::Image(source) => {
+            Some(json!({"type": "image_url", "image_url": {"url": source.url()}}))
+        }
+        Block::Document {
+            source: source @ Source::Base64 { .. },
+            filename,
+        } => Some(json!({
+            "type": "file",
+            "file": {"filename": filename, "file_data": source.url()},
+        })),
+        Block::Document {
+            source: Source::Url(url),
+            ..
+        } => Some(json!({"type": "text", "text": format!("[document at {}]", url)})),
+        Block::ToolUse { .. } | Block::ToolResult { .. } => None,
+    }
+}
+
+fn openai(messages: &[Message], blobs: &Blobs) -> Value {
+    let mut turns = Vec::new();
+    for message in messages {
+        let blocks = blocks(&message.content, blobs);
+        match message.role {
+            Role::System => turns.push(json!({"role": "system", "content": text(&blocks)})),
+            Role::Assistant => {
+                let tool_calls: Vec<Value> = blocks
+                    .iter()
+                    .filter_map(|block| match block {
+                        Block::ToolUse { id, name, input } => Some(json!({
+                            "id": id,
+                            "type": "function",
+                            "function": {"name": name, "arguments": input.to_string()},
+                        })),
+                        _ => None,
+                    })
+                    .collect();
+                let content = text(&blocks);
+                let mut turn = json!({
+                    "role": "assistant",
+                    "content": if content.is_empty() { Value::Null } else { Value::String(content) },
+                });
+                if !tool_calls.is_empty() {
+                    turn["tool_calls"] = Value::Array(tool_calls);
+                }
+                turns.push(turn);
+            }
+            _ => {
+                for block in &blocks {
+                    if let Block::ToolResult {
+                        tool_use_id,
+                        content,
+                        ..
+                    } = block
+                    {
+                        turns.push(json!({
+                            "role": "tool",
+                            "tool_call_id": tool_use_id,
+                            "content": text(content),
+                        }));
+                    }
+                }
+                let parts: Vec<Value> = blocks.iter().filter_map(openai_part).collect();
+                if !parts.is_empty() {
+                    turns.push(json!({"role": "user", "content": parts}));
+                }
+            }
+        }
+    }
+
+    json!({"messages": turns})
+}
+
+/// ferrochain content has text and images only, so documents, tool calls and tool
+/// results are described in text, with the text of `text/*` documents inlined.
+fn ferrochain_content(block: &Block) -> Vec<ferrochain::message::Content> {
+    use ferrochain::message::Content;
+
+    match block {
+        Block::Text(text) => vec![Content::Text { text: text.clone() }],
+        Block::Image(source) => vec![Content::Image {
+            image: source.url(),
+        }],
+        Block::Document {
+            source: Source::Base64 { media_type, data },
+            filename,
+        } => {
+            let text = media_type
+                .starts_with("text/")
+                .then(|| STANDARD.decode(data).ok())
+                .flatten()
+                .and_then(|data| String::from_utf8(data).ok());
+            vec![Content::Text {
+                text: match text {
+                    Some(text) => format!("[document {}]\n{}", filename, text),
+                    None => format!("[document {} ({})]", filename, media_type),
+                },
+            }]
+        }
+        Block::Document {
+            source: Source::Url(url),
+            ..
+        } => vec![Content::Text {
+            text: format!("[document at {}]", url),
+        }],
+        Block::ToolUse { name, input, .. } => vec![Content::Text {
+            text: format!("[called tool {} with {}]", name, input),
+        }],
+        Block::ToolResult {
+            content, is_error, ..
+        } => {
+            let outcome = if *is_error {
+                "tool error"
+            } else {
+                "tool result"
+            };
+            std::iter::once(Content::Text {
+                text: format!("[{}]", outcome),
+            })
+            .chain(content.iter().flat_map(ferrochain_content))
+            .collect()
+        }
+    }
+}
+
+fn ferrochain(messages: &[Message], blobs: &Blobs) -> Value {
+    use ferrochain::message::{self, Content};
+
+    let mut system = Vec::new();
+    let mut turns: Vec<message::Message> = Vec::new();
+    let mut last_role = None;
+    for message in messages {
+        let blocks = blocks(&message.content, blobs);
+        if message.role == Role::System {
+            system.push(Content::Text {
+                text: text(&blocks),
+            });
+            continue;
+        }
+
+        let content: Vec<Content> = blocks.iter().flat_map(ferrochain_content).collect();
+        if content.is_empty() {
+            continue;
+        }
+        // Turns alternate, as in the other formats.
+        let role = user_or_assistant(&message.role);
+        match turns.last_mut() {
+            Some(last) if last_role == Some(role) => last.content.extend(content),
+            _ => {
+                turns.push(message::Message {
+                    role: match message.role {
+                        Role::Assistant => message::Role::Assistant,
+                        _ => message::Role::User,
+                    },
+                    content,
+                });
+                last_role = Some(role);
+            }
+        }
+    }
+
+    json!({"system": system, "messages": turns})
+}
@@ -0,0 +1,427 @@
+use std::{
+    fmt::{self, Write},
+    io,
+};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chrono::{DateTime, SecondsFormat};
+use serde::Deserialize;
+use serde_json::json;
+use synx_domain::{
+    content::{Content, ContentKind, DocumentSource},
+    message::Message,
+    thread::Thread,
+};
+use uuid::Uuid;
+
+use super::Blobs;
+
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TranscriptFormat {
+    Md,
+    /// A `thread` line followed by one `message` line per message.
+    Jsonl,
+    Html,
+}
+
+impl TranscriptFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            TranscriptFormat::Md => "md",
+            TranscriptFormat::Jsonl => "jsonl",
+            TranscriptFormat::Html => "html",
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            TranscriptFormat::Md => "text/markdown; charset=utf-8",
+            TranscriptFormat::Jsonl => "application/x-ndjson",
+            TranscriptFormat::Html => "text/html; charset=utf-8",
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct TranscriptRequest {
+    pub format: TranscriptFormat,
+    /// Inline base64 images and image blobs as `data:` URLs instead of placeholders.
+    #[serde(default)]
+    pub embed_images: bool,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ArchiveFormat {
+    Tar,
+    Zip,
+}
+
+impl ArchiveFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ArchiveFormat::Tar => "tar",
+            ArchiveFormat::Zip => "zip",
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            ArchiveFormat::Tar => "application/x-tar",
+            ArchiveFormat::Zip => "application/zip",
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct ArchiveRequest {
+    /// Every thread when absent.
+    pub thread_ids: Option<Vec<Uuid>>,
+    pub format: TranscriptFormat,
+    pub archive: ArchiveFormat,
+    #[serde(default)]
+    pub embed_images: bool,
+}
+
+/// Renders the thread's messages, oldest first, under a header with the title
+/// and summary. With `embed_images`, inline images and the image blobs found in
+/// `blobs` become `data:` URLs; other images and attachments get a placeholder.
+pub fn render(
+    format: TranscriptFormat,
+    thread: &Thread,
+    messages: &[Message],
+    embed_images: bool,
+    blobs: &Blobs,
+) -> String {
+    let images = Images {
+        embed: embed_images,
+        blobs,
+    };
+    let mut out = String::new();
+    // Writing to a `String` cannot fail.
+    let _ = match format {
+        TranscriptFormat::Md => markdown(&mut out, thread, messages, &images),
+        TranscriptFormat::Jsonl => jsonl(&mut out, thread, messages),
+        TranscriptFormat::Html => html(&mut out, thread, messages, &images),
+    };
+    out
+}
+
+/// Packs one transcript per thread, named `<thread id>.<extension>`.
+pub fn archive(
+    format: ArchiveFormat,
+    extension: &str,
+    transcripts: Vec<(Uuid, String)>,
+) -> io::Result<Vec<u8>> {
+    match format {
+        ArchiveFormat::Tar => {
+            let mut builder = tar::Builder::new(Vec::new());
+            for (thread_id, transcript) in transcripts {
+                let mut header = tar::Header::new_gnu();
+                header.set_size(transcript.len() as u64);
+                header.set_mode(0o644);
+                header.set_cksum();
+                builder.append_data(
+                    &mut header,
+                    format!("{}.{}", thread_id, extension),
+                    transcript.as_bytes(),
+                )?;
+            }
+            builder.into_inner()
+        }
+        ArchiveFormat::Zip => {
+            let mut writer = zip::ZipWriter::new(io::Cursor::new(Vec::new()));
+            for (thread_id, transcript) in transcripts {
+                writer
+                    .start_file(
+                        format!("{}.{}", thread_id, extension),
+                        zip::write::SimpleFileOptions::default(),
+                    )
+                    .map_err(io::Error::other)?;
+                io::Write::write_all(&mut writer, transcript.as_bytes())?;
+            }
+            Ok(writer.finish().map_err(io::Error::other)?.into_inner())
+        }
+    }
+}
+
+struct Images<'a> {
+    embed: bool,
+    blobs: &'a Blobs,
+}
+
+enum Image {
+    Url(String),
+    Placeholder(String),
+}
+
+impl Images<'_> {
+    fn inline(&self, image: &str, mime_type: Option<&str>) -> Image {
+        if image.starts_with("http://") || image.starts_with("https://") {
+            return Image::Url(image.to_string());
+        }
+
+        let mime_type = mime_type.unwrap_or("image/png");
+        match (self.embed, image.starts_with("data:")) {
+            (true, true) => Image::Url(image.to_string()),
+            (true, false) => Image::Url(format!("data:{};base64,{}", mime_type, image)),
+            (false, _) => Image::Placeholder(format!("image ({})", mime_type)),
+        }
+    }
+
+    fn blob(&self, hash: &str, mime_type: Option<&str>) -> Image {
+        match self.blobs.get(hash) {
+            Some((blob, data)) if self.embed => {
+                let mime_type = mime_type.or(blob.mime_type.as_deref());
+                match mime_type.filter(|mime_type| mime_type.starts_with("image/")) {
+                    Some(mime_type) => Image::Url(format!(
+                        "data:{};base64,{}",
+                        mime_type,
+                        STANDARD.encode(data)
+                    )),
+                    None => Image::Placeholder(attachment(hash, mime_type)),
+                }
+            }
+            Some((blob, _)) => {
+                Image::Placeholder(attachment(hash, mime_type.or(blob.mime_type.as_deref())))
+            }
+            None => Image::Placeholder(attachment(hash, mime_type)),
+        }
+    }
+}
+
+fn attachment(hash: &str, mime_type: Option<&str>) -> String {
+    format!(
+        "attachment {} ({})",
+        hash,
+        mime_type.unwrap_or("application/octet-stream")
+    )
+}
+
+fn document(source: &DocumentSource, mime_type: Option<&str>) -> String {
+    let mime_type = mime_type.unwrap_or("unknown type");
+    match source {
+        DocumentSource::Base64 { .. } => format!("document ({})", mime_type),
+        DocumentSource::Url { url } => format!("document ({}) at {}", mime_type, url),
+    }
+}
+
+fn timestamp(millis: u64) -> String {
+    DateTime::from_timestamp_millis(millis as i64)
+        .map(|time| time.to_rfc3339_opts(SecondsFormat::Secs, true))
+        .unwrap_or_default()
+}
+
+fn title(thread: &Thread) -> &str {
+    thread.title.as_deref().unwrap_or("Untitled thread")
+}
+
+fn markdown(
+    out: &mut String,
+    thread: &Thread,
+    messages: &[Message],
+    images: &Images,
+) -> fmt::Result {
+    writeln!(out, "# {}\n", title(thread))?;
+    writeln!(out, "- Thread: `{}`", thread.id())?;
+    writeln!(out, "- Created: {}", timestamp(thread.created_at))?;
+    writeln!(out, "- Messages: {}\n", messages.len())?;
+    if let Some(summary) = thread.summary.as_deref().filter(|s| !s.is_empty()) {
+        writeln!(out, "## Summary\n\n{}\n", summary)?;
+    }
+
+    for message in messages {
+        write!(
+            out,
+            "---\n\n### {} · {}\n\n",
+            message.role,
+            timestamp(message.created_at)
+        )?;
+        markdown_content(out, &message.content, images)?;
+    }
+    Ok(())
+}
+
+fn markdown_content(out: &mut String, content: &Content, images: &Images) -> fmt::Result {
+    for content in &content.0 {
+        match content {
+            ContentKind::Text { text } => writeln!(out, "{}\n", text),
+            ContentKind::Image { image, mime_type } => {
+                match images.inline(image, mime_type.as_deref()) {
+                    Image::Url(url) => writeln!(out, "![image]({})\n", url),
+                    Image::Placeholder(placeholder) => writeln!(out, "*[{}]*\n", placeholder),
+                }
+            }
+            ContentKind::Blob { hash, mime_type } => {
+                match images.blob(hash, mime_type.as_deref()) {
+                    Image::Url(url) => writeln!(out, "![attachment]({})\n", url),
+                    Image::Placeholder(placeholder) => writeln!(out, "*[{}]*\n", placeholder),
+                }
+            }
+            ContentKind::Document { source, mime_type } => {
+                writeln!(out, "*[{}]*\n", document(source, mime_type.as_deref()))
+            }
+            ContentKind::ToolUse { name, input, .. } => writeln!(
+                out,
+                "**Tool call** `{}`\n\n```json\n{:#}\n```\n",
+                name, input
+            ),
+            ContentKind::ToolResult {
+                content, is_error, ..
+            } => {
+                writeln!(
+                    out,
+                    "**{}**\n",
+                    if *is_error {
+                        "Tool error"
+                    } else {
+                        "Tool result"
+                    }
+                )?;
+                markdown_content(out, content, images)
+            }
+        }?;
+    }
+    Ok(())
+}
+
+fn jsonl(out: &mut String, thread: &Thread, messages: &[Message]) -> fmt::Result {
+    writeln!(
+        out,
+        "{}",
+        json!({
+            "type": "thread",
+            "id": thread.id(),
+            "title": thread.title,
+            "summary": thread.summary,
+            "metadata": thread.metadata,
+            "created_at": thread.created_at,
+        })
+    )?;
+    for message in messages {
+        writeln!(
+            out,
+            "{}",
+            json!({
+                "type": "message",
+                "id": message.id(),
+                "role": message.role,
+                "content": message.content,
+                "metadata": message.metadata,
+                "created_at": message.created_at,
+            })
+        )?;
+    }
+    Ok(())
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+const HTML_STYLE: &str =
+    "body{font-family:sans-serif;max-width:48rem;margin:2rem auto;padding:0 1rem}\
+article{border-top:1px solid #ddd;padding:.5rem 0}\
+header{color:#555;font-size:.9rem}\
+.text,pre{white-space:pre-wrap}\
+img{max-width:100%}\
+.placeholder{color:#777;font-style:italic}";
+
+fn html(out: &mut String, thread: &Thread, messages: &[Message], images: &Images) -> fmt::Result {
+    let title = escape(title(thread));
+    write!(
+        out,
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{title}</title>\n<style>{HTML_STYLE}</style>\n</head>\n<body>\n<h1>{title}</h1>\n"
+    )?;
+    writeln!(
+        out,
+        "<dl><dt>Thread</dt><dd><code>{}</code></dd><dt>Created</dt><dd><time>{}</time></dd><dt>Messages</dt><dd>{}</dd></dl>",
+        thread.id(),
+        timestamp(thread.created_at),
+        messages.len()
+    )?;
+    if let Some(summary) = thread.summary.as_deref().filter(|s| !s.is_empty()) {
+        writeln!(
+            out,
+            "<section class=\"summary\"><h2>Summary</h2><p class=\"text\">{}</p></section>",
+            escape(summary)
+        )?;
+    }
+
+    for message in messages {
+        let created_at = timestamp(message.created_at);
+        writeln!(
+            out,
+            "<article class=\"message\" data-role=\"{role}\"><header><strong>{role}</strong> · <time datetime=\"{created_at}\">{created_at}</time></header>",
+            role = escape(message.role.as_str()),
+        )?;
+        html_content(out, &message.content, images)?;
+        writeln!(out, "</article>")?;
+    }
+    writeln!(out, "</body>\n</html>")
+}
+
+fn html_image(out: &mut String, image: Image) -> fmt::Result {
+    match image {
+        Image::Url(url) => writeln!(out, "<img src=\"{}\" alt=\"image\">", escape(&url)),
+        Image::Placeholder(placeholder) => writeln!(
+            out,
+            "<p class=\"placeholder\">[{}]</p>",
+            escape(&placeholder)
+        ),
+    }
+}
+
+fn html_content(out: &mut String, content: &Content, images: &Images) -> fmt::Result {
+    for content in &content.0 {
+        match content {
+            ContentKind::Text { text } => {
+                writeln!(out, "<p class=\"text\">{}</p>", escape(text))
+            }
+            ContentKind::Image { image, mime_type } => {
+                html_image(out, images.inline(image, mime_type.as_deref()))
+            }
+            ContentKind::Blob { hash, mime_type } => {
+                html_image(out, images.blob(hash, mime_type.as_deref()))
+            }
+            ContentKind::Document { source, mime_type } => writeln!(
+                out,
+                "<p class=\"placeholder\">[{}]</p>",
+                escape(&document(source, mime_type.as_deref()))
+            ),
+            ContentKind::ToolUse { name, input, .. } => writeln!(
+                out,
+                "<p><strong>Tool call</strong> <code>{}</code></p><pre>{}</pre>",
+                escape(name),
+                escape(&format!("{:#}", input))
+            ),
+            ContentKind::ToolResult {
+                content, is_error, ..
+            } => {
+                writeln!(
+                    out,
+                    "<p><strong>{}</strong></p>",
+                    if *is_error {
+                        "Tool error"
+                    } else {
+                        "Tool result"
+                    }
+                )?;
+                html_content(out, content, images)
+            }
+        }?;
+    }
+    Ok(())
+}
@@ -0,0 +1,180 @@
+use std::{collections::HashMap, fmt, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use synx_domain::{
+    content::{Content, ContentKind},
+    import::{ImportedMessage, ImportedThread},
+    message::Role,
+    metadata::Metadata,
+};
+use uuid::Uuid;
+
+use crate::SynxError;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ImportFormat {
+    /// `conversations.json` from a ChatGPT data export.
+    Chatgpt,
+    /// One [`ImportedThread`] per line.
+    Jsonl,
+}
+
+impl fmt::Display for ImportFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ImportFormat::Chatgpt => "chatgpt",
+            ImportFormat::Jsonl => "jsonl",
+        })
+    }
+}
+
+impl FromStr for ImportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "chatgpt" => Ok(ImportFormat::Chatgpt),
+            "jsonl" => Ok(ImportFormat::Jsonl),
+            _ => Err(format!("unknown import format: {}", s)),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct ImportRequest {
+    pub format: ImportFormat,
+    /// Queue a summary of every imported thread once it is written.
+    #[serde(default)]
+    pub summarize: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ImportResponse {
+    pub thread_ids: Vec<Uuid>,
+    pub messages: usize,
+}
+
+pub fn parse(format: ImportFormat, data: &[u8]) -> Result<Vec<ImportedThread>, SynxError> {
+    match format {
+        ImportFormat::Chatgpt => parse_chatgpt(data),
+        ImportFormat::Jsonl => parse_jsonl(data),
+    }
+}
+
+fn parse_jsonl(data: &[u8]) -> Result<Vec<ImportedThread>, SynxError> {
+    let data = std::str::from_utf8(data).map_err(|e| SynxError::InvalidInput(e.to_string()))?;
+    data.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(index, line)| {
+            serde_json::from_str(line)
+                .map_err(|e| SynxError::InvalidInput(format!("line {}: {}", index + 1, e)))
+        })
+        .collect()
+}
+
+#[derive(Deserialize)]
+struct ChatgptConversation {
+    id: Option<String>,
+    title: Option<String>,
+    create_time: Option<f64>,
+    #[serde(default)]
+    mapping: HashMap<String, ChatgptNode>,
+    current_node: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ChatgptNode {
+    message: Option<ChatgptMessage>,
+    parent: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ChatgptMessage {
+    author: ChatgptAuthor,
+    create_time: Option<f64>,
+    content: ChatgptContent,
+}
+
+#[derive(Deserialize)]
+struct ChatgptAuthor {
+    role: String,
+}
+
+#[derive(Deserialize)]
+struct ChatgptContent {
+    #[serde(default)]
+    parts: Vec<Value>,
+    text: Option<String>,
+}
+
+/// Imports the branch each conversation was left on. Parts other than text, such
+/// as pointers to uploaded images, are not part of the export and are dropped, as
+/// are messages left without content.
+fn parse_chatgpt(data: &[u8]) -> Result<Vec<ImportedThread>, SynxError> {
+    let conversations: Vec<ChatgptConversation> =
+        serde_json::from_slice(data).map_err(|e| SynxError::InvalidInput(e.to_string()))?;
+
+    Ok(conversations
+        .into_iter()
+        .map(|conversation| {
+            let mut path = Vec::new();
+            let mut node_id = conversation.current_node.as_ref();
+            while let Some(node) = node_id.and_then(|id| conversation.mapping.get(id)) {
+                // A malformed export could link nodes in a cycle.
+                if path.len() == conversation.mapping.len() {
+                    break;
+                }
+                path.push(node);
+                node_id = node.parent.as_ref();
+            }
+            path.reverse();
+
+            let mut metadata = Metadata::new();
+            if let Some(id) = conversation.id {
+                metadata.insert("chatgpt_conversation_id".to_string(), Value::String(id));
+            }
+
+            ImportedThread {
+                title: conversation.title,
+                metadata,
+                created_at: conversation.create_time.and_then(millis_from_seconds),
+                messages: path
+                    .into_iter()
+                    .filter_map(|node| node.message.as_ref())
+                    .filter_map(|message| {
+                        let content = chatgpt_content(&message.content);
+                        (!content.0.is_empty()).then(|| {
+                            ImportedMessage::new(
+                                Role::from(message.author.role.as_str()),
+                                content,
+                                message.create_time.and_then(millis_from_seconds),
+                            )
+                        })
+                    })
+                    .collect(),
+            }
+        })
+        .collect())
+}
+
+fn chatgpt_content(content: &ChatgptContent) -> Content {
+    Content(
+        content
+            .parts
+            .iter()
+            .filter_map(Value::as_str)
+            .chain(content.text.as_deref())
+            .filter(|text| !text.trim().is_empty())
+            .map(|text| ContentKind::Text {
+                text: text.to_string(),
+            })
+            .collect(),
+    )
+}
+
+fn millis_from_seconds(seconds: f64) -> Option<u64> {
+    (seconds.is_finite() && seconds >= 0.0).then(|| (seconds * 1000.0).round() as u64)
+}
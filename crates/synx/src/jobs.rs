@@ -0,0 +1,253 @@
+use std::time::Duration;
+
+use anyhow::Result;
+use chrono::Utc;
+use ferrochain::futures::FutureExt;
+use synx_database::DatabaseError;
+use synx_domain::{
+    blob::Blob,
+    job::{Job, JobKind, JobStatus},
+    message::{ListMessages, Message, MessageCursor},
+    thread::SummaryCursor,
+};
+use uuid::Uuid;
+
+use crate::{
+    events::Event,
+    utils::{content::extract_text_content, embedding::generate_embeddings},
+    Synx,
+};
+
+const MAX_JOB_ATTEMPTS: u32 = 8;
+const JOB_BACKOFF_BASE_MILLIS: u64 = 1_000;
+const JOB_BACKOFF_MAX_MILLIS: u64 = 5 * 60 * 1_000;
+const BLOB_COLLECTION_INTERVAL: Duration = Duration::from_secs(10 * 60);
+
+pub(crate) fn backoff_millis(attempts: u32) -> u64 {
+    JOB_BACKOFF_BASE_MILLIS
+        .saturating_mul(1 << attempts.min(16))
+        .min(JOB_BACKOFF_MAX_MILLIS)
+}
+
+pub(crate) fn now_millis() -> u64 {
+    Utc::now().timestamp_millis() as u64
+}
+
+fn is_not_found(error: &anyhow::Error) -> bool {
+    matches!(
+        error.downcast_ref::<DatabaseError>(),
+        Some(DatabaseError::NotFound)
+    )
+}
+
+impl Synx {
+    /// Schedules every job and webhook delivery left pending by a previous run.
+    pub async fn resume_jobs(&self) -> Result<()> {
+        let jobs = self.db.get_pending_jobs().await?;
+        tracing::info!("Resuming {} pending jobs", jobs.len());
+
+        for job in jobs {
+            self.schedule_job(job);
+        }
+
+        self.resume_webhook_deliveries().await
+    }
+
+    /// Removes the blobs of every tenant left unreferenced past their grace
+    /// period, now and then periodically.
+    pub fn collect_blobs(&self) {
+        self.executor.spawn({
+            let db = self.db.clone();
+
+            async move {
+                loop {
+                    match db
+                        .delete_unreferenced_blobs(Blob::collection_cutoff())
+                        .await
+                    {
+                        Ok(0) => {}
+                        Ok(removed) => tracing::info!("Removed {} unreferenced blobs", removed),
+                        Err(e) => tracing::error!("Failed to remove unreferenced blobs: {}", e),
+                    }
+                    tokio::time::sleep(BLOB_COLLECTION_INTERVAL).await;
+                }
+            }
+            .boxed()
+        });
+    }
+
+    pub async fn get_thread_jobs(&self, thread_id: Uuid) -> Result<Vec<Job>> {
+        Ok(self.db.get_thread_jobs(self.tenant_id, thread_id).await?)
+    }
+
+    pub(crate) async fn enqueue_job(&self, thread_id: Uuid, kind: JobKind) -> Result<()> {
+        let job = Job::new(self.tenant_id, thread_id, kind);
+        self.db.save_job(&job).await?;
+        self.schedule_job(job);
+        Ok(())
+    }
+
+    fn schedule_job(&self, mut job: Job) {
+        self.executor.spawn({
+            let this = self.for_tenant(job.tenant_id);
+
+            async move {
+                loop {
+                    let delay = job.next_attempt_at.saturating_sub(now_millis());
+                    if delay > 0 {
+                        tokio::time::sleep(Duration::from_millis(delay)).await;
+                    }
+
+                    let error = match this.run_job(&job).await {
+                        Ok(()) => None,
+                        // The thread or message went away, so there is nothing left to do.
+                        Err(e) if is_not_found(&e) => None,
+                        Err(e) => Some(e),
+                    };
+
+                    let Some(error) = error else {
+                        if let Err(e) = this
+                            .db
+                            .delete_job(this.tenant_id, job.thread_id, job.id())
+                            .await
+                        {
+                            if !matches!(e, DatabaseError::NotFound) {
+                                tracing::error!("Failed to delete job {}: {}", job.id(), e);
+                            }
+                        }
+                        return;
+                    };
+
+                    job.attempts += 1;
+                    job.last_error = Some(error.to_string());
+                    if job.attempts >= MAX_JOB_ATTEMPTS {
+                        job.status = JobStatus::Failed;
+                        tracing::error!(
+                            "Job {} failed after {} attempts: {}",
+                            job.id(),
+                            job.attempts,
+                            error
+                        );
+                    } else {
+                        job.next_attempt_at = now_millis() + backoff_millis(job.attempts);
+                        tracing::warn!(
+                            "Job {} failed (attempt {}), retrying: {}",
+                            job.id(),
+                            job.attempts,
+                            error
+                        );
+                    }
+
+                    if let Err(e) = this.db.save_job(&job).await {
+                        tracing::error!("Failed to save job {}: {}", job.id(), e);
+                        return;
+                    }
+
+                    if job.status == JobStatus::Failed {
+                        return;
+                    }
+                }
+            }
+            .boxed()
+        });
+    }
+
+    async fn run_job(&self, job: &Job) -> Result<()> {
+        match job.kind {
+            JobKind::Summarize { message_id } => self.summarize(job.thread_id, message_id).await,
+            JobKind::EmbedMessage { message_id } => {
+                self.embed_message(job.thread_id, message_id).await
+            }
+        }
+    }
+
+    /// Folds every text message up to `message_id` the summary has not seen yet,
+    /// oldest first, saving the summary after each one so a retry picks up where
+    /// this run stopped. A concurrent fold is detected through the summary cursor,
+    /// and the work goes on from the cursor it saved unless that is already past
+    /// `message_id`.
+    async fn summarize(&self, thread_id: Uuid, message_id: Uuid) -> Result<()> {
+        let target = SummaryCursor::from(
+            &self
+                .db
+                .get_message(self.tenant_id, thread_id, message_id)
+                .await?,
+        );
+
+        'fold: loop {
+            let thread = self.db.get_thread(self.tenant_id, thread_id).await?;
+            if thread.summary_cursor.is_some_and(|cursor| cursor >= target) {
+                return Ok(());
+            }
+
+            let query = ListMessages {
+                after: thread.summary_cursor.map(MessageCursor::from),
+                ..ListMessages::default()
+            };
+            let pending: Vec<Message> = self
+                .db
+                .get_thread_messages(self.tenant_id, thread_id, &query)
+                .await?
+                .messages
+                .into_iter()
+                .take_while(|message| SummaryCursor::from(message) <= target)
+                .collect();
+
+            let mut summary = thread.summary.clone().unwrap_or_default();
+            let mut saved_cursor = thread.summary_cursor;
+            for message in &pending {
+                let Some(content) = extract_text_content(&message.content) else {
+                    continue;
+                };
+                summary = self
+                    .generate_summary(summary, message.role.to_string(), content)
+                    .await?;
+                let cursor = SummaryCursor::from(message);
+                let embedding = generate_embeddings(&self.document_embedder, &summary).await?;
+
+                match self
+                    .db
+                    .update_thread_summary_and_embedding(
+                        self.tenant_id,
+                        thread_id,
+                        saved_cursor,
+                        cursor,
+                        summary.clone(),
+                        embedding,
+                    )
+                    .await
+                {
+                    Ok(()) => {
+                        saved_cursor = Some(cursor);
+                        self.emit(Event::SummaryUpdated {
+                            thread_id,
+                            summary: summary.clone(),
+                            cursor,
+                        })
+                        .await;
+                    }
+                    Err(DatabaseError::Conflict(_)) => continue 'fold,
+                    Err(e) => return Err(e.into()),
+                }
+            }
+
+            return Ok(());
+        }
+    }
+
+    async fn embed_message(&self, thread_id: Uuid, message_id: Uuid) -> Result<()> {
+        let message = self
+            .db
+            .get_message(self.tenant_id, thread_id, message_id)
+            .await?;
+        let embedding = match extract_text_content(&message.content) {
+            Some(content) => Some(generate_embeddings(&self.document_embedder, &content).await?),
+            None => None,
+        };
+
+        Ok(self
+            .db
+            .update_message_embedding(self.tenant_id, thread_id, message_id, embedding)
+            .await?)
+    }
+}
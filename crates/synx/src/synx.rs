@@ -1,34 +1,112 @@
+pub mod error;
+pub mod events;
 pub mod executor;
+pub mod export;
+pub mod import;
+mod jobs;
+pub mod tenants;
+pub mod tokenizer;
 mod utils;
+pub mod webhooks;
 
-use std::{collections::HashMap, sync::Arc};
+pub use error::SynxError;
+
+use std::{collections::HashSet, sync::Arc};
 
 use anyhow::Result;
 use ferrochain::{
     completion::Completion,
     document::{Document, StoredDocument},
     embedding::Embedder,
-    futures::FutureExt,
+    futures::Stream,
     vectorstore::Similarity,
 };
 use serde_json::Value;
-use synx_database::Db;
+use synx_database::{DatabaseError, Db};
 use synx_domain::{
-    message::{CreateMessage, Message, ThreadMessagesResponse, UpdateMessage},
-    thread::{Thread, UpdateThread},
+    blob::Blob,
+    content::Content,
+    import::{ImportedMessage, ImportedThread},
+    job::JobKind,
+    message::{
+        CreateMessage, ListMessages, Message, MessageCursor, MessageRevision, Role,
+        ThreadMessagesResponse, UpdateMessage,
+    },
+    metadata::Metadata,
+    tenant::Tenant,
+    thread::{ListThreads, ListThreadsResponse, SortOrder, Thread, UpdateThread},
 };
-use utils::{completion::SUMMARY_PROMPT, similarity::cosine_similarity};
+use utils::completion::SUMMARY_PROMPT;
 use uuid::Uuid;
 
 use crate::{
+    events::{Event, EventBus, Lagged},
     executor::Executor,
+    export::{
+        export,
+        transcript::{self, ArchiveRequest, TranscriptFormat},
+        Blobs, ExportFormat,
+    },
+    import::{ImportFormat, ImportResponse},
+    tokenizer::{ApproximateTokenizer, Tokenizer},
     utils::{content::extract_text_content, embedding::generate_embeddings},
+    webhooks::WebhookResolver,
 };
 
+const DEFAULT_SEARCH_TOP_K: usize = 10;
+/// Threads written per transaction by [`Synx::import`].
+pub const IMPORT_BATCH_SIZE: usize = 100;
+/// Messages read per query when following a branch or reading a whole thread.
+const MESSAGE_PAGE_SIZE: usize = 100;
+
 #[derive(serde::Deserialize, serde::Serialize)]
 pub struct SearchRequest {
     pub query: String,
-    pub thread_ids: Vec<Uuid>,
+    /// Restricts the search to these threads; every indexed thread is searched when omitted.
+    pub thread_ids: Option<Vec<Uuid>>,
+    /// Only threads whose metadata contains all of these key/value pairs are returned.
+    pub metadata: Option<Metadata>,
+    pub top_k: Option<usize>,
+    pub min_score: Option<f32>,
+}
+
+#[derive(serde::Deserialize, serde::Serialize)]
+pub struct MessageSearchRequest {
+    pub query: String,
+    /// Restricts the search to messages of these threads; every thread is searched when omitted.
+    pub thread_ids: Option<Vec<Uuid>>,
+    pub top_k: Option<usize>,
+    pub min_score: Option<f32>,
+}
+
+#[derive(serde::Deserialize, serde::Serialize)]
+pub struct MessageSimilarity {
+    pub thread_id: Uuid,
+    pub message: Message,
+    pub score: f32,
+}
+
+#[derive(serde::Deserialize)]
+pub struct ContextRequest {
+    pub max_tokens: usize,
+}
+
+/// Prompt material for the next completion of a thread: the rolling summary as
+/// a system preamble and the latest messages of the active path, oldest first.
+#[derive(serde::Serialize)]
+pub struct ThreadContext {
+    pub system: Option<String>,
+    pub messages: Vec<ContextMessage>,
+    pub token_count: usize,
+    pub max_tokens: usize,
+    /// Messages of the active path left out to stay within the budget.
+    pub omitted_messages: usize,
+}
+
+#[derive(serde::Serialize)]
+pub struct ContextMessage {
+    pub role: Role,
+    pub content: Content,
 }
 
 #[derive(Clone)]
@@ -38,6 +116,13 @@ pub struct Synx {
     document_embedder: Arc<dyn Embedder>,
     query_embedder: Arc<dyn Embedder>,
     executor: Arc<dyn Executor>,
+    custom_roles: Arc<HashSet<String>>,
+    tokenizer: Arc<dyn Tokenizer>,
+    events: EventBus,
+    http: reqwest::Client,
+    webhook_allowed_hosts: Arc<HashSet<String>>,
+    /// Tenant whose threads and webhooks this handle reads and writes.
+    tenant_id: Uuid,
 }
 
 impl Synx {
@@ -48,94 +133,476 @@ impl Synx {
             document_embedder: None,
             query_embedder: None,
             executor: None,
+            custom_roles: HashSet::new(),
+            tokenizer: None,
+            webhook_allowed_hosts: HashSet::new(),
         }
     }
 
+    /// The same service acting for another tenant. Built handles act for the
+    /// default tenant.
+    pub fn for_tenant(&self, tenant_id: Uuid) -> Synx {
+        Synx {
+            tenant_id,
+            ..self.clone()
+        }
+    }
+
+    pub fn tenant_id(&self) -> Uuid {
+        self.tenant_id
+    }
+
+    /// Changes to the thread from now on, until it is deleted or the subscriber
+    /// falls too far behind.
+    pub fn subscribe(
+        &self,
+        thread_id: Uuid,
+    ) -> impl Stream<Item = Result<Event, Lagged>> + Send + 'static {
+        self.events.subscribe(thread_id)
+    }
+
     pub async fn create_thread(&self) -> Result<Thread> {
-        Ok(self.db.create_thread().await?)
+        Ok(self.db.create_thread(self.tenant_id).await?)
     }
 
-    pub async fn list_threads(&self) -> Result<Vec<Thread>> {
-        Ok(self.db.list_threads().await?)
+    pub async fn list_threads(&self, query: &ListThreads) -> Result<ListThreadsResponse> {
+        Ok(self.db.list_threads(self.tenant_id, query).await?)
     }
 
     pub async fn get_thread(&self, thread_id: Uuid) -> Result<Thread> {
-        Ok(self.db.get_thread(thread_id).await?)
+        Ok(self.db.get_thread(self.tenant_id, thread_id).await?)
     }
 
     pub async fn update_thread(&self, thread_id: Uuid, update: UpdateThread) -> Result<Thread> {
-        Ok(self.db.update_thread(thread_id, update).await?)
+        Ok(self
+            .db
+            .update_thread(self.tenant_id, thread_id, update)
+            .await?)
     }
 
     pub async fn get_messages(
         &self,
         thread_id: Uuid,
-        limit: Option<usize>,
-        offset: Option<usize>,
+        query: &ListMessages,
     ) -> Result<ThreadMessagesResponse> {
         Ok(self
             .db
-            .get_thread_messages(thread_id, limit, offset)
+            .get_thread_messages(self.tenant_id, thread_id, query)
             .await?)
     }
 
     pub async fn create_message(&self, thread_id: Uuid, input: CreateMessage) -> Result<Message> {
-        let message = self.db.create_message(thread_id, input).await?;
+        self.check_role(&input.role)?;
+        self.store_message(thread_id, input).await
+    }
+
+    fn check_role(&self, role: &Role) -> Result<(), SynxError> {
+        match role {
+            Role::Custom(name) if !self.custom_roles.contains(name) => {
+                Err(SynxError::InvalidInput(format!("unknown role: {:?}", name)))
+            }
+            _ => Ok(()),
+        }
+    }
 
-        self.process_new_message(thread_id, message.clone());
+    /// Loads conversations exported in `format`, keeping their timestamps. Input
+    /// is checked in full before anything is written, then threads are written
+    /// in batches of [`IMPORT_BATCH_SIZE`], each in one transaction. Imported
+    /// messages are not embedded; with `summarize`, a summary of each thread of a
+    /// batch is queued once the batch is written. The summary job folds one
+    /// message at a time and saves its progress after each, so long threads are
+    /// summarized incrementally and an interrupted job resumes where it stopped.
+    pub async fn import(
+        &self,
+        format: ImportFormat,
+        data: &[u8],
+        summarize: bool,
+    ) -> Result<ImportResponse> {
+        let imported = import::parse(format, data)?;
+        for message in imported.iter().flat_map(|thread| &thread.messages) {
+            self.check_role(&message.role)?;
+        }
+
+        let mut response = ImportResponse {
+            thread_ids: Vec::with_capacity(imported.len()),
+            messages: 0,
+        };
+        let mut imported = imported.into_iter().peekable();
+        while imported.peek().is_some() {
+            let batch: Vec<(Thread, Vec<Message>)> = imported
+                .by_ref()
+                .take(IMPORT_BATCH_SIZE)
+                .map(|thread| thread.into_records(self.tenant_id))
+                .collect();
+            let mut latest_messages = Vec::with_capacity(batch.len());
+            for (thread, messages) in &batch {
+                response.thread_ids.push(thread.id());
+                response.messages += messages.len();
+                if let Some(latest) = messages.last() {
+                    latest_messages.push((thread.id(), latest.id()));
+                }
+            }
+            self.db.import_threads(batch).await?;
+
+            if summarize {
+                for (thread_id, message_id) in latest_messages {
+                    if let Err(e) = self
+                        .enqueue_job(thread_id, JobKind::Summarize { message_id })
+                        .await
+                    {
+                        tracing::error!("Failed to enqueue summary of thread {}: {}", thread_id, e);
+                    }
+                }
+            }
+        }
+
+        Ok(response)
+    }
+
+    /// Stores the message, announces it and schedules its embedding and the
+    /// summary update.
+    async fn store_message(&self, thread_id: Uuid, input: CreateMessage) -> Result<Message> {
+        let message = self
+            .db
+            .create_message(self.tenant_id, thread_id, input)
+            .await?;
+        self.emit(Event::MessageCreated {
+            thread_id,
+            message: message.clone(),
+        })
+        .await;
+
+        for kind in [
+            JobKind::EmbedMessage {
+                message_id: message.id(),
+            },
+            JobKind::Summarize {
+                message_id: message.id(),
+            },
+        ] {
+            if let Err(e) = self.enqueue_job(thread_id, kind).await {
+                tracing::error!("Failed to enqueue job for message {}: {}", message.id(), e);
+            }
+        }
 
         Ok(message)
     }
 
-    fn process_new_message(&self, thread_id: Uuid, message: Message) {
-        self.executor.spawn({
-            let this = self.clone();
+    /// Messages of the branch through `message_id`, or of the active path.
+    pub async fn get_branch(
+        &self,
+        thread_id: Uuid,
+        message_id: Option<Uuid>,
+    ) -> Result<Vec<Message>> {
+        let Some(message_id) = message_id else {
+            return self.active_path(thread_id).await;
+        };
+        let message = self
+            .db
+            .get_message(self.tenant_id, thread_id, message_id)
+            .await?;
+        let descendants = self.latest_descendants(thread_id, &message).await?;
+        let mut branch = self.ancestors(thread_id, message).await?;
+        branch.extend(descendants);
+        Ok(branch)
+    }
+
+    /// Packs the summary and as many of the most recent messages of the active
+    /// path as fit in `max_tokens`. The summary comes first and is left out only
+    /// when it does not fit on its own; messages are added newest first until the
+    /// next one would overflow. Only text counts towards the budget.
+    pub async fn build_context(&self, thread_id: Uuid, max_tokens: usize) -> Result<ThreadContext> {
+        let thread = self.db.get_thread(self.tenant_id, thread_id).await?;
+        let path = self.active_path(thread_id).await?;
+
+        let mut token_count = 0;
+        let system = thread
+            .summary
+            .filter(|summary| !summary.is_empty())
+            .map(|summary| format!("Summary of the conversation so far:\n\n{}", summary))
+            .filter(|preamble| {
+                let tokens = self.tokenizer.count_tokens(preamble);
+                let fits = tokens <= max_tokens;
+                if fits {
+                    token_count += tokens;
+                }
+                fits
+            });
+
+        let mut messages = Vec::new();
+        for message in path.iter().rev() {
+            let tokens = self.tokenizer.count_tokens(message.role.as_str())
+                + extract_text_content(&message.content)
+                    .map_or(0, |text| self.tokenizer.count_tokens(&text));
+            if token_count + tokens > max_tokens {
+                break;
+            }
+            token_count += tokens;
+            messages.push(ContextMessage {
+                role: message.role.clone(),
+                content: message.content.clone(),
+            });
+        }
+        messages.reverse();
+
+        Ok(ThreadContext {
+            system,
+            omitted_messages: path.len() - messages.len(),
+            messages,
+            token_count,
+            max_tokens,
+        })
+    }
 
-            async move {
-                if let Some(completion_content) = extract_text_content(&message.content) {
-                    let thread = match this.db.get_thread(thread_id).await {
-                        Ok(response) => response,
-                        Err(e) => {
-                            tracing::error!("Failed to fetch thread messages: {}", e);
-                            return;
-                        }
-                    };
-
-                    let summary = match this
-                        .generate_summary(
-                            thread.summary.unwrap_or_default(),
-                            message.role,
-                            completion_content,
-                        )
-                        .await
+    /// The active path in the request shape of `format`, with blob attachments
+    /// inlined as base64.
+    pub async fn export_thread(&self, thread_id: Uuid, format: ExportFormat) -> Result<Value> {
+        self.db.get_thread(self.tenant_id, thread_id).await?;
+        let path = self.active_path(thread_id).await?;
+
+        let blobs = self.load_blobs(&path).await?;
+        Ok(export(format, &path, &blobs))
+    }
+
+    /// Every message of the thread, oldest first, as a readable transcript.
+    pub async fn thread_transcript(
+        &self,
+        thread_id: Uuid,
+        format: TranscriptFormat,
+        embed_images: bool,
+    ) -> Result<String> {
+        let thread = self.db.get_thread(self.tenant_id, thread_id).await?;
+        let messages = self.all_messages(thread_id).await?;
+        let blobs = self.load_blobs(&messages).await?;
+
+        Ok(transcript::render(
+            format,
+            &thread,
+            &messages,
+            embed_images,
+            &blobs,
+        ))
+    }
+
+    /// Transcripts of the requested threads, or of every thread, in one archive.
+    pub async fn transcript_archive(&self, request: &ArchiveRequest) -> Result<Vec<u8>> {
+        let thread_ids = match &request.thread_ids {
+            Some(thread_ids) => thread_ids.clone(),
+            None => self
+                .db
+                .list_threads(self.tenant_id, &ListThreads::default())
+                .await?
+                .threads
+                .iter()
+                .map(Thread::id)
+                .collect(),
+        };
+
+        let mut transcripts = Vec::with_capacity(thread_ids.len());
+        for thread_id in thread_ids {
+            let transcript = self
+                .thread_transcript(thread_id, request.format, request.embed_images)
+                .await?;
+            transcripts.push((thread_id, transcript));
+        }
+
+        Ok(transcript::archive(
+            request.archive,
+            request.format.extension(),
+            transcripts,
+        )?)
+    }
+
+    /// The blobs the messages refer to, skipping any that no longer exist.
+    async fn load_blobs(&self, messages: &[Message]) -> Result<Blobs> {
+        let mut blobs = Blobs::new();
+        for message in messages {
+            for hash in message.content.blob_hashes() {
+                if blobs.contains_key(hash) {
+                    continue;
+                }
+                match self.db.get_blob(self.tenant_id, hash).await {
+                    Ok(blob) => {
+                        blobs.insert(hash.to_string(), blob);
+                    }
+                    Err(DatabaseError::NotFound) => {}
+                    Err(e) => return Err(e.into()),
+                }
+            }
+        }
+        Ok(blobs)
+    }
+
+    /// Copies the conversation leading to `at` into a new thread, written at once
+    /// with the copies in their original order under new times. The new thread
+    /// gets its own summary; the copies are not embedded.
+    pub async fn fork_thread(&self, thread_id: Uuid, at: Uuid) -> Result<Thread> {
+        let source = self.db.get_thread(self.tenant_id, thread_id).await?;
+        let at = self.db.get_message(self.tenant_id, thread_id, at).await?;
+        let history = self.ancestors(thread_id, at).await?;
+
+        let (fork, messages) = ImportedThread {
+            title: source.title,
+            metadata: source.metadata,
+            created_at: None,
+            messages: history
+                .into_iter()
+                .map(|message| ImportedMessage {
+                    role: message.role,
+                    content: message.content,
+                    metadata: message.metadata,
+                    created_at: None,
+                })
+                .collect(),
+        }
+        .into_records(self.tenant_id);
+        let latest = messages.last().map(Message::id);
+        self.db
+            .import_threads(vec![(fork.clone(), messages)])
+            .await?;
+
+        if let Some(message_id) = latest {
+            if let Err(e) = self
+                .enqueue_job(fork.id(), JobKind::Summarize { message_id })
+                .await
+            {
+                tracing::error!("Failed to enqueue summary of thread {}: {}", fork.id(), e);
+            }
+        }
+
+        Ok(self.db.get_thread(self.tenant_id, fork.id()).await?)
+    }
+
+    /// Every message of the thread, oldest first, read a page at a time.
+    async fn all_messages(&self, thread_id: Uuid) -> Result<Vec<Message>> {
+        let mut query = ListMessages::page(Some(MESSAGE_PAGE_SIZE), None);
+        let mut messages = Vec::new();
+        loop {
+            let page = self
+                .db
+                .get_thread_messages(self.tenant_id, thread_id, &query)
+                .await?;
+            messages.extend(page.messages);
+            match page.next_cursor {
+                Some(cursor) => query.after = Some(cursor),
+                None => return Ok(messages),
+            }
+        }
+    }
+
+    /// The latest message before `before`, or the latest of the thread.
+    async fn message_before(
+        &self,
+        thread_id: Uuid,
+        before: Option<MessageCursor>,
+    ) -> Result<Option<Message>> {
+        let query = ListMessages {
+            before,
+            order: SortOrder::Desc,
+            limit: Some(1),
+            ..ListMessages::default()
+        };
+        Ok(self
+            .db
+            .get_thread_messages(self.tenant_id, thread_id, &query)
+            .await?
+            .messages
+            .pop())
+    }
+
+    /// The parent of `message` as [`MessageTree`](synx_domain::branch::MessageTree)
+    /// defines it: its `parent_id` while that message is still in the thread and
+    /// older, otherwise the message created just before it.
+    async fn parent(&self, thread_id: Uuid, message: &Message) -> Result<Option<Message>> {
+        if let Some(parent_id) = message.parent_id {
+            match self
+                .db
+                .get_message(self.tenant_id, thread_id, parent_id)
+                .await
+            {
+                Ok(parent) if parent.cursor() < message.cursor() => return Ok(Some(parent)),
+                Ok(_) | Err(DatabaseError::NotFound) => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+        self.message_before(thread_id, Some(message.cursor())).await
+    }
+
+    /// The conversation from the root down to `message`, following parents one
+    /// message at a time.
+    async fn ancestors(&self, thread_id: Uuid, message: Message) -> Result<Vec<Message>> {
+        let mut path = Vec::new();
+        let mut current = Some(message);
+        while let Some(message) = current {
+            current = self.parent(thread_id, &message).await?;
+            path.push(message);
+        }
+        path.reverse();
+        Ok(path)
+    }
+
+    /// The branch ending at the latest message.
+    async fn active_path(&self, thread_id: Uuid) -> Result<Vec<Message>> {
+        match self.message_before(thread_id, None).await? {
+            Some(latest) => self.ancestors(thread_id, latest).await,
+            None => Ok(Vec::new()),
+        }
+    }
+
+    /// The most recent descendants of `message` down to a leaf, found by reading
+    /// the messages after it a page at a time. Messages come oldest first, so a
+    /// child of a message on the chain replaces whatever followed that message.
+    async fn latest_descendants(&self, thread_id: Uuid, message: &Message) -> Result<Vec<Message>> {
+        let mut chain = vec![message.clone()];
+        let mut seen = HashSet::new();
+        let mut previous = message.id();
+        let mut query = ListMessages {
+            after: Some(message.cursor()),
+            limit: Some(MESSAGE_PAGE_SIZE),
+            ..ListMessages::default()
+        };
+        loop {
+            let page = self
+                .db
+                .get_thread_messages(self.tenant_id, thread_id, &query)
+                .await?;
+            for next in page.messages {
+                let parent_id = match next.parent_id {
+                    Some(parent_id)
+                        if seen.contains(&parent_id)
+                            || chain.iter().any(|message| message.id() == parent_id) =>
                     {
-                        Ok(s) => s,
-                        Err(e) => {
-                            tracing::error!("Failed to generate summary: {}", e);
-                            return;
+                        parent_id
+                    }
+                    Some(parent_id) => {
+                        match self
+                            .db
+                            .get_message(self.tenant_id, thread_id, parent_id)
+                            .await
+                        {
+                            Ok(parent) if parent.cursor() < message.cursor() => parent_id,
+                            Ok(_) | Err(DatabaseError::NotFound) => previous,
+                            Err(e) => return Err(e.into()),
                         }
-                    };
-
-                    let embedding =
-                        match generate_embeddings(&this.document_embedder, &summary).await {
-                            Ok(e) => e,
-                            Err(e) => {
-                                tracing::error!("Failed to create embedding: {}", e);
-                                return;
-                            }
-                        };
-
-                    if let Err(e) = this
-                        .db
-                        .update_thread_summary_and_embedding(thread_id, summary, embedding)
-                        .await
-                    {
-                        tracing::error!("Failed to update thread summary and embedding: {}", e);
                     }
+                    None => previous,
+                };
+
+                let id = next.id();
+                if let Some(position) = chain.iter().position(|message| message.id() == parent_id) {
+                    chain.truncate(position + 1);
+                    chain.push(next);
                 }
+                seen.insert(id);
+                previous = id;
             }
-            .boxed()
-        });
+            match page.next_cursor {
+                Some(cursor) => query.after = Some(cursor),
+                None => break,
+            }
+        }
+        chain.remove(0);
+        Ok(chain)
     }
 
     async fn generate_summary(
@@ -164,7 +631,7 @@ impl Synx {
 
         let mut summary = String::new();
         while let Some(event) = stream.next().await {
-            match event? {
+            match event.map_err(|e| SynxError::Summarization(e.to_string()))? {
                 StreamEvent::Start { content, .. } | StreamEvent::Delta { content, .. } => {
                     match content {
                         Content::Text { text } => summary.push_str(&text),
@@ -184,18 +651,74 @@ impl Synx {
         message_id: Uuid,
         content: UpdateMessage,
     ) -> Result<Message> {
+        let message = self
+            .db
+            .update_message(self.tenant_id, thread_id, message_id, content)
+            .await?;
+        self.emit(Event::MessageUpdated {
+            thread_id,
+            message: message.clone(),
+        })
+        .await;
+
+        if let Err(e) = self
+            .enqueue_job(thread_id, JobKind::EmbedMessage { message_id })
+            .await
+        {
+            tracing::error!("Failed to enqueue job for message {}: {}", message_id, e);
+        }
+
+        Ok(message)
+    }
+
+    pub async fn list_message_revisions(
+        &self,
+        thread_id: Uuid,
+        message_id: Uuid,
+    ) -> Result<Vec<MessageRevision>> {
+        Ok(self
+            .db
+            .list_message_revisions(self.tenant_id, thread_id, message_id)
+            .await?)
+    }
+
+    pub async fn get_message_revision(
+        &self,
+        thread_id: Uuid,
+        message_id: Uuid,
+        revision: u32,
+    ) -> Result<MessageRevision> {
         Ok(self
             .db
-            .update_message(thread_id, message_id, content)
+            .get_message_revision(self.tenant_id, thread_id, message_id, revision)
             .await?)
     }
 
     pub async fn delete_message(&self, thread_id: Uuid, message_id: Uuid) -> Result<()> {
-        Ok(self.db.delete_message(thread_id, message_id).await?)
+        self.db
+            .delete_message(self.tenant_id, thread_id, message_id)
+            .await?;
+        self.emit(Event::MessageDeleted {
+            thread_id,
+            message_id,
+        })
+        .await;
+        Ok(())
+    }
+
+    pub async fn put_blob(&self, data: Vec<u8>, mime_type: Option<String>) -> Result<Blob> {
+        let blob = Blob::new(&data, mime_type);
+        Ok(self.db.put_blob(self.tenant_id, blob, data).await?)
+    }
+
+    pub async fn get_blob(&self, hash: &str) -> Result<(Blob, Vec<u8>)> {
+        Ok(self.db.get_blob(self.tenant_id, hash).await?)
     }
 
     pub async fn delete_thread(&self, thread_id: Uuid) -> Result<()> {
-        Ok(self.db.delete_thread(thread_id).await?)
+        self.db.delete_thread(self.tenant_id, thread_id).await?;
+        self.emit(Event::ThreadDeleted { thread_id }).await;
+        Ok(())
     }
 
     pub async fn debug_state(&self) -> Result<Value> {
@@ -203,36 +726,69 @@ impl Synx {
     }
 
     pub async fn search_threads(&self, search_request: SearchRequest) -> Result<Vec<Similarity>> {
+        let query_embedding =
+            generate_embeddings(&self.query_embedder, &search_request.query).await?;
+
+        let top_k = search_request.top_k.unwrap_or_else(|| {
+            search_request
+                .thread_ids
+                .as_ref()
+                .map_or(DEFAULT_SEARCH_TOP_K, Vec::len)
+        });
+
         let threads = self
             .db
-            .get_threads_with_embeddings(&search_request.thread_ids)
+            .search_thread_embeddings(
+                self.tenant_id,
+                &query_embedding,
+                search_request.thread_ids.as_deref(),
+                &search_request.metadata.unwrap_or_default(),
+                top_k,
+            )
             .await?;
 
+        Ok(threads
+            .into_iter()
+            .filter(|(_, score)| search_request.min_score.is_none_or(|min| *score >= min))
+            .map(|(thread, score)| Similarity {
+                stored: StoredDocument {
+                    id: thread.id.to_string(),
+                    document: Document {
+                        content: thread.summary.unwrap_or_default(),
+                        metadata: thread.metadata.into_iter().collect(),
+                    },
+                },
+                score,
+            })
+            .collect())
+    }
+
+    pub async fn search_messages(
+        &self,
+        search_request: MessageSearchRequest,
+    ) -> Result<Vec<MessageSimilarity>> {
         let query_embedding =
             generate_embeddings(&self.query_embedder, &search_request.query).await?;
 
-        let mut similarities: Vec<Similarity> = threads
+        let messages = self
+            .db
+            .search_message_embeddings(
+                self.tenant_id,
+                &query_embedding,
+                search_request.thread_ids.as_deref(),
+                search_request.top_k.unwrap_or(DEFAULT_SEARCH_TOP_K),
+            )
+            .await?;
+
+        Ok(messages
             .into_iter()
-            .filter_map(|thread| {
-                thread.embedding.map(|embedding| {
-                    let score = cosine_similarity(&query_embedding, &embedding);
-                    Similarity {
-                        stored: StoredDocument {
-                            id: thread.id.to_string(),
-                            document: Document {
-                                content: thread.summary.unwrap_or_default(),
-                                metadata: HashMap::new(),
-                            },
-                        },
-                        score,
-                    }
-                })
+            .filter(|(_, score)| search_request.min_score.is_none_or(|min| *score >= min))
+            .map(|(message, score)| MessageSimilarity {
+                thread_id: message.thread_id,
+                message,
+                score,
             })
-            .collect();
-
-        similarities.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap());
-
-        Ok(similarities)
+            .collect())
     }
 }
 
@@ -242,6 +798,9 @@ pub struct SynxBuilder {
     document_embedder: Option<Arc<dyn Embedder>>,
     query_embedder: Option<Arc<dyn Embedder>>,
     executor: Option<Arc<dyn Executor>>,
+    custom_roles: HashSet<String>,
+    tokenizer: Option<Arc<dyn Tokenizer>>,
+    webhook_allowed_hosts: HashSet<String>,
 }
 
 impl SynxBuilder {
@@ -270,7 +829,27 @@ impl SynxBuilder {
         self
     }
 
+    /// Counts tokens for context assembly; defaults to [`ApproximateTokenizer`].
+    pub fn with_tokenizer(mut self, tokenizer: Arc<dyn Tokenizer>) -> Self {
+        self.tokenizer = Some(tokenizer);
+        self
+    }
+
+    /// Role names accepted besides user, assistant, system and tool.
+    pub fn with_custom_roles(mut self, roles: impl IntoIterator<Item = String>) -> Self {
+        self.custom_roles.extend(roles);
+        self
+    }
+
+    /// Hosts webhooks may reach even on a private, loopback or link-local
+    /// address, such as services running next to the server.
+    pub fn with_webhook_allowed_hosts(mut self, hosts: impl IntoIterator<Item = String>) -> Self {
+        self.webhook_allowed_hosts.extend(hosts);
+        self
+    }
+
     pub fn build(self) -> Synx {
+        let webhook_allowed_hosts = Arc::new(self.webhook_allowed_hosts);
         Synx {
             db: self.db.expect("db is required"),
             summarizer: self.summarizer.expect("completion is required"),
@@ -279,6 +858,21 @@ impl SynxBuilder {
                 .expect("document_embedder is required"),
             query_embedder: self.query_embedder.expect("query_embedder is required"),
             executor: self.executor.expect("executor is required"),
+            custom_roles: Arc::new(self.custom_roles),
+            tokenizer: self
+                .tokenizer
+                .unwrap_or_else(|| Arc::new(ApproximateTokenizer)),
+            events: EventBus::new(),
+            http: reqwest::Client::builder()
+                .dns_resolver(Arc::new(WebhookResolver {
+                    allowed_hosts: webhook_allowed_hosts.clone(),
+                }))
+                // A redirect could lead to an address the resolver never sees.
+                .redirect(reqwest::redirect::Policy::none())
+                .build()
+                .expect("HTTP client can be built"),
+            webhook_allowed_hosts,
+            tenant_id: Tenant::DEFAULT_ID,
         }
     }
 }
@@ -0,0 +1,95 @@
+use anyhow::Result;
+use synx_database::{DatabaseError, Db};
+use synx_domain::{
+    api_key::{ApiKey, CreateApiKey, IssuedApiKey},
+    tenant::{CreateTenant, Tenant},
+};
+use uuid::Uuid;
+
+use crate::{Synx, SynxError};
+
+/// [`Synx::create_api_key`] on the database alone, for tools that manage keys
+/// without serving.
+pub async fn issue_api_key(
+    db: &dyn Db,
+    tenant_id: Uuid,
+    input: CreateApiKey,
+) -> Result<IssuedApiKey> {
+    if input.scopes.is_empty() {
+        return Err(
+            SynxError::InvalidInput("an API key needs at least one scope".to_string()).into(),
+        );
+    }
+    let (api_key, key) = ApiKey::generate(tenant_id, input.name, input.scopes);
+    db.save_api_key(&api_key).await?;
+    Ok(IssuedApiKey {
+        api_key: api_key.redacted(),
+        key,
+    })
+}
+
+impl Synx {
+    pub async fn create_tenant(&self, input: CreateTenant) -> Result<Tenant> {
+        if input.name.trim().is_empty() {
+            return Err(
+                SynxError::InvalidInput("tenant name must not be empty".to_string()).into(),
+            );
+        }
+        let tenant = Tenant::new(input.name);
+        self.db.save_tenant(&tenant).await?;
+        Ok(tenant)
+    }
+
+    pub async fn list_tenants(&self) -> Result<Vec<Tenant>> {
+        Ok(self.db.list_tenants().await?)
+    }
+
+    pub async fn get_tenant(&self, tenant_id: Uuid) -> Result<Tenant> {
+        Ok(self.db.get_tenant(tenant_id).await?)
+    }
+
+    /// Removes a tenant that no longer owns threads, with its keys and webhooks.
+    /// The default tenant cannot be removed.
+    pub async fn delete_tenant(&self, tenant_id: Uuid) -> Result<()> {
+        if tenant_id == Tenant::DEFAULT_ID {
+            return Err(SynxError::InvalidInput(
+                "the default tenant cannot be deleted".to_string(),
+            )
+            .into());
+        }
+        Ok(self.db.delete_tenant(tenant_id).await?)
+    }
+
+    /// Issues a key acting for the tenant with the given scopes. The key itself
+    /// is only returned here.
+    pub async fn create_api_key(
+        &self,
+        tenant_id: Uuid,
+        input: CreateApiKey,
+    ) -> Result<IssuedApiKey> {
+        issue_api_key(self.db.as_ref(), tenant_id, input).await
+    }
+
+    pub async fn list_api_keys(&self, tenant_id: Uuid) -> Result<Vec<ApiKey>> {
+        Ok(self
+            .db
+            .list_api_keys(tenant_id)
+            .await?
+            .into_iter()
+            .map(ApiKey::redacted)
+            .collect())
+    }
+
+    pub async fn delete_api_key(&self, tenant_id: Uuid, key_id: Uuid) -> Result<()> {
+        Ok(self.db.delete_api_key(tenant_id, key_id).await?)
+    }
+
+    /// The stored key matching `key`, if any.
+    pub async fn authenticate(&self, key: &str) -> Result<Option<ApiKey>> {
+        match self.db.get_api_key_by_hash(&ApiKey::hash(key)).await {
+            Ok(api_key) => Ok(Some(api_key)),
+            Err(DatabaseError::NotFound) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
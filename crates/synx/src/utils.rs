@@ -1,5 +1,3 @@
 pub mod completion;
 pub mod content;
 pub mod embedding;
-
-pub mod similarity;
@@ -1,17 +1,10 @@
-use synx_domain::content::{Content, ContentKind};
+use synx_domain::content::{Content, ContentKind, DocumentSource};
 
+/// Text a summary or embedding can be built from. Tool calls, tool results and
+/// documents are described in a line of their own; images, inline or
+/// attached, are left out.
 pub fn extract_text_content(content: &Content) -> Option<String> {
-    let text_contents: Vec<String> = content
-        .0
-        .iter()
-        .filter_map(|c| {
-            if let ContentKind::Text { text } = c {
-                Some(text.clone())
-            } else {
-                None
-            }
-        })
-        .collect();
+    let text_contents: Vec<String> = content.0.iter().filter_map(render).collect();
 
     if text_contents.is_empty() {
         None
@@ -19,3 +12,38 @@ pub fn extract_text_content(content: &Content) -> Option<String> {
         Some(text_contents.join("\n"))
     }
 }
+
+fn render(content: &ContentKind) -> Option<String> {
+    match content {
+        ContentKind::Text { text } => Some(text.clone()),
+        ContentKind::Image { .. } => None,
+        ContentKind::ToolUse { name, input, .. } => {
+            Some(format!("[called tool {} with {}]", name, input))
+        }
+        ContentKind::ToolResult {
+            content, is_error, ..
+        } => {
+            let outcome = if *is_error {
+                "tool error"
+            } else {
+                "tool result"
+            };
+            Some(match extract_text_content(content) {
+                Some(text) => format!("[{}]\n{}", outcome, text),
+                None => format!("[{}]", outcome),
+            })
+        }
+        ContentKind::Document { source, mime_type } => {
+            let kind = mime_type.as_deref().unwrap_or("unknown type");
+            Some(match source {
+                DocumentSource::Url { url } => format!("[document ({}) at {}]", kind, url),
+                DocumentSource::Base64 { .. } => format!("[document ({})]", kind),
+            })
+        }
+        ContentKind::Blob { mime_type, .. } => match mime_type.as_deref() {
+            Some(mime_type) if mime_type.starts_with("image/") => None,
+            Some(mime_type) => Some(format!("[attachment ({})]", mime_type)),
+            None => Some("[attachment]".to_string()),
+        },
+    }
+}
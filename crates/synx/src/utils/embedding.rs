@@ -3,17 +3,21 @@ use std::sync::Arc;
 use ferrochain::embedding::Embedder;
 use synx_domain::embedding::Embedding;
 
+use crate::error::SynxError;
+
 pub async fn generate_embeddings(
     embedder: &Arc<dyn Embedder>,
     content: &str,
-) -> Result<Embedding, anyhow::Error> {
+) -> Result<Embedding, SynxError> {
     let embeddings = embedder
         .embed(vec![content.to_owned()])
         .await
-        .map_err(|e| anyhow::anyhow!("Failed to create embedding: {}", e))?;
+        .map_err(|e| SynxError::Embedding(e.to_string()))?;
 
     let Some(embedding) = embeddings.first() else {
-        return Err(anyhow::anyhow!("No embedding generated for content"));
+        return Err(SynxError::Embedding(
+            "No embedding generated for content".to_string(),
+        ));
     };
 
     Ok(embedding.to_owned())
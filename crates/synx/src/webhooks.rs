@@ -0,0 +1,485 @@
+use std::{
+    collections::{BTreeMap, HashSet},
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    sync::Arc,
+    time::Duration,
+};
+
+use anyhow::Result;
+use chrono::Utc;
+use ferrochain::futures::FutureExt;
+use hmac::{Hmac, Mac};
+use reqwest::{
+    dns::{Addrs, Name, Resolve, Resolving},
+    Url,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::Sha256;
+use synx_database::DatabaseError;
+use synx_domain::webhook::{CreateWebhook, DeliveryStatus, Webhook, WebhookDelivery};
+use uuid::Uuid;
+
+use crate::{
+    events::{Event, EVENT_TYPES},
+    jobs::{backoff_millis, now_millis},
+    Synx, SynxError,
+};
+
+const MAX_DELIVERY_ATTEMPTS: u32 = 8;
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+/// Sent by the test endpoint, whatever the webhook's filters.
+const TEST_EVENT: &str = "webhook.test";
+
+pub const DELIVERY_HEADER: &str = "X-Synx-Delivery";
+pub const EVENT_HEADER: &str = "X-Synx-Event";
+pub const TIMESTAMP_HEADER: &str = "X-Synx-Timestamp";
+pub const SIGNATURE_HEADER: &str = "X-Synx-Signature";
+
+#[derive(Deserialize)]
+pub struct TestWebhookRequest {
+    /// Build and sign the request without sending it.
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+/// A request as posted to a webhook.
+#[derive(Debug, Serialize)]
+pub struct SignedRequest {
+    pub url: String,
+    pub headers: BTreeMap<String, String>,
+    pub body: String,
+}
+
+/// The outcome of a test, without the response status or the transport error,
+/// so the endpoint cannot be used to probe what a URL answers.
+#[derive(Debug, Serialize)]
+pub struct WebhookTest {
+    pub request: SignedRequest,
+    /// Whether the request was sent and answered with a success status.
+    pub delivered: bool,
+    pub error: Option<String>,
+}
+
+/// `sha256=` and the hex HMAC-SHA256 of `{timestamp}.{body}` keyed by the secret.
+pub fn sign(secret: &str, timestamp: i64, body: &str) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any size");
+    mac.update(format!("{}.{}", timestamp, body).as_bytes());
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+fn signed_request(
+    webhook: &Webhook,
+    delivery_id: Uuid,
+    event: &str,
+    payload: &Value,
+) -> SignedRequest {
+    let body = payload.to_string();
+    let timestamp = Utc::now().timestamp();
+
+    let mut headers = BTreeMap::from([
+        ("Content-Type".to_string(), "application/json".to_string()),
+        (DELIVERY_HEADER.to_string(), delivery_id.to_string()),
+        (EVENT_HEADER.to_string(), event.to_string()),
+        (TIMESTAMP_HEADER.to_string(), timestamp.to_string()),
+    ]);
+    if let Some(secret) = &webhook.secret {
+        headers.insert(SIGNATURE_HEADER.to_string(), sign(secret, timestamp, &body));
+    }
+
+    SignedRequest {
+        url: webhook.url.clone(),
+        headers,
+        body,
+    }
+}
+
+fn payload(event: &str, data: Value) -> Value {
+    json!({
+        "type": event,
+        "created_at": now_millis(),
+        "data": data,
+    })
+}
+
+/// Whether the address is reachable from anywhere, rather than only from the
+/// server's own host or network.
+fn is_public(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => is_public_v4(ip),
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => is_public_v4(ip),
+            None => is_public_v6(ip),
+        },
+    }
+}
+
+fn is_public_v4(ip: Ipv4Addr) -> bool {
+    let [first, second, ..] = ip.octets();
+    !(ip.is_unspecified()
+        || ip.is_loopback()
+        || ip.is_private()
+        || ip.is_link_local()
+        || ip.is_broadcast()
+        || ip.is_multicast()
+        || ip.is_documentation()
+        // 0.0.0.0/8, shared address space 100.64.0.0/10 and reserved 240.0.0.0/4.
+        || first == 0
+        || (first == 100 && second & 0xc0 == 64)
+        || first >= 240)
+}
+
+fn is_public_v6(ip: Ipv6Addr) -> bool {
+    let first = ip.segments()[0];
+    !(ip.is_unspecified()
+        || ip.is_loopback()
+        || ip.is_multicast()
+        // Unique local fc00::/7 and link-local fe80::/10.
+        || first & 0xfe00 == 0xfc00
+        || first & 0xffc0 == 0xfe80)
+}
+
+/// The address of a URL host written as one, IPv6 ones in brackets.
+fn literal_address(host: &str) -> Option<IpAddr> {
+    host.strip_prefix('[')
+        .and_then(|host| host.strip_suffix(']'))
+        .unwrap_or(host)
+        .parse()
+        .ok()
+}
+
+/// Resolves webhook hosts to their public addresses only, so that deliveries
+/// cannot reach the server's own network; hosts the operator allowed resolve
+/// as usual.
+pub(crate) struct WebhookResolver {
+    pub(crate) allowed_hosts: Arc<HashSet<String>>,
+}
+
+impl Resolve for WebhookResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let host = name.as_str().to_string();
+        let allowed = self.allowed_hosts.contains(&host);
+        Box::pin(async move {
+            let addrs: Vec<SocketAddr> = tokio::net::lookup_host((host.as_str(), 0))
+                .await?
+                .filter(|addr| allowed || is_public(addr.ip()))
+                .collect();
+            if addrs.is_empty() {
+                return Err(format!("{} has no public address", host).into());
+            }
+            Ok(Box::new(addrs.into_iter()) as Addrs)
+        })
+    }
+}
+
+fn check_webhook(webhook: &CreateWebhook) -> Result<Url, SynxError> {
+    let url = match Url::parse(&webhook.url) {
+        Ok(url) if matches!(url.scheme(), "http" | "https") && url.host().is_some() => url,
+        _ => {
+            return Err(SynxError::InvalidInput(format!(
+                "invalid webhook url: {:?}",
+                webhook.url
+            )))
+        }
+    };
+    if let Some(event) = webhook
+        .events
+        .iter()
+        .find(|event| !EVENT_TYPES.contains(&event.as_str()))
+    {
+        return Err(SynxError::InvalidInput(format!(
+            "unknown event type: {:?}",
+            event
+        )));
+    }
+    if webhook.secret.as_deref().is_some_and(str::is_empty) {
+        return Err(SynxError::InvalidInput(
+            "webhook secret must not be empty".to_string(),
+        ));
+    }
+    Ok(url)
+}
+
+impl Synx {
+    /// The address of the URL's host if it is given as one that deliveries must
+    /// not reach. Such URLs never go through [`WebhookResolver`].
+    fn blocked_address(&self, url: &Url) -> Option<IpAddr> {
+        let host = url.host_str()?;
+        let ip = literal_address(host)?;
+        (!self.webhook_allowed_hosts.contains(host) && !is_public(ip)).then_some(ip)
+    }
+
+    /// Refuses URLs whose host is, or only resolves to, a private, loopback or
+    /// link-local address, unless the operator allowed the host.
+    async fn check_webhook_target(&self, url: &Url) -> Result<(), SynxError> {
+        let unreachable =
+            |reason: String| SynxError::InvalidInput(format!("webhook url {} {}", url, reason));
+        if let Some(ip) = self.blocked_address(url) {
+            return Err(unreachable(format!("points to non-public address {}", ip)));
+        }
+
+        let Some(host) = url.host_str() else {
+            return Ok(());
+        };
+        if literal_address(host).is_some() || self.webhook_allowed_hosts.contains(host) {
+            return Ok(());
+        }
+        let addrs: Vec<SocketAddr> = tokio::net::lookup_host((host, 0))
+            .await
+            .map_err(|e| unreachable(format!("cannot be resolved: {}", e)))?
+            .collect();
+        if !addrs.iter().any(|addr| is_public(addr.ip())) {
+            return Err(unreachable("has no public address".to_string()));
+        }
+        Ok(())
+    }
+
+    /// Registers a webhook, returning it with its secret, which is generated
+    /// when none is given and not shown again.
+    pub async fn create_webhook(&self, input: CreateWebhook) -> Result<Webhook> {
+        let url = check_webhook(&input)?;
+        self.check_webhook_target(&url).await?;
+        let secret = input
+            .secret
+            .clone()
+            .unwrap_or_else(|| format!("whsec_{}", Uuid::new_v4().simple()));
+        let webhook = input.into_webhook(self.tenant_id, secret);
+        self.db.save_webhook(&webhook).await?;
+        Ok(webhook)
+    }
+
+    pub async fn list_webhooks(&self) -> Result<Vec<Webhook>> {
+        Ok(self
+            .db
+            .list_webhooks(self.tenant_id)
+            .await?
+            .into_iter()
+            .map(Webhook::redacted)
+            .collect())
+    }
+
+    pub async fn get_webhook(&self, webhook_id: Uuid) -> Result<Webhook> {
+        Ok(self
+            .db
+            .get_webhook(self.tenant_id, webhook_id)
+            .await?
+            .redacted())
+    }
+
+    /// Removes the webhook and its delivery log; deliveries still being retried
+    /// are dropped.
+    pub async fn delete_webhook(&self, webhook_id: Uuid) -> Result<()> {
+        Ok(self.db.delete_webhook(self.tenant_id, webhook_id).await?)
+    }
+
+    pub async fn list_webhook_deliveries(&self, webhook_id: Uuid) -> Result<Vec<WebhookDelivery>> {
+        Ok(self
+            .db
+            .list_webhook_deliveries(self.tenant_id, webhook_id)
+            .await?)
+    }
+
+    /// Sends a `webhook.test` event once, or only signs it on a dry run. Tests
+    /// are not retried nor kept in the delivery log.
+    pub async fn test_webhook(&self, webhook_id: Uuid, dry_run: bool) -> Result<WebhookTest> {
+        let webhook = self.db.get_webhook(self.tenant_id, webhook_id).await?;
+        let payload = payload(TEST_EVENT, json!({ "webhook_id": webhook_id }));
+        let request = signed_request(&webhook, Uuid::new_v4(), TEST_EVENT, &payload);
+
+        if dry_run {
+            return Ok(WebhookTest {
+                request,
+                delivered: false,
+                error: None,
+            });
+        }
+
+        let (response_status, error) = self.send(&request).await;
+        Ok(WebhookTest {
+            request,
+            delivered: error.is_none(),
+            error: error.map(|_| match response_status {
+                Some(_) => "webhook responded with an error status".to_string(),
+                None => "webhook could not be reached".to_string(),
+            }),
+        })
+    }
+
+    /// Publishes the event to subscribers and queues a delivery to every webhook
+    /// accepting it.
+    pub(crate) async fn emit(&self, event: Event) {
+        self.events.publish(event.clone());
+
+        let webhooks = match self.db.list_webhooks(self.tenant_id).await {
+            Ok(webhooks) => webhooks,
+            Err(e) => {
+                tracing::error!("Failed to list webhooks for {}: {}", event.name(), e);
+                return;
+            }
+        };
+        if !webhooks.iter().any(|webhook| webhook.accepts(event.name())) {
+            return;
+        }
+
+        let payload = match serde_json::to_value(&event) {
+            Ok(data) => payload(event.name(), data),
+            Err(e) => {
+                tracing::error!("Failed to serialize {}: {}", event.name(), e);
+                return;
+            }
+        };
+        for webhook in webhooks
+            .iter()
+            .filter(|webhook| webhook.accepts(event.name()))
+        {
+            let delivery = WebhookDelivery::new(webhook, event.name(), payload.clone());
+            match self.db.save_webhook_delivery(&delivery).await {
+                Ok(()) => self.schedule_delivery(delivery),
+                // Deleted since it was listed.
+                Err(DatabaseError::NotFound) => {}
+                Err(e) => tracing::error!(
+                    "Failed to queue {} for webhook {}: {}",
+                    event.name(),
+                    webhook.id(),
+                    e
+                ),
+            }
+        }
+    }
+
+    /// Schedules every webhook delivery left pending by a previous run.
+    pub(crate) async fn resume_webhook_deliveries(&self) -> Result<()> {
+        let deliveries = self.db.get_pending_webhook_deliveries().await?;
+        tracing::info!("Resuming {} pending webhook deliveries", deliveries.len());
+
+        for delivery in deliveries {
+            self.schedule_delivery(delivery);
+        }
+
+        Ok(())
+    }
+
+    fn schedule_delivery(&self, mut delivery: WebhookDelivery) {
+        self.executor.spawn({
+            let this = self.for_tenant(delivery.tenant_id);
+
+            async move {
+                loop {
+                    let delay = delivery.next_attempt_at.saturating_sub(now_millis());
+                    if delay > 0 {
+                        tokio::time::sleep(Duration::from_millis(delay)).await;
+                    }
+
+                    // The secret may have changed, or the webhook may be gone.
+                    let webhook = match this
+                        .db
+                        .get_webhook(this.tenant_id, delivery.webhook_id)
+                        .await
+                    {
+                        Ok(webhook) => webhook,
+                        Err(DatabaseError::NotFound) => return,
+                        Err(e) => {
+                            tracing::error!(
+                                "Failed to load webhook {}: {}",
+                                delivery.webhook_id,
+                                e
+                            );
+                            return;
+                        }
+                    };
+                    let request =
+                        signed_request(&webhook, delivery.id(), &delivery.event, &delivery.payload);
+                    let (response_status, error) = this.send(&request).await;
+
+                    delivery.attempts += 1;
+                    delivery.response_status = response_status;
+                    match &error {
+                        None => {
+                            delivery.status = DeliveryStatus::Succeeded;
+                            delivery.delivered_at = Some(now_millis());
+                            delivery.last_error = None;
+                        }
+                        Some(error) if delivery.attempts >= MAX_DELIVERY_ATTEMPTS => {
+                            delivery.status = DeliveryStatus::Failed;
+                            delivery.last_error = Some(error.clone());
+                            tracing::error!(
+                                "Webhook delivery {} failed after {} attempts: {}",
+                                delivery.id(),
+                                delivery.attempts,
+                                error
+                            );
+                        }
+                        Some(error) => {
+                            delivery.next_attempt_at =
+                                now_millis() + backoff_millis(delivery.attempts);
+                            delivery.last_error = Some(error.clone());
+                            tracing::warn!(
+                                "Webhook delivery {} failed (attempt {}), retrying: {}",
+                                delivery.id(),
+                                delivery.attempts,
+                                error
+                            );
+                        }
+                    }
+
+                    match this.db.save_webhook_delivery(&delivery).await {
+                        Ok(()) => {}
+                        Err(DatabaseError::NotFound) => return,
+                        Err(e) => {
+                            tracing::error!(
+                                "Failed to save webhook delivery {}: {}",
+                                delivery.id(),
+                                e
+                            );
+                            return;
+                        }
+                    }
+
+                    if delivery.status != DeliveryStatus::Pending {
+                        return;
+                    }
+                }
+            }
+            .boxed()
+        });
+    }
+
+    /// Posts the request, returning the response status if one came back and an
+    /// error unless it was a success.
+    async fn send(&self, request: &SignedRequest) -> (Option<u16>, Option<String>) {
+        // The URL was checked when the webhook was registered; this catches
+        // webhooks stored before that check, or since the allow-list changed.
+        match Url::parse(&request.url) {
+            Ok(url) => {
+                if let Some(ip) = self.blocked_address(&url) {
+                    return (
+                        None,
+                        Some(format!("webhook points to non-public address {}", ip)),
+                    );
+                }
+            }
+            Err(e) => return (None, Some(format!("invalid webhook url: {}", e))),
+        }
+
+        let mut builder = self
+            .http
+            .post(&request.url)
+            .timeout(WEBHOOK_TIMEOUT)
+            .body(request.body.clone());
+        for (name, value) in &request.headers {
+            builder = builder.header(name, value);
+        }
+
+        match builder.send().await {
+            Ok(response) if response.status().is_success() => {
+                (Some(response.status().as_u16()), None)
+            }
+            Ok(response) => (
+                Some(response.status().as_u16()),
+                Some(format!("webhook responded with {}", response.status())),
+            ),
+            Err(e) => (None, Some(e.to_string())),
+        }
+    }
+}
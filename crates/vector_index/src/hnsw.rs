@@ -0,0 +1,467 @@
+use std::{
+    cmp::{Ordering, Reverse},
+    collections::{BinaryHeap, HashSet},
+};
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::similarity::cosine_similarity;
+
+/// Adjacency lists of a single vector, one per layer it belongs to.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct HnswNode {
+    pub neighbours: Vec<Vec<Uuid>>,
+}
+
+impl HnswNode {
+    fn with_level(level: usize) -> Self {
+        Self {
+            neighbours: vec![Vec::new(); level + 1],
+        }
+    }
+
+    pub fn level(&self) -> usize {
+        self.neighbours.len().saturating_sub(1)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HnswEntryPoint {
+    pub id: Uuid,
+    pub level: usize,
+}
+
+/// Read access to the graph and to the vectors it indexes.
+///
+/// Vectors are not owned by the index: backends keep them wherever they
+/// already store embeddings and only expose them through this trait.
+pub trait HnswStorage {
+    type Error;
+
+    fn vector(&self, id: Uuid) -> Result<Option<Vec<f32>>, Self::Error>;
+
+    fn node(&self, id: Uuid) -> Result<Option<HnswNode>, Self::Error>;
+
+    fn entry_point(&self) -> Result<Option<HnswEntryPoint>, Self::Error>;
+}
+
+pub trait HnswStorageMut: HnswStorage {
+    fn put_node(&mut self, id: Uuid, node: &HnswNode) -> Result<(), Self::Error>;
+
+    fn delete_node(&mut self, id: Uuid) -> Result<(), Self::Error>;
+
+    fn set_entry_point(&mut self, entry_point: Option<HnswEntryPoint>) -> Result<(), Self::Error>;
+
+    /// Any node still in the graph, used to recover when the entry point is removed.
+    fn any_node(&self) -> Result<Option<(Uuid, HnswNode)>, Self::Error>;
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Scored {
+    id: Uuid,
+    similarity: f32,
+}
+
+impl PartialEq for Scored {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Scored {}
+
+impl PartialOrd for Scored {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Scored {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.similarity
+            .total_cmp(&other.similarity)
+            .then_with(|| self.id.cmp(&other.id))
+    }
+}
+
+/// Hierarchical navigable small world graph over cosine similarity.
+#[derive(Clone, Copy, Debug)]
+pub struct Hnsw {
+    max_neighbours: usize,
+    max_neighbours_base: usize,
+    ef_construction: usize,
+    ef_search: usize,
+    level_multiplier: f64,
+}
+
+impl Default for Hnsw {
+    fn default() -> Self {
+        Self::new(16, 100, 64)
+    }
+}
+
+impl Hnsw {
+    pub fn new(max_neighbours: usize, ef_construction: usize, ef_search: usize) -> Self {
+        Self {
+            max_neighbours,
+            max_neighbours_base: max_neighbours * 2,
+            ef_construction,
+            ef_search,
+            level_multiplier: 1.0 / (max_neighbours.max(2) as f64).ln(),
+        }
+    }
+
+    pub fn insert<S: HnswStorageMut>(
+        &self,
+        storage: &mut S,
+        id: Uuid,
+        vector: &[f32],
+    ) -> Result<(), S::Error> {
+        if storage.node(id)?.is_some() {
+            self.remove(storage, id)?;
+        }
+
+        let level = self.random_level(id);
+        let Some(entry_point) = storage.entry_point()? else {
+            storage.put_node(id, &HnswNode::with_level(level))?;
+            return storage.set_entry_point(Some(HnswEntryPoint { id, level }));
+        };
+
+        let mut entry_points = vec![self.score(storage, vector, entry_point.id)?];
+        for layer in (level + 1..=entry_point.level).rev() {
+            entry_points = self.closest(storage, vector, entry_points, layer)?;
+        }
+
+        let mut node = HnswNode::with_level(level);
+        for layer in (0..=level.min(entry_point.level)).rev() {
+            let candidates = self.search_layer(
+                storage,
+                vector,
+                entry_points,
+                self.ef_construction,
+                layer,
+                &|_| true,
+            )?;
+            node.neighbours[layer] = candidates
+                .iter()
+                .filter(|candidate| candidate.id != id)
+                .take(self.max_neighbours)
+                .map(|candidate| candidate.id)
+                .collect();
+            entry_points = candidates;
+        }
+
+        storage.put_node(id, &node)?;
+        for (layer, neighbours) in node.neighbours.iter().enumerate() {
+            for &neighbour in neighbours {
+                self.connect(storage, neighbour, id, layer)?;
+            }
+        }
+
+        if level > entry_point.level {
+            storage.set_entry_point(Some(HnswEntryPoint { id, level }))?;
+        }
+
+        Ok(())
+    }
+
+    pub fn remove<S: HnswStorageMut>(&self, storage: &mut S, id: Uuid) -> Result<(), S::Error> {
+        let Some(node) = storage.node(id)? else {
+            return Ok(());
+        };
+        storage.delete_node(id)?;
+
+        // Reconnect former neighbours amongst themselves so the graph stays navigable.
+        for (layer, neighbours) in node.neighbours.iter().enumerate() {
+            for &neighbour in neighbours {
+                let Some(mut neighbour_node) = storage.node(neighbour)? else {
+                    continue;
+                };
+                let Some(list) = neighbour_node.neighbours.get_mut(layer) else {
+                    continue;
+                };
+
+                let mut list = std::mem::take(list);
+                list.retain(|&other| other != id);
+                for &other in neighbours {
+                    if other != neighbour && !list.contains(&other) {
+                        list.push(other);
+                    }
+                }
+                neighbour_node.neighbours[layer] =
+                    self.shrink(storage, neighbour, list, self.max_for_layer(layer))?;
+                storage.put_node(neighbour, &neighbour_node)?;
+            }
+        }
+
+        if storage.entry_point()?.map(|entry_point| entry_point.id) == Some(id) {
+            let mut next: Option<HnswEntryPoint> = None;
+            for neighbours in node.neighbours.iter().rev() {
+                for &neighbour in neighbours {
+                    if let Some(neighbour_node) = storage.node(neighbour)? {
+                        if next.is_none_or(|next| neighbour_node.level() > next.level) {
+                            next = Some(HnswEntryPoint {
+                                id: neighbour,
+                                level: neighbour_node.level(),
+                            });
+                        }
+                    }
+                }
+                if next.is_some() {
+                    break;
+                }
+            }
+
+            if next.is_none() {
+                next = storage.any_node()?.map(|(id, node)| HnswEntryPoint {
+                    id,
+                    level: node.level(),
+                });
+            }
+
+            storage.set_entry_point(next)?;
+        }
+
+        Ok(())
+    }
+
+    /// Returns up to `k` ids accepted by `filter`, most similar first.
+    ///
+    /// The search gives up once the filter has turned down a bounded number of
+    /// nodes, so a filter accepting few of them may get fewer than `k` results;
+    /// callers able to list the accepted ids can then turn to [`Hnsw::search_exact`].
+    pub fn search<S: HnswStorage>(
+        &self,
+        storage: &S,
+        query: &[f32],
+        k: usize,
+        filter: &dyn Fn(&Uuid) -> bool,
+    ) -> Result<Vec<(Uuid, f32)>, S::Error> {
+        let Some(entry_point) = storage.entry_point()? else {
+            return Ok(Vec::new());
+        };
+
+        let mut entry_points = vec![self.score(storage, query, entry_point.id)?];
+        for layer in (1..=entry_point.level).rev() {
+            entry_points = self.closest(storage, query, entry_points, layer)?;
+        }
+
+        let results = self.search_layer(
+            storage,
+            query,
+            entry_points,
+            self.ef_search.max(k),
+            0,
+            filter,
+        )?;
+
+        Ok(results
+            .into_iter()
+            .take(k)
+            .map(|scored| (scored.id, scored.similarity))
+            .collect())
+    }
+
+    /// Returns up to `k` of `ids`, most similar first, comparing the query with
+    /// each of their vectors instead of walking the graph.
+    pub fn search_exact<S: HnswStorage>(
+        &self,
+        storage: &S,
+        query: &[f32],
+        k: usize,
+        ids: impl IntoIterator<Item = Uuid>,
+    ) -> Result<Vec<(Uuid, f32)>, S::Error> {
+        let mut results: BinaryHeap<Reverse<Scored>> = BinaryHeap::new();
+        for id in ids {
+            let Some(vector) = storage.vector(id)? else {
+                continue;
+            };
+            results.push(Reverse(Scored {
+                id,
+                similarity: cosine_similarity(query, &vector),
+            }));
+            if results.len() > k {
+                results.pop();
+            }
+        }
+
+        let mut results: Vec<Scored> = results.into_iter().map(|Reverse(scored)| scored).collect();
+        results.sort_by(|a, b| b.cmp(a));
+        Ok(results
+            .into_iter()
+            .map(|scored| (scored.id, scored.similarity))
+            .collect())
+    }
+
+    fn random_level(&self, id: Uuid) -> usize {
+        // Version 4 ids are random, so their low bits are a stable uniform sample.
+        let sample = ((id.as_u128() & ((1 << 52) - 1)) as f64 + 1.0) / (1u64 << 52) as f64;
+        (-sample.ln() * self.level_multiplier).floor() as usize
+    }
+
+    fn max_for_layer(&self, layer: usize) -> usize {
+        if layer == 0 {
+            self.max_neighbours_base
+        } else {
+            self.max_neighbours
+        }
+    }
+
+    fn score<S: HnswStorage>(
+        &self,
+        storage: &S,
+        query: &[f32],
+        id: Uuid,
+    ) -> Result<Scored, S::Error> {
+        let similarity = storage
+            .vector(id)?
+            .map(|vector| cosine_similarity(query, &vector))
+            .unwrap_or(f32::MIN);
+        Ok(Scored { id, similarity })
+    }
+
+    fn closest<S: HnswStorage>(
+        &self,
+        storage: &S,
+        query: &[f32],
+        entry_points: Vec<Scored>,
+        layer: usize,
+    ) -> Result<Vec<Scored>, S::Error> {
+        let mut closest = self.search_layer(storage, query, entry_points, 1, layer, &|_| true)?;
+        closest.truncate(1);
+        Ok(closest)
+    }
+
+    /// Greedy search of one layer, keeping the `ef` best nodes accepted by
+    /// `filter`. Gives up after `ef` times the base layer's neighbour count of
+    /// nodes were turned down, rather than walking the whole graph for a filter
+    /// few nodes pass.
+    fn search_layer<S: HnswStorage>(
+        &self,
+        storage: &S,
+        query: &[f32],
+        entry_points: Vec<Scored>,
+        ef: usize,
+        layer: usize,
+        filter: &dyn Fn(&Uuid) -> bool,
+    ) -> Result<Vec<Scored>, S::Error> {
+        let mut visited: HashSet<Uuid> = entry_points.iter().map(|scored| scored.id).collect();
+        let mut candidates: BinaryHeap<Scored> = BinaryHeap::new();
+        let mut results: BinaryHeap<Reverse<Scored>> = BinaryHeap::new();
+        let max_rejected = ef.saturating_mul(self.max_neighbours_base);
+        let mut rejected = 0;
+
+        for entry_point in entry_points {
+            candidates.push(entry_point);
+            if filter(&entry_point.id) {
+                results.push(Reverse(entry_point));
+            }
+        }
+        while results.len() > ef {
+            results.pop();
+        }
+
+        while let Some(candidate) = candidates.pop() {
+            if rejected >= max_rejected {
+                break;
+            }
+            if let Some(Reverse(worst)) = results.peek() {
+                if results.len() >= ef && candidate.similarity < worst.similarity {
+                    break;
+                }
+            }
+
+            let Some(node) = storage.node(candidate.id)? else {
+                continue;
+            };
+            let Some(neighbours) = node.neighbours.get(layer) else {
+                continue;
+            };
+
+            for &neighbour in neighbours {
+                if !visited.insert(neighbour) {
+                    continue;
+                }
+                let Some(vector) = storage.vector(neighbour)? else {
+                    continue;
+                };
+
+                let scored = Scored {
+                    id: neighbour,
+                    similarity: cosine_similarity(query, &vector),
+                };
+                let worst = results.peek().map(|Reverse(worst)| worst.similarity);
+                if results.len() < ef || worst.is_none_or(|worst| scored.similarity > worst) {
+                    candidates.push(scored);
+                    if filter(&neighbour) {
+                        results.push(Reverse(scored));
+                        if results.len() > ef {
+                            results.pop();
+                        }
+                    } else {
+                        rejected += 1;
+                    }
+                }
+            }
+        }
+
+        let mut results: Vec<Scored> = results.into_iter().map(|Reverse(scored)| scored).collect();
+        results.sort_by(|a, b| b.cmp(a));
+        Ok(results)
+    }
+
+    fn connect<S: HnswStorageMut>(
+        &self,
+        storage: &mut S,
+        id: Uuid,
+        neighbour: Uuid,
+        layer: usize,
+    ) -> Result<(), S::Error> {
+        let Some(mut node) = storage.node(id)? else {
+            return Ok(());
+        };
+        let Some(list) = node.neighbours.get_mut(layer) else {
+            return Ok(());
+        };
+
+        if list.contains(&neighbour) {
+            return Ok(());
+        }
+
+        let mut list = std::mem::take(list);
+        list.push(neighbour);
+        node.neighbours[layer] = self.shrink(storage, id, list, self.max_for_layer(layer))?;
+        storage.put_node(id, &node)
+    }
+
+    fn shrink<S: HnswStorage>(
+        &self,
+        storage: &S,
+        id: Uuid,
+        mut neighbours: Vec<Uuid>,
+        max: usize,
+    ) -> Result<Vec<Uuid>, S::Error> {
+        if neighbours.len() <= max {
+            return Ok(neighbours);
+        }
+
+        let Some(vector) = storage.vector(id)? else {
+            neighbours.truncate(max);
+            return Ok(neighbours);
+        };
+
+        let mut scored = Vec::with_capacity(neighbours.len());
+        for neighbour in neighbours {
+            if let Some(other) = storage.vector(neighbour)? {
+                scored.push(Scored {
+                    id: neighbour,
+                    similarity: cosine_similarity(&vector, &other),
+                });
+            }
+        }
+        scored.sort_by(|a, b| b.cmp(a));
+
+        Ok(scored.into_iter().take(max).map(|scored| scored.id).collect())
+    }
+}
@@ -0,0 +1,4 @@
+pub mod hnsw;
+pub mod similarity;
+
+pub use hnsw::{Hnsw, HnswEntryPoint, HnswNode, HnswStorage, HnswStorageMut};
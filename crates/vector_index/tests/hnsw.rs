@@ -0,0 +1,371 @@
+use std::{
+    cell::Cell,
+    collections::{HashMap, HashSet},
+    convert::Infallible,
+};
+
+use synx_vector_index::{
+    similarity::cosine_similarity, Hnsw, HnswEntryPoint, HnswNode, HnswStorage, HnswStorageMut,
+};
+use uuid::Uuid;
+
+const DIMENSIONS: usize = 16;
+
+/// Keeps the graph next to the vectors, as the database backends do.
+#[derive(Default)]
+struct MemoryStorage {
+    vectors: HashMap<Uuid, Vec<f32>>,
+    nodes: HashMap<Uuid, HnswNode>,
+    entry_point: Option<HnswEntryPoint>,
+    /// Vectors read so far, to tell how much of the graph a search walked.
+    vector_reads: Cell<usize>,
+}
+
+impl HnswStorage for MemoryStorage {
+    type Error = Infallible;
+
+    fn vector(&self, id: Uuid) -> Result<Option<Vec<f32>>, Self::Error> {
+        self.vector_reads.set(self.vector_reads.get() + 1);
+        Ok(self.vectors.get(&id).cloned())
+    }
+
+    fn node(&self, id: Uuid) -> Result<Option<HnswNode>, Self::Error> {
+        Ok(self.nodes.get(&id).cloned())
+    }
+
+    fn entry_point(&self) -> Result<Option<HnswEntryPoint>, Self::Error> {
+        Ok(self.entry_point)
+    }
+}
+
+impl HnswStorageMut for MemoryStorage {
+    fn put_node(&mut self, id: Uuid, node: &HnswNode) -> Result<(), Self::Error> {
+        self.nodes.insert(id, node.clone());
+        Ok(())
+    }
+
+    fn delete_node(&mut self, id: Uuid) -> Result<(), Self::Error> {
+        self.nodes.remove(&id);
+        Ok(())
+    }
+
+    fn set_entry_point(&mut self, entry_point: Option<HnswEntryPoint>) -> Result<(), Self::Error> {
+        self.entry_point = entry_point;
+        Ok(())
+    }
+
+    fn any_node(&self) -> Result<Option<(Uuid, HnswNode)>, Self::Error> {
+        Ok(self
+            .nodes
+            .iter()
+            .next()
+            .map(|(id, node)| (*id, node.clone())))
+    }
+}
+
+/// An index whose vectors are stored before insertion and dropped before
+/// removal, in the order the backends follow.
+struct Index {
+    hnsw: Hnsw,
+    storage: MemoryStorage,
+}
+
+impl Index {
+    fn new() -> Self {
+        Self::with_hnsw(Hnsw::default())
+    }
+
+    fn with_hnsw(hnsw: Hnsw) -> Self {
+        Self {
+            hnsw,
+            storage: MemoryStorage::default(),
+        }
+    }
+
+    fn insert(&mut self, id: Uuid, vector: Vec<f32>) {
+        self.storage.vectors.insert(id, vector.clone());
+        self.hnsw.insert(&mut self.storage, id, &vector).unwrap();
+    }
+
+    fn remove(&mut self, id: Uuid) {
+        self.storage.vectors.remove(&id);
+        self.hnsw.remove(&mut self.storage, id).unwrap();
+    }
+
+    fn search(&self, query: &[f32], k: usize) -> Vec<Uuid> {
+        self.hnsw
+            .search(&self.storage, query, k, &|_| true)
+            .unwrap()
+            .into_iter()
+            .map(|(id, _)| id)
+            .collect()
+    }
+
+    /// The `k` stored vectors most similar to `query`, by exhaustive comparison.
+    fn brute_force(&self, query: &[f32], k: usize) -> Vec<Uuid> {
+        let mut scored: Vec<(Uuid, f32)> = self
+            .storage
+            .vectors
+            .iter()
+            .map(|(id, vector)| (*id, cosine_similarity(query, vector)))
+            .collect();
+        scored.sort_by(|a, b| b.1.total_cmp(&a.1));
+        scored.into_iter().take(k).map(|(id, _)| id).collect()
+    }
+
+    /// Share of the exact `k` nearest neighbours found, over every query.
+    fn recall(&self, queries: &[Vec<f32>], k: usize) -> f64 {
+        let mut found = 0;
+        let mut expected = 0;
+        for query in queries {
+            let exact: HashSet<Uuid> = self.brute_force(query, k).into_iter().collect();
+            found += self
+                .search(query, k)
+                .iter()
+                .filter(|id| exact.contains(id))
+                .count();
+            expected += exact.len();
+        }
+        found as f64 / expected as f64
+    }
+}
+
+/// SplitMix64, so every run sees the same vectors and ids.
+struct Rng(u64);
+
+impl Rng {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    fn id(&mut self) -> Uuid {
+        Uuid::from_u64_pair(self.next_u64(), self.next_u64())
+    }
+
+    fn vector(&mut self) -> Vec<f32> {
+        (0..DIMENSIONS)
+            .map(|_| (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32 * 2.0 - 1.0)
+            .collect()
+    }
+}
+
+fn populated(rng: &mut Rng, count: usize) -> (Index, Vec<Uuid>) {
+    let mut index = Index::new();
+    let ids: Vec<Uuid> = (0..count).map(|_| rng.id()).collect();
+    for &id in &ids {
+        index.insert(id, rng.vector());
+    }
+    (index, ids)
+}
+
+/// The entry point is a node still in the graph, at its own level, unless the
+/// graph is empty.
+fn assert_entry_point(index: &Index) {
+    let storage = &index.storage;
+    match storage.entry_point {
+        Some(entry_point) => {
+            assert_eq!(storage.nodes[&entry_point.id].level(), entry_point.level);
+        }
+        None => assert!(storage.nodes.is_empty()),
+    }
+}
+
+#[test]
+fn search_on_empty_index() {
+    let index = Index::new();
+
+    assert!(index.search(&[1.0; DIMENSIONS], 10).is_empty());
+}
+
+#[test]
+fn insert_finds_each_vector() {
+    let mut rng = Rng(1);
+    let (index, ids) = populated(&mut rng, 50);
+
+    for id in ids {
+        let vector = index.storage.vectors[&id].clone();
+        assert_eq!(index.search(&vector, 1), vec![id]);
+    }
+    assert_entry_point(&index);
+}
+
+#[test]
+fn insert_again_replaces_vector() {
+    let mut rng = Rng(2);
+    let (mut index, ids) = populated(&mut rng, 50);
+
+    let vector = rng.vector();
+    index.insert(ids[0], vector.clone());
+
+    let results = index.search(&vector, 50);
+    assert_eq!(results[0], ids[0]);
+    assert_eq!(results.iter().filter(|&&id| id == ids[0]).count(), 1);
+    assert_eq!(index.storage.nodes.len(), 50);
+    assert_entry_point(&index);
+}
+
+#[test]
+fn remove_excludes_vector() {
+    let mut rng = Rng(3);
+    let (mut index, ids) = populated(&mut rng, 50);
+
+    let vector = index.storage.vectors[&ids[10]].clone();
+    index.remove(ids[10]);
+
+    assert!(!index.search(&vector, 50).contains(&ids[10]));
+    assert_eq!(index.storage.nodes.len(), 49);
+    assert_entry_point(&index);
+}
+
+#[test]
+fn remove_entry_point_reassigns_it() {
+    let mut rng = Rng(4);
+    let (mut index, _) = populated(&mut rng, 200);
+
+    for _ in 0..50 {
+        let entry_point = index.storage.entry_point.unwrap();
+        index.remove(entry_point.id);
+
+        let next = index.storage.entry_point.unwrap();
+        assert_ne!(next.id, entry_point.id);
+        assert_entry_point(&index);
+    }
+    let query = rng.vector();
+    assert_eq!(index.search(&query, 10).len(), 10);
+}
+
+#[test]
+fn remove_every_vector() {
+    let mut rng = Rng(5);
+    let (mut index, ids) = populated(&mut rng, 100);
+
+    for id in ids {
+        index.remove(id);
+        assert_entry_point(&index);
+    }
+
+    assert_eq!(index.storage.entry_point, None);
+    assert!(index.storage.nodes.is_empty());
+    assert!(index.search(&rng.vector(), 10).is_empty());
+
+    let id = rng.id();
+    index.insert(id, rng.vector());
+    assert_eq!(
+        index.storage.entry_point.map(|entry_point| entry_point.id),
+        Some(id)
+    );
+}
+
+#[test]
+fn search_applies_filter() {
+    let mut rng = Rng(6);
+    let (index, ids) = populated(&mut rng, 300);
+    let allowed: HashSet<Uuid> = ids.iter().copied().step_by(3).collect();
+
+    let results = index
+        .hnsw
+        .search(&index.storage, &rng.vector(), 20, &|id| {
+            allowed.contains(id)
+        })
+        .unwrap();
+
+    assert_eq!(results.len(), 20);
+    assert!(results.iter().all(|(id, _)| allowed.contains(id)));
+    assert!(results.windows(2).all(|pair| pair[0].1 >= pair[1].1));
+}
+
+#[test]
+fn recall_against_brute_force() {
+    let mut rng = Rng(7);
+    let (index, _) = populated(&mut rng, 1000);
+    let queries: Vec<Vec<f32>> = (0..50).map(|_| rng.vector()).collect();
+
+    let recall = index.recall(&queries, 10);
+    assert!(recall >= 0.9, "recall {}", recall);
+}
+
+#[test]
+fn recall_against_brute_force_after_removals() {
+    let mut rng = Rng(8);
+    let (mut index, ids) = populated(&mut rng, 1000);
+    for &id in ids.iter().step_by(2) {
+        index.remove(id);
+    }
+    assert_entry_point(&index);
+    let queries: Vec<Vec<f32>> = (0..50).map(|_| rng.vector()).collect();
+
+    let recall = index.recall(&queries, 10);
+    assert!(recall >= 0.9, "recall {}", recall);
+}
+
+#[test]
+fn selective_filter_search_is_bounded() {
+    let mut rng = Rng(9);
+    let mut index = Index::with_hnsw(Hnsw::new(8, 50, 16));
+    let ids: Vec<Uuid> = (0..2000).map(|_| rng.id()).collect();
+    for &id in &ids {
+        index.insert(id, rng.vector());
+    }
+    let allowed: HashSet<Uuid> = ids.iter().copied().step_by(400).collect();
+    let query = rng.vector();
+
+    index.storage.vector_reads.set(0);
+    let results = index
+        .hnsw
+        .search(&index.storage, &query, 10, &|id| allowed.contains(id))
+        .unwrap();
+    assert!(
+        index.storage.vector_reads.get() < ids.len() / 2,
+        "read {} vectors",
+        index.storage.vector_reads.get()
+    );
+    assert!(results.iter().all(|(id, _)| allowed.contains(id)));
+
+    let exact = index
+        .hnsw
+        .search_exact(&index.storage, &query, 10, allowed.iter().copied())
+        .unwrap();
+    let mut expected: Vec<(Uuid, f32)> = allowed
+        .iter()
+        .map(|id| (*id, cosine_similarity(&query, &index.storage.vectors[id])))
+        .collect();
+    expected.sort_by(|a, b| b.1.total_cmp(&a.1));
+    assert_eq!(exact, expected);
+}
+
+#[test]
+fn search_exact_keeps_the_closest() {
+    let mut rng = Rng(10);
+    let (index, ids) = populated(&mut rng, 100);
+    let query = rng.vector();
+
+    let exact: Vec<Uuid> = index
+        .hnsw
+        .search_exact(&index.storage, &query, 10, ids.iter().copied())
+        .unwrap()
+        .into_iter()
+        .map(|(id, _)| id)
+        .collect();
+
+    assert_eq!(exact, index.brute_force(&query, 10));
+}
+
+#[test]
+fn zero_vectors_have_no_similarity() {
+    assert_eq!(cosine_similarity(&[0.0; 4], &[1.0, 2.0, 3.0, 4.0]), 0.0);
+    assert_eq!(cosine_similarity(&[0.0; 4], &[0.0; 4]), 0.0);
+
+    let mut rng = Rng(11);
+    let (index, _) = populated(&mut rng, 50);
+    let results = index
+        .hnsw
+        .search(&index.storage, &[0.0; DIMENSIONS], 10, &|_| true)
+        .unwrap();
+
+    assert_eq!(results.len(), 10);
+    assert!(results.iter().all(|(_, similarity)| *similarity == 0.0));
+}
@@ -0,0 +1,151 @@
+use std::sync::Arc;
+
+use axum::{
+    async_trait,
+    extract::{FromRequestParts, Request, State},
+    http::{header, request::Parts, HeaderMap, StatusCode},
+    middleware::Next,
+    response::Response,
+};
+use synx::Synx;
+use synx_domain::{
+    api_key::{ApiKey, Scope},
+    tenant::Tenant,
+};
+use uuid::Uuid;
+
+use crate::api::error::ApiError;
+
+const API_KEY_HEADER: &str = "X-Api-Key";
+
+/// Who a request acts for, known once its key is checked.
+#[derive(Clone, Debug)]
+pub struct Caller {
+    pub tenant_id: Uuid,
+    pub scopes: Vec<Scope>,
+}
+
+impl Caller {
+    fn allows(&self, scope: Scope) -> bool {
+        self.scopes.contains(&scope) || self.scopes.contains(&Scope::Admin)
+    }
+
+    /// Managing tenants and inspecting the database reach every tenant, so
+    /// they take an admin key of the default tenant.
+    fn is_operator(&self) -> bool {
+        self.tenant_id == Tenant::DEFAULT_ID && self.allows(Scope::Admin)
+    }
+}
+
+#[derive(Clone)]
+pub struct Auth {
+    synx: Synx,
+    /// Hash of the server's own key, compared in place of the key itself.
+    admin_key_hash: Arc<str>,
+}
+
+impl Auth {
+    pub fn new(synx: Synx, admin_key: &str) -> Self {
+        Self {
+            synx,
+            admin_key_hash: ApiKey::hash(admin_key).into(),
+        }
+    }
+}
+
+fn unauthorized() -> ApiError {
+    ApiError::new(
+        StatusCode::UNAUTHORIZED,
+        "unauthorized",
+        "missing or invalid API key",
+    )
+}
+
+/// The key of an `Authorization: Bearer` or `X-Api-Key` header.
+fn presented_key(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .or_else(|| {
+            headers
+                .get(API_KEY_HEADER)
+                .and_then(|value| value.to_str().ok())
+        })
+        .map(str::trim)
+}
+
+/// Resolves the request's key to a [`Caller`]. The server's key acts as admin
+/// for the default tenant, any other key for the tenant it was issued to with
+/// the scopes it was issued with.
+pub async fn authenticate(
+    State(auth): State<Auth>,
+    mut request: Request,
+    next: Next,
+) -> Result<Response, ApiError> {
+    let key = presented_key(request.headers()).ok_or_else(unauthorized)?;
+    let key_hash = ApiKey::hash(key);
+
+    let caller = if key_hash == *auth.admin_key_hash {
+        Caller {
+            tenant_id: Tenant::DEFAULT_ID,
+            scopes: Scope::ALL.to_vec(),
+        }
+    } else {
+        match auth.synx.authenticate(key).await {
+            Ok(Some(api_key)) => Caller {
+                tenant_id: api_key.tenant_id,
+                scopes: api_key.scopes,
+            },
+            Ok(None) => return Err(unauthorized()),
+            Err(e) => {
+                tracing::error!("Failed to look up API key: {:?}", e);
+                return Err(e.into());
+            }
+        }
+    };
+
+    request.extensions_mut().insert(caller);
+    Ok(next.run(request).await)
+}
+
+fn forbidden(message: impl Into<String>) -> ApiError {
+    ApiError::new(StatusCode::FORBIDDEN, "forbidden", message)
+}
+
+/// Lets through callers whose key has `scope`.
+pub async fn require_scope(
+    State(scope): State<Scope>,
+    request: Request,
+    next: Next,
+) -> Result<Response, ApiError> {
+    match request.extensions().get::<Caller>() {
+        Some(caller) if caller.allows(scope) => Ok(next.run(request).await),
+        Some(_) => Err(forbidden(format!("the {} scope is required", scope))),
+        None => Err(unauthorized()),
+    }
+}
+
+/// Lets through admin keys of the default tenant.
+pub async fn require_operator(request: Request, next: Next) -> Result<Response, ApiError> {
+    match request.extensions().get::<Caller>() {
+        Some(caller) if caller.is_operator() => Ok(next.run(request).await),
+        Some(_) => Err(forbidden(
+            "an admin API key of the default tenant is required",
+        )),
+        None => Err(unauthorized()),
+    }
+}
+
+/// The service acting for the caller's tenant.
+pub struct TenantSynx(pub Synx);
+
+#[async_trait]
+impl FromRequestParts<Synx> for TenantSynx {
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, synx: &Synx) -> Result<Self, Self::Rejection> {
+        let caller = parts.extensions.get::<Caller>().ok_or_else(unauthorized)?;
+        Ok(Self(synx.for_tenant(caller.tenant_id)))
+    }
+}
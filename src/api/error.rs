@@ -0,0 +1,108 @@
+use axum::{
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use synx::SynxError;
+use synx_database::DatabaseError;
+
+#[derive(Debug)]
+pub struct ApiError {
+    status: StatusCode,
+    code: &'static str,
+    message: String,
+}
+
+impl ApiError {
+    pub fn new(status: StatusCode, code: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            code,
+            message: message.into(),
+        }
+    }
+
+    pub fn bad_request(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::BAD_REQUEST, "invalid_input", message)
+    }
+
+    pub fn not_found(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::NOT_FOUND, "not_found", message)
+    }
+
+    pub fn internal() -> Self {
+        Self::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "internal_error",
+            "internal server error",
+        )
+    }
+}
+
+impl From<DatabaseError> for ApiError {
+    fn from(error: DatabaseError) -> Self {
+        match error {
+            DatabaseError::NotFound => Self::not_found("resource not found"),
+            DatabaseError::InvalidInput(message) => Self::bad_request(message),
+            DatabaseError::Conflict(message) => {
+                Self::new(StatusCode::CONFLICT, "conflict", message)
+            }
+            DatabaseError::ConnectionError(_) => Self::new(
+                StatusCode::SERVICE_UNAVAILABLE,
+                "database_unavailable",
+                "database is unavailable",
+            ),
+            DatabaseError::QueryError(_)
+            | DatabaseError::SerializationError(_)
+            | DatabaseError::OperationFailed(_)
+            | DatabaseError::InternalError(_) => Self::internal(),
+        }
+    }
+}
+
+impl From<SynxError> for ApiError {
+    fn from(error: SynxError) -> Self {
+        match error {
+            SynxError::Embedding(_) => Self::new(
+                StatusCode::SERVICE_UNAVAILABLE,
+                "embedding_unavailable",
+                "embedding service is unavailable",
+            ),
+            SynxError::Summarization(_) => Self::new(
+                StatusCode::SERVICE_UNAVAILABLE,
+                "summarization_unavailable",
+                "summarization service is unavailable",
+            ),
+            SynxError::InvalidInput(message) => Self::bad_request(message),
+        }
+    }
+}
+
+impl From<anyhow::Error> for ApiError {
+    fn from(error: anyhow::Error) -> Self {
+        let error = match error.downcast::<DatabaseError>() {
+            Ok(error) => return error.into(),
+            Err(error) => error,
+        };
+
+        match error.downcast::<SynxError>() {
+            Ok(error) => error.into(),
+            Err(_) => Self::internal(),
+        }
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        (
+            self.status,
+            Json(serde_json::json!({
+                "error": {
+                    "code": self.code,
+                    "message": self.message,
+                }
+            })),
+        )
+            .into_response()
+    }
+}
@@ -1,24 +1,43 @@
+use std::{collections::HashMap, convert::Infallible};
+
 use axum::{
+    body::Bytes,
     extract::{Path, Query, State},
-    http::StatusCode,
-    response::{IntoResponse, Response},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::{
+        sse::{self, KeepAlive, Sse},
+        IntoResponse,
+    },
     Json,
 };
-use ferrochain::vectorstore::Similarity;
-use synx::{SearchRequest, Synx};
+use ferrochain::{
+    futures::{Stream, StreamExt},
+    vectorstore::Similarity,
+};
+use serde_json::Value;
+use synx::{
+    export::{
+        transcript::{ArchiveRequest, TranscriptRequest},
+        ExportRequest,
+    },
+    import::ImportRequest,
+    webhooks::{TestWebhookRequest, WebhookTest},
+    ContextRequest, MessageSearchRequest, MessageSimilarity, SearchRequest, Synx, ThreadContext,
+};
 use synx_domain::{
-    message::{CreateMessage, UpdateMessage},
-    thread::{Thread, UpdateThread},
+    api_key::{ApiKey, CreateApiKey, IssuedApiKey},
+    branch::{ForkThread, GetBranch},
+    job::Job,
+    message::{CreateMessage, ListMessages, Message, MessageRevision, UpdateMessage},
+    tenant::{CreateTenant, Tenant},
+    thread::{ListThreads, Thread, UpdateThread},
+    webhook::{CreateWebhook, Webhook, WebhookDelivery},
 };
 use uuid::Uuid;
 
-#[derive(serde::Deserialize)]
-pub struct PaginationParams {
-    limit: Option<usize>,
-    offset: Option<usize>,
-}
+use crate::api::{auth::TenantSynx, error::ApiError};
 
-pub async fn create_thread(State(synx): State<Synx>) -> Result<impl IntoResponse, StatusCode> {
+pub async fn create_thread(TenantSynx(synx): TenantSynx) -> Result<impl IntoResponse, ApiError> {
     tracing::info!("Attempting to create a new thread");
     match synx.create_thread().await {
         Ok(thread) => {
@@ -27,104 +46,137 @@ pub async fn create_thread(State(synx): State<Synx>) -> Result<impl IntoResponse
         }
         Err(e) => {
             tracing::error!("Failed to create thread: {:?}", e);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
+            Err(e.into())
         }
     }
 }
 
-pub async fn list_threads(State(synx): State<Synx>) -> Result<Json<Vec<Thread>>, StatusCode> {
+/// Lists threads a page at a time. Threads can be filtered by metadata with
+/// `metadata.<key>=<value>` query parameters.
+pub async fn list_threads(
+    TenantSynx(synx): TenantSynx,
+    Query(mut query): Query<ListThreads>,
+    Query(params): Query<HashMap<String, String>>,
+) -> Result<impl IntoResponse, ApiError> {
     tracing::info!("Attempting to list threads");
-    match synx.list_threads().await {
-        Ok(threads) => {
-            tracing::info!("Successfully retrieved {} threads", threads.len());
-            Ok(Json(threads))
+    query.metadata = params
+        .into_iter()
+        .filter_map(|(key, value)| {
+            key.strip_prefix("metadata.")
+                .map(|key| (key.to_string(), serde_json::Value::String(value)))
+        })
+        .collect();
+    match synx.list_threads(&query).await {
+        Ok(response) => {
+            tracing::info!("Successfully retrieved {} threads", response.threads.len());
+            let mut headers = HeaderMap::new();
+            headers.insert("X-Total-Count", response.total.into());
+            headers.insert("X-Limit", response.limit.into());
+            if let Some(cursor) = response.next_cursor {
+                headers.insert(
+                    "X-Next-Cursor",
+                    HeaderValue::from_str(&cursor.to_string()).map_err(|_| ApiError::internal())?,
+                );
+            }
+            Ok((headers, Json(response.threads)))
         }
         Err(e) => {
             tracing::error!("Failed to list threads: {:?}", e);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
+            Err(e.into())
         }
     }
 }
 
 pub async fn get_thread(
-    State(synx): State<Synx>,
+    TenantSynx(synx): TenantSynx,
     Path(thread_id): Path<Uuid>,
-) -> Result<Json<Thread>, StatusCode> {
+) -> Result<Json<Thread>, ApiError> {
     match synx.get_thread(thread_id).await {
         Ok(thread) => Ok(Json(thread)),
         Err(e) => {
             tracing::error!("Failed to get thread {}: {:?}", thread_id, e);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
+            Err(e.into())
         }
     }
 }
 
 pub async fn update_thread(
-    State(synx): State<Synx>,
+    TenantSynx(synx): TenantSynx,
     Path(thread_id): Path<Uuid>,
     Json(update_thread): Json<UpdateThread>,
-) -> Result<Json<Thread>, StatusCode> {
+) -> Result<Json<Thread>, ApiError> {
     match synx.update_thread(thread_id, update_thread).await {
         Ok(thread) => Ok(Json(thread)),
         Err(e) => {
             tracing::error!("Failed to update thread {}: {:?}", thread_id, e);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
+            Err(e.into())
+        }
+    }
+}
+
+pub async fn get_thread_jobs(
+    TenantSynx(synx): TenantSynx,
+    Path(thread_id): Path<Uuid>,
+) -> Result<Json<Vec<Job>>, ApiError> {
+    match synx.get_thread_jobs(thread_id).await {
+        Ok(jobs) => Ok(Json(jobs)),
+        Err(e) => {
+            tracing::error!("Failed to get jobs for thread {}: {:?}", thread_id, e);
+            Err(e.into())
         }
     }
 }
 
 pub async fn get_messages(
-    State(synx): State<Synx>,
+    TenantSynx(synx): TenantSynx,
     Path(thread_id): Path<Uuid>,
-    Query(params): Query<PaginationParams>,
-) -> Result<impl IntoResponse, StatusCode> {
-    match synx
-        .get_messages(thread_id, params.limit, params.offset)
-        .await
-    {
+    Query(query): Query<ListMessages>,
+) -> Result<impl IntoResponse, ApiError> {
+    match synx.get_messages(thread_id, &query).await {
         Ok(response) => {
-            let headers = [
-                ("X-Total-Count", response.total.to_string()),
-                ("X-Offset", response.offset.to_string()),
-                ("X-Limit", response.limit.to_string()),
-            ];
+            let mut headers = HeaderMap::new();
+            headers.insert("X-Total-Count", response.total.into());
+            headers.insert("X-Offset", response.offset.into());
+            headers.insert("X-Limit", response.limit.into());
+            if let Some(cursor) = response.next_cursor {
+                headers.insert(
+                    "X-Next-Cursor",
+                    HeaderValue::from_str(&cursor.to_string()).map_err(|_| ApiError::internal())?,
+                );
+            }
             Ok((headers, Json(response.messages)))
         }
         Err(e) => {
             tracing::error!("Failed to get messages for thread {}: {:?}", thread_id, e);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
+            Err(e.into())
         }
     }
 }
 
 pub async fn create_message(
-    State(synx): State<Synx>,
+    TenantSynx(synx): TenantSynx,
     Path(thread_id): Path<Uuid>,
     Json(create_message): Json<CreateMessage>,
-) -> Response {
+) -> Result<impl IntoResponse, ApiError> {
     match synx.create_message(thread_id, create_message).await {
-        Ok(message) => (StatusCode::CREATED, Json(message)).into_response(),
+        Ok(message) => Ok((StatusCode::CREATED, Json(message))),
         Err(e) => {
             tracing::error!("Failed to create message in thread {}: {:?}", thread_id, e);
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(serde_json::json!({ "error": "internal server error" })),
-            )
-                .into_response()
+            Err(e.into())
         }
     }
 }
 
 pub async fn update_message(
-    State(synx): State<Synx>,
+    TenantSynx(synx): TenantSynx,
     Path((thread_id, message_id)): Path<(Uuid, Uuid)>,
     Json(update_message): Json<UpdateMessage>,
-) -> Response {
+) -> Result<Json<Message>, ApiError> {
     match synx
         .update_message(thread_id, message_id, update_message)
         .await
     {
-        Ok(message) => (StatusCode::OK, Json(message)).into_response(),
+        Ok(message) => Ok(Json(message)),
         Err(e) => {
             tracing::error!(
                 "Failed to update message {} in thread {}: {:?}",
@@ -132,21 +184,201 @@ pub async fn update_message(
                 thread_id,
                 e
             );
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(serde_json::json!({ "error": format!("internal server error: {}", e) })),
-            )
-                .into_response()
+            Err(e.into())
+        }
+    }
+}
+
+pub async fn get_branch(
+    TenantSynx(synx): TenantSynx,
+    Path(thread_id): Path<Uuid>,
+    Query(query): Query<GetBranch>,
+) -> Result<Json<Vec<Message>>, ApiError> {
+    match synx.get_branch(thread_id, query.through).await {
+        Ok(messages) => Ok(Json(messages)),
+        Err(e) => {
+            tracing::error!("Failed to get branch of thread {}: {:?}", thread_id, e);
+            Err(e.into())
+        }
+    }
+}
+
+pub async fn get_context(
+    TenantSynx(synx): TenantSynx,
+    Path(thread_id): Path<Uuid>,
+    Query(query): Query<ContextRequest>,
+) -> Result<Json<ThreadContext>, ApiError> {
+    match synx.build_context(thread_id, query.max_tokens).await {
+        Ok(context) => Ok(Json(context)),
+        Err(e) => {
+            tracing::error!("Failed to build context of thread {}: {:?}", thread_id, e);
+            Err(e.into())
+        }
+    }
+}
+
+pub async fn export_thread(
+    TenantSynx(synx): TenantSynx,
+    Path(thread_id): Path<Uuid>,
+    Query(query): Query<ExportRequest>,
+) -> Result<Json<Value>, ApiError> {
+    match synx.export_thread(thread_id, query.format).await {
+        Ok(export) => Ok(Json(export)),
+        Err(e) => {
+            tracing::error!("Failed to export thread {}: {:?}", thread_id, e);
+            Err(e.into())
+        }
+    }
+}
+
+pub async fn thread_events(
+    TenantSynx(synx): TenantSynx,
+    Path(thread_id): Path<Uuid>,
+) -> Result<Sse<impl Stream<Item = Result<sse::Event, Infallible>>>, ApiError> {
+    // Subscribe first so that nothing happening after the check is missed.
+    let events = synx.subscribe(thread_id);
+    if let Err(e) = synx.get_thread(thread_id).await {
+        tracing::error!("Failed to subscribe to thread {}: {:?}", thread_id, e);
+        return Err(e.into());
+    }
+
+    tracing::info!("Streaming events of thread {}", thread_id);
+    let events = events.filter_map(|event| async move {
+        let (name, data) = match &event {
+            Ok(event) => (event.name(), sse::Event::default().json_data(event)),
+            Err(lagged) => ("stream.lagged", sse::Event::default().json_data(lagged)),
+        };
+        match data {
+            Ok(sse_event) => Some(Ok(sse_event.event(name))),
+            Err(e) => {
+                tracing::error!("Failed to serialize {} event: {}", name, e);
+                None
+            }
+        }
+    });
+    Ok(Sse::new(events).keep_alive(KeepAlive::default()))
+}
+
+pub async fn get_transcript(
+    TenantSynx(synx): TenantSynx,
+    Path(thread_id): Path<Uuid>,
+    Query(query): Query<TranscriptRequest>,
+) -> Result<impl IntoResponse, ApiError> {
+    match synx
+        .thread_transcript(thread_id, query.format, query.embed_images)
+        .await
+    {
+        Ok(transcript) => Ok((
+            [(header::CONTENT_TYPE, query.format.content_type())],
+            transcript,
+        )),
+        Err(e) => {
+            tracing::error!(
+                "Failed to render transcript of thread {}: {:?}",
+                thread_id,
+                e
+            );
+            Err(e.into())
+        }
+    }
+}
+
+pub async fn archive_transcripts(
+    TenantSynx(synx): TenantSynx,
+    Json(request): Json<ArchiveRequest>,
+) -> Result<impl IntoResponse, ApiError> {
+    match synx.transcript_archive(&request).await {
+        Ok(archive) => Ok((
+            [
+                (
+                    header::CONTENT_TYPE,
+                    request.archive.content_type().to_string(),
+                ),
+                (
+                    header::CONTENT_DISPOSITION,
+                    format!(
+                        "attachment; filename=\"transcripts.{}\"",
+                        request.archive.extension()
+                    ),
+                ),
+            ],
+            archive,
+        )),
+        Err(e) => {
+            tracing::error!("Failed to archive transcripts: {:?}", e);
+            Err(e.into())
+        }
+    }
+}
+
+pub async fn fork_thread(
+    TenantSynx(synx): TenantSynx,
+    Path(thread_id): Path<Uuid>,
+    Query(query): Query<ForkThread>,
+) -> Result<impl IntoResponse, ApiError> {
+    match synx.fork_thread(thread_id, query.at).await {
+        Ok(thread) => {
+            tracing::info!("Thread {} forked into {}", thread_id, thread.id());
+            Ok((StatusCode::CREATED, Json(thread)))
+        }
+        Err(e) => {
+            tracing::error!(
+                "Failed to fork thread {} at message {}: {:?}",
+                thread_id,
+                query.at,
+                e
+            );
+            Err(e.into())
+        }
+    }
+}
+
+pub async fn list_message_revisions(
+    TenantSynx(synx): TenantSynx,
+    Path((thread_id, message_id)): Path<(Uuid, Uuid)>,
+) -> Result<Json<Vec<MessageRevision>>, ApiError> {
+    match synx.list_message_revisions(thread_id, message_id).await {
+        Ok(revisions) => Ok(Json(revisions)),
+        Err(e) => {
+            tracing::error!(
+                "Failed to list revisions of message {} in thread {}: {:?}",
+                message_id,
+                thread_id,
+                e
+            );
+            Err(e.into())
+        }
+    }
+}
+
+pub async fn get_message_revision(
+    TenantSynx(synx): TenantSynx,
+    Path((thread_id, message_id, revision)): Path<(Uuid, Uuid, u32)>,
+) -> Result<Json<MessageRevision>, ApiError> {
+    match synx
+        .get_message_revision(thread_id, message_id, revision)
+        .await
+    {
+        Ok(revision) => Ok(Json(revision)),
+        Err(e) => {
+            tracing::error!(
+                "Failed to get revision {} of message {} in thread {}: {:?}",
+                revision,
+                message_id,
+                thread_id,
+                e
+            );
+            Err(e.into())
         }
     }
 }
 
 pub async fn delete_message(
-    State(synx): State<Synx>,
+    TenantSynx(synx): TenantSynx,
     Path((thread_id, message_id)): Path<(Uuid, Uuid)>,
-) -> StatusCode {
+) -> Result<StatusCode, ApiError> {
     match synx.delete_message(thread_id, message_id).await {
-        Ok(_) => StatusCode::NO_CONTENT,
+        Ok(_) => Ok(StatusCode::NO_CONTENT),
         Err(e) => {
             tracing::error!(
                 "Failed to delete message {} in thread {}: {:?}",
@@ -154,24 +386,93 @@ pub async fn delete_message(
                 thread_id,
                 e
             );
-            StatusCode::INTERNAL_SERVER_ERROR
+            Err(e.into())
         }
     }
 }
 
-pub async fn delete_thread(State(synx): State<Synx>, Path(thread_id): Path<Uuid>) -> StatusCode {
+pub async fn delete_thread(
+    TenantSynx(synx): TenantSynx,
+    Path(thread_id): Path<Uuid>,
+) -> Result<StatusCode, ApiError> {
     match synx.delete_thread(thread_id).await {
-        Ok(_) => StatusCode::NO_CONTENT,
+        Ok(_) => Ok(StatusCode::NO_CONTENT),
         Err(e) => {
             tracing::error!("Failed to delete thread {}: {:?}", thread_id, e);
-            StatusCode::INTERNAL_SERVER_ERROR
+            Err(e.into())
+        }
+    }
+}
+
+pub async fn upload_blob(
+    TenantSynx(synx): TenantSynx,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<impl IntoResponse, ApiError> {
+    if body.is_empty() {
+        return Err(ApiError::bad_request("blob is empty"));
+    }
+
+    let mime_type = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string);
+    match synx.put_blob(body.to_vec(), mime_type).await {
+        Ok(blob) => {
+            tracing::info!("Blob stored: {} ({} bytes)", blob.hash, blob.size);
+            Ok((StatusCode::CREATED, Json(blob)))
+        }
+        Err(e) => {
+            tracing::error!("Failed to store blob: {:?}", e);
+            Err(e.into())
+        }
+    }
+}
+
+pub async fn import(
+    TenantSynx(synx): TenantSynx,
+    Query(query): Query<ImportRequest>,
+    body: Bytes,
+) -> Result<impl IntoResponse, ApiError> {
+    match synx.import(query.format, &body, query.summarize).await {
+        Ok(imported) => {
+            tracing::info!(
+                "Imported {} threads with {} messages from {}",
+                imported.thread_ids.len(),
+                imported.messages,
+                query.format
+            );
+            Ok((StatusCode::CREATED, Json(imported)))
+        }
+        Err(e) => {
+            tracing::error!("Failed to import {}: {:?}", query.format, e);
+            Err(e.into())
+        }
+    }
+}
+
+pub async fn download_blob(
+    TenantSynx(synx): TenantSynx,
+    Path(hash): Path<String>,
+) -> Result<impl IntoResponse, ApiError> {
+    match synx.get_blob(&hash).await {
+        Ok((blob, data)) => {
+            let content_type = blob
+                .mime_type
+                .and_then(|mime_type| HeaderValue::from_str(&mime_type).ok())
+                .unwrap_or(HeaderValue::from_static("application/octet-stream"));
+            Ok(([(header::CONTENT_TYPE, content_type)], data))
+        }
+        Err(e) => {
+            tracing::error!("Failed to get blob {}: {:?}", hash, e);
+            Err(e.into())
         }
     }
 }
 
 pub async fn debug_database_state(
     State(synx): State<Synx>,
-) -> Result<Json<serde_json::Value>, StatusCode> {
+) -> Result<Json<serde_json::Value>, ApiError> {
     tracing::info!("Debugging database state");
     match synx.debug_state().await {
         Ok(state) => {
@@ -180,20 +481,246 @@ pub async fn debug_database_state(
         }
         Err(e) => {
             tracing::error!("Failed to retrieve database state: {:?}", e);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
+            Err(e.into())
         }
     }
 }
 
 pub async fn search_threads(
-    State(synx): State<Synx>,
+    TenantSynx(synx): TenantSynx,
     Json(search_request): Json<SearchRequest>,
-) -> Result<Json<Vec<Similarity>>, StatusCode> {
+) -> Result<Json<Vec<Similarity>>, ApiError> {
     match synx.search_threads(search_request).await {
         Ok(similarities) => Ok(Json(similarities)),
         Err(e) => {
             tracing::error!("Failed to search threads: {:?}", e);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
+            Err(e.into())
+        }
+    }
+}
+
+pub async fn search_messages(
+    TenantSynx(synx): TenantSynx,
+    Json(search_request): Json<MessageSearchRequest>,
+) -> Result<Json<Vec<MessageSimilarity>>, ApiError> {
+    match synx.search_messages(search_request).await {
+        Ok(similarities) => Ok(Json(similarities)),
+        Err(e) => {
+            tracing::error!("Failed to search messages: {:?}", e);
+            Err(e.into())
+        }
+    }
+}
+
+pub async fn search_thread_messages(
+    TenantSynx(synx): TenantSynx,
+    Path(thread_id): Path<Uuid>,
+    Json(mut search_request): Json<MessageSearchRequest>,
+) -> Result<Json<Vec<MessageSimilarity>>, ApiError> {
+    if let Err(e) = synx.get_thread(thread_id).await {
+        tracing::error!("Failed to get thread {}: {:?}", thread_id, e);
+        return Err(e.into());
+    }
+
+    search_request.thread_ids = Some(vec![thread_id]);
+    match synx.search_messages(search_request).await {
+        Ok(similarities) => Ok(Json(similarities)),
+        Err(e) => {
+            tracing::error!("Failed to search messages in thread {}: {:?}", thread_id, e);
+            Err(e.into())
+        }
+    }
+}
+
+/// Registers a webhook. The response is the only one to include its secret.
+pub async fn create_webhook(
+    TenantSynx(synx): TenantSynx,
+    Json(create_webhook): Json<CreateWebhook>,
+) -> Result<impl IntoResponse, ApiError> {
+    match synx.create_webhook(create_webhook).await {
+        Ok(webhook) => {
+            tracing::info!("Webhook {} created for {}", webhook.id(), webhook.url);
+            Ok((StatusCode::CREATED, Json(webhook)))
+        }
+        Err(e) => {
+            tracing::error!("Failed to create webhook: {:?}", e);
+            Err(e.into())
+        }
+    }
+}
+
+pub async fn list_webhooks(TenantSynx(synx): TenantSynx) -> Result<Json<Vec<Webhook>>, ApiError> {
+    match synx.list_webhooks().await {
+        Ok(webhooks) => Ok(Json(webhooks)),
+        Err(e) => {
+            tracing::error!("Failed to list webhooks: {:?}", e);
+            Err(e.into())
+        }
+    }
+}
+
+pub async fn get_webhook(
+    TenantSynx(synx): TenantSynx,
+    Path(webhook_id): Path<Uuid>,
+) -> Result<Json<Webhook>, ApiError> {
+    match synx.get_webhook(webhook_id).await {
+        Ok(webhook) => Ok(Json(webhook)),
+        Err(e) => {
+            tracing::error!("Failed to get webhook {}: {:?}", webhook_id, e);
+            Err(e.into())
+        }
+    }
+}
+
+pub async fn delete_webhook(
+    TenantSynx(synx): TenantSynx,
+    Path(webhook_id): Path<Uuid>,
+) -> Result<StatusCode, ApiError> {
+    match synx.delete_webhook(webhook_id).await {
+        Ok(_) => Ok(StatusCode::NO_CONTENT),
+        Err(e) => {
+            tracing::error!("Failed to delete webhook {}: {:?}", webhook_id, e);
+            Err(e.into())
+        }
+    }
+}
+
+pub async fn list_webhook_deliveries(
+    TenantSynx(synx): TenantSynx,
+    Path(webhook_id): Path<Uuid>,
+) -> Result<Json<Vec<WebhookDelivery>>, ApiError> {
+    match synx.list_webhook_deliveries(webhook_id).await {
+        Ok(deliveries) => Ok(Json(deliveries)),
+        Err(e) => {
+            tracing::error!(
+                "Failed to list deliveries of webhook {}: {:?}",
+                webhook_id,
+                e
+            );
+            Err(e.into())
+        }
+    }
+}
+
+/// Sends a test event to the webhook, or with `dry_run` returns the signed
+/// request it would receive. An unreachable webhook is reported in the body, not
+/// as an error.
+pub async fn test_webhook(
+    TenantSynx(synx): TenantSynx,
+    Path(webhook_id): Path<Uuid>,
+    Query(query): Query<TestWebhookRequest>,
+) -> Result<Json<WebhookTest>, ApiError> {
+    match synx.test_webhook(webhook_id, query.dry_run).await {
+        Ok(test) => Ok(Json(test)),
+        Err(e) => {
+            tracing::error!("Failed to test webhook {}: {:?}", webhook_id, e);
+            Err(e.into())
+        }
+    }
+}
+
+pub async fn create_tenant(
+    State(synx): State<Synx>,
+    Json(create_tenant): Json<CreateTenant>,
+) -> Result<impl IntoResponse, ApiError> {
+    match synx.create_tenant(create_tenant).await {
+        Ok(tenant) => {
+            tracing::info!("Tenant {} created as {:?}", tenant.id(), tenant.name);
+            Ok((StatusCode::CREATED, Json(tenant)))
+        }
+        Err(e) => {
+            tracing::error!("Failed to create tenant: {:?}", e);
+            Err(e.into())
+        }
+    }
+}
+
+pub async fn list_tenants(State(synx): State<Synx>) -> Result<Json<Vec<Tenant>>, ApiError> {
+    match synx.list_tenants().await {
+        Ok(tenants) => Ok(Json(tenants)),
+        Err(e) => {
+            tracing::error!("Failed to list tenants: {:?}", e);
+            Err(e.into())
+        }
+    }
+}
+
+pub async fn get_tenant(
+    State(synx): State<Synx>,
+    Path(tenant_id): Path<Uuid>,
+) -> Result<Json<Tenant>, ApiError> {
+    match synx.get_tenant(tenant_id).await {
+        Ok(tenant) => Ok(Json(tenant)),
+        Err(e) => {
+            tracing::error!("Failed to get tenant {}: {:?}", tenant_id, e);
+            Err(e.into())
+        }
+    }
+}
+
+/// Deletes a tenant with its keys and webhooks; 409 while it still owns threads.
+pub async fn delete_tenant(
+    State(synx): State<Synx>,
+    Path(tenant_id): Path<Uuid>,
+) -> Result<StatusCode, ApiError> {
+    match synx.delete_tenant(tenant_id).await {
+        Ok(_) => Ok(StatusCode::NO_CONTENT),
+        Err(e) => {
+            tracing::error!("Failed to delete tenant {}: {:?}", tenant_id, e);
+            Err(e.into())
+        }
+    }
+}
+
+/// Issues a key for the tenant. The response is the only one to include the key.
+pub async fn create_api_key(
+    State(synx): State<Synx>,
+    Path(tenant_id): Path<Uuid>,
+    Json(create_api_key): Json<CreateApiKey>,
+) -> Result<(StatusCode, Json<IssuedApiKey>), ApiError> {
+    match synx.create_api_key(tenant_id, create_api_key).await {
+        Ok(issued) => {
+            tracing::info!(
+                "API key {} issued for tenant {}",
+                issued.api_key.prefix,
+                tenant_id
+            );
+            Ok((StatusCode::CREATED, Json(issued)))
+        }
+        Err(e) => {
+            tracing::error!("Failed to issue API key for tenant {}: {:?}", tenant_id, e);
+            Err(e.into())
+        }
+    }
+}
+
+pub async fn list_api_keys(
+    State(synx): State<Synx>,
+    Path(tenant_id): Path<Uuid>,
+) -> Result<Json<Vec<ApiKey>>, ApiError> {
+    match synx.list_api_keys(tenant_id).await {
+        Ok(api_keys) => Ok(Json(api_keys)),
+        Err(e) => {
+            tracing::error!("Failed to list API keys of tenant {}: {:?}", tenant_id, e);
+            Err(e.into())
+        }
+    }
+}
+
+pub async fn delete_api_key(
+    State(synx): State<Synx>,
+    Path((tenant_id, key_id)): Path<(Uuid, Uuid)>,
+) -> Result<StatusCode, ApiError> {
+    match synx.delete_api_key(tenant_id, key_id).await {
+        Ok(_) => Ok(StatusCode::NO_CONTENT),
+        Err(e) => {
+            tracing::error!(
+                "Failed to delete API key {} of tenant {}: {:?}",
+                key_id,
+                tenant_id,
+                e
+            );
+            Err(e.into())
         }
     }
 }
@@ -1,29 +1,108 @@
 use axum::{
+    extract::DefaultBodyLimit,
+    middleware,
     routing::{delete, get, post, put},
     Router,
 };
 use synx::Synx;
+use synx_domain::api_key::Scope;
 
-use crate::api::handlers;
+use crate::api::{auth, handlers};
 
-pub fn router(synx: Synx) -> Router {
-    Router::new()
-        .route("/threads", post(handlers::create_thread))
+/// Exports hold years of conversations, well past the default body limit.
+const IMPORT_BODY_LIMIT: usize = 512 * 1024 * 1024;
+
+/// Routes of `router` only reachable with `scope`.
+fn scoped(scope: Scope, router: Router<Synx>) -> Router<Synx> {
+    router.route_layer(middleware::from_fn_with_state(scope, auth::require_scope))
+}
+
+/// Every route requires an API key with the scope it is grouped under;
+/// `admin_key` is the server's own key.
+pub fn router(synx: Synx, admin_key: &str) -> Router {
+    let read = Router::new()
         .route("/threads", get(handlers::list_threads))
         .route("/threads/:id", get(handlers::get_thread))
-        .route("/threads/:id", delete(handlers::delete_thread))
+        .route("/threads/:id/jobs", get(handlers::get_thread_jobs))
+        .route("/threads/:id/events", get(handlers::thread_events))
+        .route("/threads/:id/branch", get(handlers::get_branch))
+        .route("/threads/:id/context", get(handlers::get_context))
+        .route("/threads/:id/export", get(handlers::export_thread))
+        .route("/threads/:id/transcript", get(handlers::get_transcript))
+        .route("/threads/:id/messages", get(handlers::get_messages))
+        .route(
+            "/threads/:thread_id/messages/:message_id/revisions",
+            get(handlers::list_message_revisions),
+        )
+        .route(
+            "/threads/:thread_id/messages/:message_id/revisions/:revision",
+            get(handlers::get_message_revision),
+        )
+        .route("/blobs/:hash", get(handlers::download_blob))
+        .route("/transcripts", post(handlers::archive_transcripts))
+        .route("/webhooks", get(handlers::list_webhooks))
+        .route("/webhooks/:id", get(handlers::get_webhook))
+        .route(
+            "/webhooks/:id/deliveries",
+            get(handlers::list_webhook_deliveries),
+        );
+
+    let write = Router::new()
+        .route("/threads", post(handlers::create_thread))
         .route("/threads/:id", put(handlers::update_thread))
+        .route("/threads/:id/fork", post(handlers::fork_thread))
         .route("/threads/:id/messages", post(handlers::create_message))
-        .route("/threads/:id/messages", get(handlers::get_messages))
         .route(
             "/threads/:thread_id/messages/:message_id",
             put(handlers::update_message),
         )
+        .route("/blobs", post(handlers::upload_blob))
+        .route(
+            "/import",
+            post(handlers::import).layer(DefaultBodyLimit::max(IMPORT_BODY_LIMIT)),
+        )
+        .route("/webhooks", post(handlers::create_webhook))
+        .route("/webhooks/:id/test", post(handlers::test_webhook));
+
+    let search = Router::new()
+        .route(
+            "/threads/:id/messages/search",
+            post(handlers::search_thread_messages),
+        )
+        .route("/search", post(handlers::search_threads))
+        .route("/messages/search", post(handlers::search_messages));
+
+    let admin = Router::new()
+        .route("/threads/:id", delete(handlers::delete_thread))
         .route(
             "/threads/:thread_id/messages/:message_id",
             delete(handlers::delete_message),
         )
-        .route("/search", post(handlers::search_threads))
+        .route("/webhooks/:id", delete(handlers::delete_webhook));
+
+    let operator = Router::new()
+        .route("/admin/tenants", post(handlers::create_tenant))
+        .route("/admin/tenants", get(handlers::list_tenants))
+        .route("/admin/tenants/:id", get(handlers::get_tenant))
+        .route("/admin/tenants/:id", delete(handlers::delete_tenant))
+        .route("/admin/tenants/:id/keys", post(handlers::create_api_key))
+        .route("/admin/tenants/:id/keys", get(handlers::list_api_keys))
+        .route(
+            "/admin/tenants/:tenant_id/keys/:key_id",
+            delete(handlers::delete_api_key),
+        )
         .route("/debug/database", get(handlers::debug_database_state))
+        .route_layer(middleware::from_fn(auth::require_operator));
+
+    Router::new()
+        .merge(scoped(Scope::ThreadsRead, read))
+        .merge(scoped(Scope::ThreadsWrite, write))
+        .merge(scoped(Scope::Search, search))
+        .merge(scoped(Scope::Admin, admin))
+        .merge(operator)
+        .route_layer(middleware::from_fn_with_state(
+            auth::Auth::new(synx.clone(), admin_key),
+            auth::authenticate,
+        ))
         .with_state(synx)
 }
@@ -2,18 +2,24 @@ mod api;
 
 use std::{future::Future, path::PathBuf, pin::Pin, sync::Arc};
 
-use anyhow::Result;
-use axum::{middleware, routing::get};
-use axum_auth_api_key::auth_middleware;
+use anyhow::{bail, Context, Result};
+use axum::routing::get;
 use clap::{Parser, Subcommand};
 use ferrochain_anthropic_completion::{AnthropicCompletion, Model};
 use ferrochain_voyageai_embedder::{EmbeddingInputType, EmbeddingModel, VoyageAiEmbedder};
-use synx::{executor::Executor, Synx};
+use synx::{executor::Executor, import::ImportFormat, tenants::issue_api_key, Synx};
+use synx_database::Db;
+use synx_domain::{
+    api_key::{CreateApiKey, Scope},
+    tenant::Tenant,
+};
 use synx_heed_database::{heed::EnvOpenOptions, SynxHeedDatabase};
 use synx_in_memory_database::SynxInMemory;
+use synx_sqlite_database::SynxSqliteDatabase;
 use tokio::net::TcpListener;
 use tower_http::trace::TraceLayer;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
+use uuid::Uuid;
 
 struct TokioExecutor;
 
@@ -29,10 +35,75 @@ struct Cli {
     host: String,
     #[clap(long, default_value = "3000")]
     port: u16,
+    /// Required to serve the API. Acts as an admin key of the default tenant.
     #[clap(long, env = "SYNX_API_KEY")]
-    api_key: String,
+    api_key: Option<String>,
+    /// Message roles accepted besides user, assistant, system and tool.
+    #[clap(long = "custom-role", env = "SYNX_CUSTOM_ROLES", value_delimiter = ',')]
+    custom_roles: Vec<String>,
+    /// Hosts webhooks may reach although they are on a private, loopback or
+    /// link-local address.
+    #[clap(
+        long = "webhook-allowed-host",
+        env = "SYNX_WEBHOOK_ALLOWED_HOSTS",
+        value_delimiter = ','
+    )]
+    webhook_allowed_hosts: Vec<String>,
     #[clap(subcommand)]
-    database: Database,
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Loads conversations from an export file into the database and exits.
+    Import {
+        file: PathBuf,
+        /// `chatgpt` for a ChatGPT `conversations.json`, `jsonl` for one thread per line.
+        #[clap(long)]
+        format: ImportFormat,
+        /// Queue a summary of every imported thread, generated on the next start.
+        #[clap(long, default_value = "false")]
+        summarize: bool,
+        /// Tenant owning the imported threads, the default tenant when omitted.
+        #[clap(long)]
+        tenant: Option<Uuid>,
+        #[clap(subcommand)]
+        database: Database,
+    },
+    /// Issues or revokes an API key and exits.
+    Keys {
+        #[clap(subcommand)]
+        command: Keys,
+    },
+    /// Any database subcommand on its own serves the API.
+    #[clap(flatten)]
+    Serve(Database),
+}
+
+#[derive(Subcommand)]
+enum Keys {
+    /// Prints the new key with its record; the key cannot be shown again.
+    Issue {
+        /// Tenant the key acts for, the default tenant when omitted.
+        #[clap(long)]
+        tenant: Option<Uuid>,
+        #[clap(long)]
+        name: Option<String>,
+        /// `threads:read`, `threads:write`, `search` or `admin`; repeat or comma separate.
+        #[clap(long = "scope", value_delimiter = ',', required = true)]
+        scopes: Vec<Scope>,
+        #[clap(subcommand)]
+        database: Database,
+    },
+    /// Removes a key, refused by the API from then on.
+    Revoke {
+        key_id: Uuid,
+        /// Tenant the key was issued for, the default tenant when omitted.
+        #[clap(long)]
+        tenant: Option<Uuid>,
+        #[clap(subcommand)]
+        database: Database,
+    },
 }
 
 #[derive(Default, Subcommand)]
@@ -43,6 +114,10 @@ enum Database {
         #[clap(long, default_value = "false")]
         regenerate: bool,
     },
+    Sqlite {
+        #[clap(long)]
+        path: PathBuf,
+    },
     #[default]
     InMemory,
 }
@@ -64,28 +139,14 @@ async fn main() -> Result<()> {
 
     let cli = Cli::parse();
 
+    let database = match &cli.command {
+        Command::Import { database, .. } | Command::Serve(database) => database,
+        // Keys only live in the database, so nothing else is set up for them.
+        Command::Keys { command } => return manage_keys(command).await,
+    };
+
     let synx = Synx::builder()
-        .with_db({
-            match cli.database {
-                Database::Heed { path, regenerate } => {
-                    tokio::fs::create_dir_all(&path).await?;
-                    if regenerate {
-                        tokio::fs::remove_dir_all(&path).await?;
-                        tokio::fs::create_dir_all(&path).await?;
-                    }
-
-                    let env = unsafe {
-                        EnvOpenOptions::new()
-                            .map_size(10 * 1024 * 1024 * 1024) // 10 GB
-                            .max_dbs(6)
-                            .open(path)?
-                    };
-
-                    Arc::new(SynxHeedDatabase::new(Arc::new(env), true)?)
-                }
-                Database::InMemory => Arc::new(SynxInMemory::new()),
-            }
-        })
+        .with_db(open_database(database).await?)
         .with_document_embedder(Arc::new(
             VoyageAiEmbedder::builder()
                 .model(EmbeddingModel::Voyage3)
@@ -113,17 +174,52 @@ async fn main() -> Result<()> {
                 .build()?,
         ))
         .with_executor(Arc::new(TokioExecutor))
+        .with_custom_roles(cli.custom_roles)
+        .with_webhook_allowed_hosts(cli.webhook_allowed_hosts)
         .build();
 
+    match cli.command {
+        Command::Import {
+            file,
+            format,
+            summarize,
+            tenant,
+            ..
+        } => {
+            let synx = match tenant {
+                Some(tenant_id) => {
+                    synx.get_tenant(tenant_id)
+                        .await
+                        .with_context(|| format!("no tenant {}", tenant_id))?;
+                    synx.for_tenant(tenant_id)
+                }
+                None => synx,
+            };
+            let data = tokio::fs::read(&file).await?;
+            let imported = synx.import(format, &data, summarize).await?;
+            tracing::info!(
+                "Imported {} threads with {} messages from {}",
+                imported.thread_ids.len(),
+                imported.messages,
+                file.display()
+            );
+            return Ok(());
+        }
+        Command::Keys { .. } | Command::Serve(_) => {}
+    }
+
+    let api_key = cli
+        .api_key
+        .context("an API key is required to serve, pass --api-key or set SYNX_API_KEY")?;
+
+    synx.resume_jobs().await?;
+    synx.collect_blobs();
+
     let listener = TcpListener::bind((cli.host, cli.port)).await?;
     tracing::debug!("listening on {}", listener.local_addr()?);
     axum::serve(
         listener,
-        api::routes::router(synx)
-            .route_layer(middleware::from_fn_with_state(
-                cli.api_key.into(),
-                auth_middleware,
-            ))
+        api::routes::router(synx, &api_key)
             .route("/healthz", get(api::handlers::healthz))
             .layer(TraceLayer::new_for_http()),
     )
@@ -131,3 +227,66 @@ async fn main() -> Result<()> {
 
     Ok(())
 }
+
+async fn manage_keys(command: &Keys) -> Result<()> {
+    let database = match command {
+        Keys::Issue { database, .. } | Keys::Revoke { database, .. } => database,
+    };
+    if matches!(database, Database::InMemory) {
+        bail!("keys are kept in the database, pass heed or sqlite to manage them");
+    }
+    let db = open_database(database).await?;
+
+    match command {
+        Keys::Issue {
+            tenant,
+            name,
+            scopes,
+            ..
+        } => {
+            let tenant_id = tenant.unwrap_or(Tenant::DEFAULT_ID);
+            let issued = issue_api_key(
+                db.as_ref(),
+                tenant_id,
+                CreateApiKey {
+                    name: name.clone(),
+                    scopes: scopes.clone(),
+                },
+            )
+            .await
+            .with_context(|| format!("failed to issue a key for tenant {}", tenant_id))?;
+            println!("{}", serde_json::to_string_pretty(&issued)?);
+        }
+        Keys::Revoke { key_id, tenant, .. } => {
+            let tenant_id = tenant.unwrap_or(Tenant::DEFAULT_ID);
+            db.delete_api_key(tenant_id, *key_id)
+                .await
+                .with_context(|| format!("no key {} for tenant {}", key_id, tenant_id))?;
+            tracing::info!("Revoked API key {}", key_id);
+        }
+    }
+    Ok(())
+}
+
+async fn open_database(database: &Database) -> Result<Arc<dyn Db>> {
+    Ok(match database {
+        Database::Heed { path, regenerate } => {
+            tokio::fs::create_dir_all(path).await?;
+            if *regenerate {
+                tokio::fs::remove_dir_all(path).await?;
+                tokio::fs::create_dir_all(path).await?;
+            }
+
+            let env = unsafe {
+                EnvOpenOptions::new()
+                    .map_size(10 * 1024 * 1024 * 1024) // 10 GB
+                    .max_dbs(32)
+                    .open(path)?
+            };
+
+            Arc::new(SynxHeedDatabase::new(Arc::new(env), true)?)
+        }
+        Database::Sqlite { path } => Arc::new(SynxSqliteDatabase::open(path)?),
+        Database::InMemory => Arc::new(SynxInMemory::new()),
+    })
+}